@@ -0,0 +1,12 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/usertenants.proto");
+
+        let file_descriptors = protox::compile(["usertenants.proto"], ["proto"])
+            .expect("Unable to parse proto/usertenants.proto");
+        tonic_prost_build::configure()
+            .compile_fds(file_descriptors)
+            .expect("Unable to generate gRPC code");
+    }
+}
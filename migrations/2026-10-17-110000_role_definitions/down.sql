@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE roles ADD COLUMN role_type TEXT;
+
+UPDATE roles SET role_type = role_definitions.role_type
+    FROM role_definitions
+    WHERE role_definitions.id = roles.role_definition_id;
+
+ALTER TABLE roles ALTER COLUMN role_type SET NOT NULL;
+ALTER TABLE roles DROP COLUMN role_definition_id;
+
+DROP TABLE permissions;
+DROP TABLE role_definitions;
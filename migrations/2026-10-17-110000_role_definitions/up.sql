@@ -0,0 +1,36 @@
+-- Your SQL goes here
+
+CREATE TABLE "role_definitions" (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name TEXT NOT NULL,
+    role_type TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id UUID references tenants(id) ON DELETE CASCADE,
+    UNIQUE (db_tenant_id, name)
+);
+
+CREATE TABLE "permissions" (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    role_definition_id UUID NOT NULL references role_definitions(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    UNIQUE (role_definition_id, name)
+);
+
+-- Built-in definitions for existing tenants
+INSERT INTO role_definitions (name, role_type, db_tenant_id)
+    SELECT built_in.name, built_in.name, tenants.id
+    FROM tenants CROSS JOIN (VALUES ('Administrator'), ('User')) AS built_in(name);
+
+INSERT INTO permissions (role_definition_id, name)
+    SELECT id, '*' FROM role_definitions WHERE name = 'Administrator';
+
+ALTER TABLE roles ADD COLUMN role_definition_id UUID references role_definitions(id);
+
+UPDATE roles SET role_definition_id = role_definitions.id
+    FROM role_definitions
+    WHERE role_definitions.db_tenant_id = roles.db_tenant_id AND role_definitions.name = roles.role_type;
+
+ALTER TABLE roles ALTER COLUMN role_definition_id SET NOT NULL;
+ALTER TABLE roles DROP COLUMN role_type;
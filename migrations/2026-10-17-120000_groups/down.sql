@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM roles WHERE group_id IS NOT NULL;
+ALTER TABLE roles DROP COLUMN group_id;
+
+DROP TABLE group_members;
+DROP TABLE groups;
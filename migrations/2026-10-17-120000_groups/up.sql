@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+CREATE TABLE "groups" (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id UUID references tenants(id) ON DELETE CASCADE,
+    UNIQUE (db_tenant_id, name)
+);
+
+CREATE TABLE "group_members" (
+    group_id UUID NOT NULL references groups(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL references users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE INDEX group_members_user_id_idx ON "group_members" (user_id);
+
+ALTER TABLE roles ADD COLUMN group_id UUID references groups(id) ON DELETE CASCADE;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+CREATE TABLE "invitations" (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    email TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    expires_at TIMESTAMP NOT NULL,
+    accepted_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    db_tenant_id UUID references tenants(id) ON DELETE CASCADE
+);
+
+CREATE INDEX invitations_db_tenant_id_idx ON "invitations" (db_tenant_id);
+
+CREATE TABLE "invitation_roles" (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    invitation_id UUID NOT NULL references invitations(id) ON DELETE CASCADE,
+    role_definition_id UUID NOT NULL references role_definitions(id) ON DELETE CASCADE,
+    application_id UUID references applications(id) ON DELETE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE "user_tokens" (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL references users(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    db_tenant_id UUID references tenants(id) ON DELETE CASCADE
+);
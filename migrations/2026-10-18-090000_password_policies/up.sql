@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+CREATE TABLE "password_policies" (
+    db_tenant_id UUID PRIMARY KEY references tenants(id) ON DELETE CASCADE,
+    policy TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP
+);
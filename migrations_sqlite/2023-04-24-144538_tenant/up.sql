@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+CREATE TABLE "tenants" (
+    id TEXT PRIMARY KEY NOT NULL,
+    title TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP
+);
+
+
+CREATE TABLE "users" (
+    id TEXT PRIMARY KEY NOT NULL,
+    email TEXT UNIQUE NOT NULL,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    password TEXT NOT NULL,
+    encryption_mode TEXT NOT NULL,
+    full_name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
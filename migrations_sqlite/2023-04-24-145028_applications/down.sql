@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+
+
+DROP TABLE applications;
+
+DROP TABLE storages;
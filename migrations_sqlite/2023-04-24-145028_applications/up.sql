@@ -0,0 +1,23 @@
+-- Your SQL goes here
+
+CREATE TABLE "storages" (
+    id TEXT PRIMARY KEY NOT NULL,
+    storage_type TEXT NOT NULL,
+    path TEXT NULL,
+    connection_string TEXT NULL,
+    schema TEXT NULL,
+    table_prefix TEXT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
+
+
+CREATE TABLE "applications" (
+    id TEXT PRIMARY KEY NOT NULL,
+    application_type TEXT NOT NULL,
+    storage_id TEXT references storages(id),
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+CREATE TABLE "roles" (
+    id TEXT PRIMARY KEY NOT NULL,
+    role_type TEXT NOT NULL,
+    user_id TEXT references users(id),
+    application_id TEXT references applications(id),
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
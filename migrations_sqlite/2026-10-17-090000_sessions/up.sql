@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE "sessions" (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL references users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    expires_at TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    revoked_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id) ON DELETE CASCADE
+);
@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+
+CREATE TABLE "roles_old" (
+    id TEXT PRIMARY KEY NOT NULL,
+    role_type TEXT NOT NULL,
+    user_id TEXT references users(id),
+    application_id TEXT references applications(id),
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
+
+INSERT INTO roles_old (id, role_type, user_id, application_id, created_at, updated_at, db_tenant_id)
+    SELECT roles.id, role_definitions.role_type, roles.user_id, roles.application_id, roles.created_at, roles.updated_at, roles.db_tenant_id
+    FROM roles JOIN role_definitions ON role_definitions.id = roles.role_definition_id;
+
+DROP TABLE roles;
+ALTER TABLE roles_old RENAME TO roles;
+
+DROP TABLE permissions;
+DROP TABLE role_definitions;
@@ -0,0 +1,51 @@
+-- Your SQL goes here
+
+CREATE TABLE "role_definitions" (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    role_type TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id) ON DELETE CASCADE,
+    UNIQUE (db_tenant_id, name)
+);
+
+CREATE TABLE "permissions" (
+    id TEXT PRIMARY KEY NOT NULL,
+    role_definition_id TEXT NOT NULL references role_definitions(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    UNIQUE (role_definition_id, name)
+);
+
+-- Built-in definitions for existing tenants, with random version 4 UUIDs
+INSERT INTO role_definitions (id, name, role_type, db_tenant_id)
+    SELECT lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-'
+            || substr('89ab', 1 + (abs(random()) % 4), 1) || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6))),
+        built_in.name, built_in.name, tenants.id
+    FROM tenants CROSS JOIN (SELECT 'Administrator' AS name UNION ALL SELECT 'User') AS built_in;
+
+INSERT INTO permissions (id, role_definition_id, name)
+    SELECT lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2) || '-'
+            || substr('89ab', 1 + (abs(random()) % 4), 1) || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6))),
+        id, '*'
+    FROM role_definitions WHERE name = 'Administrator';
+
+-- SQLite cannot add a NOT NULL reference to an existing table, so roles is rebuilt
+CREATE TABLE "roles_new" (
+    id TEXT PRIMARY KEY NOT NULL,
+    role_definition_id TEXT NOT NULL references role_definitions(id),
+    user_id TEXT references users(id),
+    application_id TEXT references applications(id),
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
+
+INSERT INTO roles_new (id, role_definition_id, user_id, application_id, created_at, updated_at, db_tenant_id)
+    SELECT roles.id, role_definitions.id, roles.user_id, roles.application_id, roles.created_at, roles.updated_at, roles.db_tenant_id
+    FROM roles JOIN role_definitions
+        ON role_definitions.db_tenant_id = roles.db_tenant_id AND role_definitions.name = roles.role_type;
+
+DROP TABLE roles;
+ALTER TABLE roles_new RENAME TO roles;
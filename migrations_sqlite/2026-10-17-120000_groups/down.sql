@@ -0,0 +1,22 @@
+-- This file should undo anything in `up.sql`
+
+-- SQLite cannot drop a column with a foreign key, so roles is rebuilt without group roles
+CREATE TABLE "roles_old" (
+    id TEXT PRIMARY KEY NOT NULL,
+    role_definition_id TEXT NOT NULL references role_definitions(id),
+    user_id TEXT references users(id),
+    application_id TEXT references applications(id),
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id)
+);
+
+INSERT INTO roles_old (id, role_definition_id, user_id, application_id, created_at, updated_at, db_tenant_id)
+    SELECT id, role_definition_id, user_id, application_id, created_at, updated_at, db_tenant_id
+    FROM roles WHERE group_id IS NULL;
+
+DROP TABLE roles;
+ALTER TABLE roles_old RENAME TO roles;
+
+DROP TABLE group_members;
+DROP TABLE groups;
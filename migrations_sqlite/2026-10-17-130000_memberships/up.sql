@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TABLE "memberships" (
+    user_id TEXT NOT NULL references users(id) ON DELETE CASCADE,
+    db_tenant_id TEXT NOT NULL references tenants(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (user_id, db_tenant_id)
+);
+
+CREATE INDEX memberships_db_tenant_id_idx ON "memberships" (db_tenant_id);
+
+-- Every existing user is a member of the tenant they were created in
+INSERT INTO memberships (user_id, db_tenant_id, created_at)
+    SELECT id, db_tenant_id, created_at FROM users WHERE db_tenant_id IS NOT NULL;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE "user_tokens" (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL references users(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    db_tenant_id TEXT references tenants(id) ON DELETE CASCADE
+);
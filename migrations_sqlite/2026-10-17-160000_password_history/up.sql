@@ -0,0 +1,11 @@
+-- Your SQL goes here
+
+CREATE TABLE "password_history" (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL references users(id) ON DELETE CASCADE,
+    password TEXT NOT NULL,
+    encryption_mode TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX password_history_user_id_idx ON "password_history" (user_id, created_at);
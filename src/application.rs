@@ -18,12 +18,12 @@ pub struct Application {
 impl From<&DbApplication> for Application {
     fn from(value: &DbApplication) -> Self {
         Application {
-            id: value.id,
+            id: value.id.0,
             application_type: ApplicationType::from_str(&value.application_type).unwrap(),
-            storage_id: value.storage_id,
+            storage_id: value.storage_id.map(uuid::Uuid::from),
             created_at: value.created_at,
             updated_at: value.updated_at,
-            db_tenant_id: value.db_tenant_id
+            db_tenant_id: value.db_tenant_id.map(uuid::Uuid::from)
         }
     }
 }
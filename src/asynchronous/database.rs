@@ -0,0 +1,41 @@
+use diesel::{Connection, PgConnection};
+use diesel_async::AsyncPgConnection;
+use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+use diesel_async::pooled_connection::deadpool::{Object, PoolError};
+use log::info;
+
+use crate::diesel_migrations::MigrationHarness;
+use crate::postgresql::database::MIGRATIONS;
+
+/// Async connection pool type backing a [`crate::AsyncTenet`] instance.
+pub type Pool = diesel_async::pooled_connection::deadpool::Pool<AsyncPgConnection>;
+pub type DbConnection = Object<AsyncPgConnection>;
+
+/// Builds a new async connection pool for the given connection string and runs
+/// any pending migrations against it.
+///
+/// Migrations are run on a blocking thread with a synchronous connection, as
+/// diesel's migration harness is synchronous.
+pub async fn build_pool(connection_string: &str) -> Pool {
+    info!("Initializing Tenet Database");
+    info!("Tenet connection string: {}", connection_string);
+
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(connection_string);
+    let pool = Pool::builder(manager).build().expect("Failed to create db pool");
+
+    info!("Running pending database migrations...");
+    let database_url = connection_string.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut connection = PgConnection::establish(&database_url).expect("Failed to get db connection");
+        connection.run_pending_migrations(MIGRATIONS).expect("Unable to run migrations");
+    })
+    .await
+    .expect("Unable to run migrations");
+    info!("Database migrations completed");
+
+    pool
+}
+
+pub async fn connection(pool: &Pool) -> Result<DbConnection, PoolError> {
+    pool.get().await
+}
@@ -0,0 +1,280 @@
+pub mod database;
+pub mod repository;
+pub mod tenant;
+
+use log::info;
+use uuid::Uuid;
+
+use crate::breached_passwords::SharedBreachedPasswords;
+use crate::{encryption_modes::PasswordHashConfig, BreachedPasswords, PasswordPolicy, RoleDefinition, TenetError, DEFAULT_DATABASE_URL};
+use repository::AsyncPostgresRepository;
+use tenant::AsyncTenant;
+
+
+/// Async counterpart of [`crate::Tenet`].
+///
+/// All database access goes through an async PostgreSQL connection pool, and
+/// password hashing runs on tokio's blocking thread pool, so `AsyncTenet` can
+/// be used from async services without wrapping each call in `spawn_blocking`.
+/// The semantics of every method match its counterpart on `Tenet`.
+///
+/// Only available with the `async` cargo feature.
+#[derive(Debug, Clone)]
+pub struct AsyncTenet {
+    repository: AsyncPostgresRepository,
+    password_hash_config: PasswordHashConfig,
+    password_policy: PasswordPolicy,
+    breached_passwords: SharedBreachedPasswords
+}
+
+
+impl AsyncTenet {
+    /// Creates a new AsyncTenet instance with the specified database connection.
+    ///
+    /// Builds a fresh, independent connection pool for the given connection
+    /// string and runs any pending migrations against it.
+    ///
+    /// # Parameters
+    ///
+    /// * `connection_string` - The connection string for the database.
+    ///   If empty, the default URL will be used.
+    pub async fn new(connection_string: String) -> Self {
+        let database_url = if connection_string.is_empty() {
+            info!("Database url not set, using default ConnectionString");
+            DEFAULT_DATABASE_URL.to_string()
+        } else {
+            connection_string
+        };
+
+        AsyncTenet {
+            repository: AsyncPostgresRepository::new(&database_url).await,
+            password_hash_config: PasswordHashConfig::default(),
+            password_policy: PasswordPolicy::default(),
+            breached_passwords: SharedBreachedPasswords::default()
+        }
+    }
+
+    /// Sets the Argon2id parameters new passwords are hashed with, see
+    /// [`crate::Tenet::with_password_hash_config`].
+    ///
+    /// Tenants obtained before this call keep the previous parameters.
+    pub fn with_password_hash_config(mut self, password_hash_config: PasswordHashConfig) -> Self {
+        self.password_hash_config = password_hash_config;
+        self
+    }
+
+    /// Sets the password policy of all tenants without a policy of their own,
+    /// see [`crate::Tenet::with_password_policy`].
+    ///
+    /// Tenants obtained before this call keep the previous policy.
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = password_policy;
+        self
+    }
+
+    /// Replaces the breach corpus new passwords are checked against, see
+    /// [`crate::Tenet::set_breached_passwords`].
+    pub fn set_breached_passwords(&self, breached_passwords: Option<BreachedPasswords>) {
+        self.breached_passwords.set(breached_passwords);
+    }
+
+    fn connect(&self, tenant: crate::Tenant) -> AsyncTenant {
+        AsyncTenant::new(tenant, self.repository.clone(), self.password_hash_config.clone(), self.password_policy.clone(), self.breached_passwords.clone())
+    }
+
+    /// Returns a list of all tenant IDs.
+    pub async fn get_tenant_ids(&self) -> Vec<Uuid> {
+        if let Ok(tenants) = self.repository.find_all_tenants().await {
+            return tenants.iter().map(|t| t.id).collect();
+        }
+        Vec::new()
+    }
+
+    /// Finds a tenant ID for a user by their username.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError::NotFoundError` if the user or tenant is not found.
+    pub async fn get_tenant_id_by_username(&self, username: String) -> Result<Uuid, TenetError> {
+        self.get_tenant_by_username(username).await.map(|tenant| tenant.id)
+    }
+
+    /// Finds the tenant a user was created in by their username.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError::NotFoundError` if the user or tenant is not found.
+    pub async fn get_tenant_by_username(&self, username: String) -> Result<AsyncTenant, TenetError> {
+        let user = self.repository.find_user_by_email(username).await.map_err(|_| TenetError::NotFoundError)?;
+        let tenant_id = user.db_tenant_id.ok_or(TenetError::NotFoundError)?;
+        if let Ok(tenant) = self.repository.find_tenant(tenant_id).await {
+            return Ok(self.connect(tenant));
+        }
+
+        Err(TenetError::NotFoundError)
+    }
+
+    /// Returns all tenants the user is a member of, ordered by title.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError` if the tenants cannot be loaded.
+    pub async fn get_tenants_for_user(&self, user_id: Uuid) -> Result<Vec<AsyncTenant>, TenetError> {
+        let tenants = self.repository.find_tenants_by_user(user_id).await?;
+        Ok(tenants.into_iter().map(|t| self.connect(t)).collect())
+    }
+
+    /// Finds a tenant by its ID.
+    pub async fn get_tenant_by_id(&self, tenant_id: Uuid) -> Option<AsyncTenant> {
+        if let Ok(tenant) = self.repository.find_tenant(tenant_id).await {
+            return Some(self.connect(tenant));
+        }
+        None
+    }
+
+    /// Updates the title of a tenant.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError` if the update fails.
+    pub async fn set_tenant_title(&self, tenant_id: Uuid, title: String) -> Result<AsyncTenant, TenetError> {
+        let updated_tenant = self.repository.update_tenant(tenant_id, title).await?;
+
+        Ok(self.connect(updated_tenant))
+    }
+
+    /// Creates a new tenant, seeded with the built-in role definitions.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError` if the creation fails.
+    pub async fn create_tenant(&self, title: String) -> Result<AsyncTenant, TenetError> {
+        let tenant = crate::Tenant::new(title);
+        let created_tenant = self.repository.create_tenant(&tenant, &RoleDefinition::defaults(tenant.id)).await?;
+
+        Ok(self.connect(created_tenant))
+    }
+
+    /// Deletes a tenant by its ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError` if the deletion fails.
+    pub async fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        self.repository.delete_tenant(tenant_id).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use testcontainers_modules::postgres::Postgres;
+    use testcontainers_modules::testcontainers::runners::SyncRunner;
+
+    use crate::{application_type::ApplicationType, role_type::RoleType};
+    use crate::{Application, Role, Storage};
+    use crate::test_suite::user;
+
+    use super::*;
+
+    fn test_harness<F: Future<Output = ()>>(test_code: impl Fn(String) -> F) {
+        let node = Postgres::default().start().expect("Unable to start container");
+
+        let host = node.get_host().unwrap();
+        let port = node.get_host_port_ipv4(5432).unwrap();
+        let connection_string = format!("postgres://postgres:postgres@{}:{}/postgres", host, port);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(test_code(connection_string));
+
+        node.stop().expect("Failed to stop postgres container");
+    }
+
+    #[test]
+    fn create_tenant_test() {
+        test_harness(|connection_string| async move {
+            let tenet = AsyncTenet::new(connection_string).await;
+
+            let tenant = tenet.create_tenant("SomeTenantTitle".to_string()).await.unwrap();
+            assert_eq!(vec![tenant.id], tenet.get_tenant_ids().await);
+
+            let updated_tenant = tenet.set_tenant_title(tenant.id, "Updated Title".to_string()).await.unwrap();
+            assert_eq!("Updated Title", updated_tenant.title);
+            assert_eq!("Updated Title", tenet.get_tenant_by_id(tenant.id).await.unwrap().title);
+
+            tenet.delete_tenant(tenant.id).await.unwrap();
+            assert!(tenet.get_tenant_by_id(tenant.id).await.is_none());
+        });
+    }
+
+    #[test]
+    fn user_and_roles_test() {
+        test_harness(|connection_string| async move {
+            let tenet = AsyncTenet::new(connection_string).await;
+            let tenant = tenet.create_tenant("TenantTitle".to_string()).await.unwrap();
+
+            let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).await.unwrap();
+            let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).await.unwrap();
+
+            let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).await.unwrap();
+            assert_ne!("password", created_user.password);
+
+            assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).await.is_some());
+            assert!(tenant.authenticate_user("someone@something.de".to_string(), "wrong".to_string()).await.is_none());
+            assert_eq!(tenant.id, tenet.get_tenant_id_by_username("someone@something.de".to_string()).await.unwrap());
+
+            let role = Role::new(RoleType::Administrator, created_user.id, application.id, tenant.id);
+            tenant.add_role(&role).await.unwrap();
+
+            let roles = tenant.get_roles_for_user(created_user.id).await.unwrap();
+            assert_eq!(1, roles.len());
+            assert_eq!(RoleType::Administrator, roles[0].role_type);
+        });
+    }
+
+    #[test]
+    fn membership_test() {
+        test_harness(|connection_string| async move {
+            let tenet = AsyncTenet::new(connection_string).await;
+            let home = tenet.create_tenant("Home".to_string()).await.unwrap();
+            let customer = tenet.create_tenant("Customer".to_string()).await.unwrap();
+            let storage = customer.add_storage(&Storage::new_json_file("some_path", customer.id)).await.unwrap();
+            let application = customer.add_application(&Application::new(ApplicationType::Shop, storage.id, customer.id)).await.unwrap();
+
+            let consultant = home.add_user(&user("consultant@something.de", home.id)).await.unwrap();
+            let role = Role::new(RoleType::User, consultant.id, application.id, customer.id);
+            assert!(customer.add_role(&role).await.unwrap_err().is_not_found());
+            customer.add_member(consultant.id).await.unwrap();
+            customer.add_role(&role).await.unwrap();
+            assert_eq!(2, tenet.get_tenants_for_user(consultant.id).await.unwrap().len());
+
+            // Deleting the user in one of their tenants only ends the membership there
+            customer.delete_user(consultant.id).await.unwrap();
+            assert!(customer.get_user_by_id(consultant.id).await.is_err());
+            assert!(customer.get_roles().await.is_empty());
+            assert!(home.get_user_by_id(consultant.id).await.is_ok());
+
+            home.delete_user(consultant.id).await.unwrap();
+            assert!(home.get_user_by_id(consultant.id).await.is_err());
+        });
+    }
+
+    #[test]
+    fn password_policy_test() {
+        test_harness(|connection_string| async move {
+            let tenet = AsyncTenet::new(connection_string).await
+                .with_password_policy(PasswordPolicy { min_length: 8, ..Default::default() });
+            let tenant = tenet.create_tenant("TenantTitle".to_string()).await.unwrap();
+            assert_eq!(8, tenant.get_password_policy().await.unwrap().min_length);
+
+            let policy = PasswordPolicy { require_digit: true, ..Default::default() };
+            tenant.set_password_policy(&policy).await.unwrap();
+            assert_eq!(policy, tenet.get_tenant_by_id(tenant.id).await.unwrap().get_password_policy().await.unwrap());
+            let new_user = user("someone@something.de", tenant.id);
+            assert!(matches!(tenant.add_user(&new_user).await, Err(TenetError::PasswordPolicyError(_))));
+
+            tenant.remove_password_policy().await.unwrap();
+            tenant.add_user(&new_user).await.unwrap();
+        });
+    }
+}
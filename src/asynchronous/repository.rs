@@ -0,0 +1,446 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+use super::database::{self, Pool};
+use crate::postgresql::database::DbUuid;
+use crate::postgresql::dbapplication::{DbApplication, DbApplicationMessage};
+use crate::postgresql::dbmembership::DbMembership;
+use crate::postgresql::dbpasswordpolicy::DbPasswordPolicy;
+use crate::postgresql::dbrole::{DbRole, DbRoleMessage, DbRoleWithType};
+use crate::postgresql::dbroledefinition::{DbPermission, DbRoleDefinition};
+use crate::postgresql::dbstorage::{DbStorage, DbStorageMessage};
+use crate::postgresql::dbtenant::{DbTenant, DbTenantMessage};
+use crate::postgresql::dbuser::{DbUser, DbUserMessage};
+use crate::schema::{applications, group_members, groups, memberships, password_policies, permissions, refresh_tokens, role_definitions, roles, sessions, storages, tenants, users};
+use crate::{Application, PasswordPolicy, Role, RoleDefinition, Storage, Tenant, TenetError, User};
+
+
+/// Async counterpart of [`crate::PostgresRepository`], running the same
+/// queries through `diesel-async`.
+#[derive(Clone)]
+pub(crate) struct AsyncPostgresRepository {
+    pool: Pool
+}
+
+impl std::fmt::Debug for AsyncPostgresRepository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncPostgresRepository").finish_non_exhaustive()
+    }
+}
+
+impl AsyncPostgresRepository {
+    pub async fn new(connection_string: &str) -> Self {
+        AsyncPostgresRepository {
+            pool: database::build_pool(connection_string).await
+        }
+    }
+
+    /* Tenants */
+    pub async fn find_all_tenants(&self) -> Result<Vec<Tenant>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let tenants = tenants::table.load::<DbTenant>(&mut connection).await?;
+        Ok(tenants.iter().map(Tenant::from).collect())
+    }
+
+    pub async fn find_tenant(&self, tenant_id: Uuid) -> Result<Tenant, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let tenant: DbTenant = tenants::table.filter(tenants::id.eq(DbUuid(tenant_id))).first(&mut connection).await?;
+        Ok(Tenant::from(&tenant))
+    }
+
+    pub async fn create_tenant(&self, tenant: &Tenant, role_definitions: &[RoleDefinition]) -> Result<Tenant, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let new_tenant = DbTenant::from(tenant);
+        let tenant: DbTenant = connection.transaction::<_, TenetError, _>(async |connection| {
+            let tenant = diesel::insert_into(tenants::table)
+                .values(new_tenant)
+                .get_result(connection)
+                .await?;
+            for role_definition in role_definitions {
+                insert_role_definition(connection, role_definition).await?;
+            }
+            Ok(tenant)
+        }).await?;
+        Ok(Tenant::from(&tenant))
+    }
+
+    pub async fn update_tenant(&self, tenant_id: Uuid, title: String) -> Result<Tenant, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let tenant: DbTenant = diesel::update(tenants::table)
+            .filter(tenants::id.eq(DbUuid(tenant_id)))
+            .set(DbTenantMessage { title })
+            .get_result(&mut connection)
+            .await?;
+        Ok(Tenant::from(&tenant))
+    }
+
+    pub async fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::delete(tenants::table.filter(tenants::id.eq(DbUuid(tenant_id))))
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn find_tenants_by_user(&self, user_id: Uuid) -> Result<Vec<Tenant>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let tenants: Vec<DbTenant> = tenants::table
+            .inner_join(memberships::table)
+            .filter(memberships::user_id.eq(DbUuid(user_id)))
+            .order(tenants::title)
+            .select(tenants::all_columns)
+            .load(&mut connection)
+            .await?;
+        Ok(tenants.iter().map(Tenant::from).collect())
+    }
+
+    pub async fn create_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::insert_into(memberships::table)
+            .values(DbMembership { user_id: DbUuid(user_id), db_tenant_id: DbUuid(tenant_id), created_at: Utc::now().naive_utc() })
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes the membership together with the user's roles and group
+    /// memberships in the tenant and revokes their sessions and refresh
+    /// tokens there, all in one transaction.
+    pub async fn delete_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let now = Utc::now().naive_utc();
+        connection.transaction::<_, TenetError, _>(async |connection| {
+            diesel::delete(roles::table.filter(roles::db_tenant_id.eq(DbUuid(tenant_id))).filter(roles::user_id.eq(DbUuid(user_id))))
+                .execute(connection)
+                .await?;
+            let group_ids = groups::table
+                .filter(groups::db_tenant_id.eq(DbUuid(tenant_id)))
+                .select(groups::id);
+            diesel::delete(group_members::table.filter(group_members::user_id.eq(DbUuid(user_id))).filter(group_members::group_id.eq_any(group_ids)))
+                .execute(connection)
+                .await?;
+            diesel::update(sessions::table.filter(sessions::db_tenant_id.eq(DbUuid(tenant_id))).filter(sessions::user_id.eq(DbUuid(user_id))).filter(sessions::revoked_at.is_null()))
+                .set(sessions::revoked_at.eq(now))
+                .execute(connection)
+                .await?;
+            diesel::update(refresh_tokens::table.filter(refresh_tokens::db_tenant_id.eq(DbUuid(tenant_id))).filter(refresh_tokens::user_id.eq(DbUuid(user_id))).filter(refresh_tokens::revoked_at.is_null()))
+                .set(refresh_tokens::revoked_at.eq(now))
+                .execute(connection)
+                .await?;
+            diesel::delete(memberships::table.filter(memberships::user_id.eq(DbUuid(user_id))).filter(memberships::db_tenant_id.eq(DbUuid(tenant_id))))
+                .execute(connection)
+                .await?;
+            Ok(())
+        }).await
+    }
+
+    /* Users */
+    pub async fn find_users_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<User>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let users: Vec<DbUser> = users::table
+            .inner_join(memberships::table)
+            .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
+            .select(users::all_columns)
+            .load(&mut connection)
+            .await?;
+        Ok(users.iter().map(User::from).collect())
+    }
+
+    pub async fn find_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<User, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let user: DbUser = users::table
+            .inner_join(memberships::table)
+            .filter(users::id.eq(DbUuid(user_id)))
+            .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
+            .select(users::all_columns)
+            .first(&mut connection)
+            .await?;
+        Ok(User::from(&user))
+    }
+
+    pub async fn find_user_by_email(&self, email: String) -> Result<User, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let user: DbUser = users::table
+            .filter(users::email.eq(email))
+            .first(&mut connection)
+            .await?;
+        Ok(User::from(&user))
+    }
+
+    pub async fn find_user_by_tenant_and_email(&self, tenant_id: Uuid, email: String) -> Result<User, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let user: DbUser = users::table
+            .inner_join(memberships::table)
+            .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
+            .filter(users::email.eq(email))
+            .select(users::all_columns)
+            .first(&mut connection)
+            .await?;
+        Ok(User::from(&user))
+    }
+
+    pub async fn create_user(&self, user: &User) -> Result<User, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let user: DbUser = diesel::insert_into(users::table)
+            .values(DbUser::from(DbUserMessage::from(user)))
+            .get_result(&mut connection)
+            .await?;
+        if let Some(tenant_id) = user.db_tenant_id {
+            diesel::insert_into(memberships::table)
+                .values(DbMembership { user_id: user.id, db_tenant_id: tenant_id, created_at: user.created_at })
+                .execute(&mut connection)
+                .await?;
+        }
+        Ok(User::from(&user))
+    }
+
+    pub async fn update_user(&self, user: &User) -> Result<User, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let updated_user: DbUser = diesel::update(users::table)
+            .filter(users::id.eq(DbUuid(user.id)))
+            .set(DbUserMessage::from(user))
+            .get_result(&mut connection)
+            .await?;
+        Ok(User::from(&updated_user))
+    }
+
+    pub async fn delete_user(&self, user_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::delete(users::table.filter(users::id.eq(DbUuid(user_id))))
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /* Applications */
+    pub async fn find_applications_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Application>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let applications: Vec<DbApplication> = applications::table
+            .filter(applications::db_tenant_id.eq(DbUuid(tenant_id)))
+            .load(&mut connection)
+            .await?;
+        Ok(applications.iter().map(Application::from).collect())
+    }
+
+    pub async fn find_application(&self, tenant_id: Uuid, application_id: Uuid) -> Result<Application, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let application: DbApplication = applications::table
+            .filter(applications::id.eq(DbUuid(application_id)))
+            .filter(applications::db_tenant_id.eq(DbUuid(tenant_id)))
+            .first(&mut connection)
+            .await?;
+        Ok(Application::from(&application))
+    }
+
+    pub async fn create_application(&self, application: &Application) -> Result<Application, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let application: DbApplication = diesel::insert_into(applications::table)
+            .values(DbApplication::from(DbApplicationMessage::from(application)))
+            .get_result(&mut connection)
+            .await?;
+        Ok(Application::from(&application))
+    }
+
+    pub async fn delete_application(&self, application_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::delete(applications::table.filter(applications::id.eq(DbUuid(application_id))))
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /* Storages */
+    pub async fn find_storages_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Storage>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let storages: Vec<DbStorage> = storages::table
+            .filter(storages::db_tenant_id.eq(DbUuid(tenant_id)))
+            .load(&mut connection)
+            .await?;
+        Ok(storages.iter().map(Storage::from).collect())
+    }
+
+    pub async fn find_storage(&self, tenant_id: Uuid, storage_id: Uuid) -> Result<Storage, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let storage: DbStorage = storages::table
+            .filter(storages::id.eq(DbUuid(storage_id)))
+            .filter(storages::db_tenant_id.eq(DbUuid(tenant_id)))
+            .first(&mut connection)
+            .await?;
+        Ok(Storage::from(&storage))
+    }
+
+    pub async fn create_storage(&self, storage: &Storage) -> Result<Storage, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let storage: DbStorage = diesel::insert_into(storages::table)
+            .values(DbStorage::from(DbStorageMessage::from(storage)))
+            .get_result(&mut connection)
+            .await?;
+        Ok(Storage::from(&storage))
+    }
+
+    pub async fn delete_storage(&self, storage_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::delete(storages::table.filter(storages::id.eq(DbUuid(storage_id))))
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /* Roles */
+    pub async fn find_roles_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let roles: Vec<DbRoleWithType> = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(DbUuid(tenant_id)))
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)
+            .await?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    pub async fn find_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let roles: Vec<DbRoleWithType> = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(DbUuid(tenant_id)))
+            .filter(roles::user_id.eq(DbUuid(user_id)))
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)
+            .await?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    pub async fn find_inherited_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let group_ids = group_members::table
+            .filter(group_members::user_id.eq(DbUuid(user_id)))
+            .select(group_members::group_id.nullable());
+        let roles: Vec<DbRoleWithType> = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(DbUuid(tenant_id)))
+            .filter(roles::group_id.eq_any(group_ids))
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)
+            .await?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    pub async fn find_role(&self, tenant_id: Uuid, role_id: Uuid) -> Result<Role, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let role: DbRoleWithType = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::id.eq(DbUuid(role_id)))
+            .filter(roles::db_tenant_id.eq(DbUuid(tenant_id)))
+            .select((roles::all_columns, role_definitions::role_type))
+            .first(&mut connection)
+            .await?;
+        Ok(Role::from(&role))
+    }
+
+    pub async fn create_role(&self, role: &Role) -> Result<Role, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let created_role: DbRole = diesel::insert_into(roles::table)
+            .values(DbRole::from(DbRoleMessage::from(role)))
+            .get_result(&mut connection)
+            .await?;
+        Ok(Role::from(&(created_role, role.role_type.to_string())))
+    }
+
+    pub async fn delete_role(&self, role_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::delete(roles::table.filter(roles::id.eq(DbUuid(role_id))))
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /* Role definitions */
+    pub async fn find_role_definition(&self, tenant_id: Uuid, role_definition_id: Uuid) -> Result<RoleDefinition, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let role_definition: DbRoleDefinition = role_definitions::table
+            .filter(role_definitions::id.eq(DbUuid(role_definition_id)))
+            .filter(role_definitions::db_tenant_id.eq(DbUuid(tenant_id)))
+            .first(&mut connection)
+            .await?;
+        self.with_permissions(role_definition).await
+    }
+
+    pub async fn find_role_definition_by_name(&self, tenant_id: Uuid, name: String) -> Result<RoleDefinition, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let role_definition: DbRoleDefinition = role_definitions::table
+            .filter(role_definitions::name.eq(name))
+            .filter(role_definitions::db_tenant_id.eq(DbUuid(tenant_id)))
+            .first(&mut connection)
+            .await?;
+        self.with_permissions(role_definition).await
+    }
+
+    async fn with_permissions(&self, role_definition: DbRoleDefinition) -> Result<RoleDefinition, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let names: Vec<String> = permissions::table
+            .filter(permissions::role_definition_id.eq(role_definition.id))
+            .order(permissions::name)
+            .select(permissions::name)
+            .load(&mut connection)
+            .await?;
+        Ok(RoleDefinition::from(&(role_definition, names)))
+    }
+
+    /* Password policies */
+    pub async fn find_password_policy(&self, tenant_id: Uuid) -> Result<PasswordPolicy, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let policy: DbPasswordPolicy = password_policies::table
+            .filter(password_policies::db_tenant_id.eq(DbUuid(tenant_id)))
+            .first(&mut connection)
+            .await?;
+        policy.policy()
+    }
+
+    pub async fn save_password_policy(&self, tenant_id: Uuid, policy: &PasswordPolicy) -> Result<PasswordPolicy, TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        let policy: DbPasswordPolicy = diesel::insert_into(password_policies::table)
+            .values(DbPasswordPolicy::new(tenant_id, policy)?)
+            .on_conflict(password_policies::db_tenant_id)
+            .do_update()
+            .set((
+                password_policies::policy.eq(diesel::upsert::excluded(password_policies::policy)),
+                password_policies::updated_at.eq(Some(Utc::now().naive_utc()))
+            ))
+            .get_result(&mut connection)
+            .await?;
+        policy.policy()
+    }
+
+    pub async fn delete_password_policy(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut connection = database::connection(&self.pool).await?;
+        diesel::delete(password_policies::table.filter(password_policies::db_tenant_id.eq(DbUuid(tenant_id))))
+            .execute(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+}
+
+
+async fn insert_role_definition(connection: &mut AsyncPgConnection, role_definition: &RoleDefinition) -> Result<DbRoleDefinition, TenetError> {
+    let created: DbRoleDefinition = diesel::insert_into(role_definitions::table)
+        .values(DbRoleDefinition::from(role_definition))
+        .get_result(connection)
+        .await?;
+
+    let db_permissions: Vec<DbPermission> = role_definition.permissions.iter()
+        .map(|name| DbPermission {
+            id: DbUuid(Uuid::new_v4()),
+            role_definition_id: created.id,
+            name: name.clone(),
+            created_at: created.created_at
+        })
+        .collect();
+    diesel::insert_into(permissions::table)
+        .values(db_permissions)
+        .execute(connection)
+        .await?;
+    Ok(created)
+}
@@ -0,0 +1,242 @@
+use chrono::NaiveDateTime;
+use log::warn;
+
+use super::repository::AsyncPostgresRepository;
+use crate::breached_passwords::SharedBreachedPasswords;
+use crate::encryption_modes::{EncryptionModes, PasswordHashConfig};
+use crate::{Application, PasswordPolicy, Role, Storage, Tenant, TenetError, User};
+
+
+/// Async counterpart of [`Tenant`].
+///
+/// Obtain one from [`crate::AsyncTenet`]. Password hashing and verification run
+/// on tokio's blocking thread pool, so none of the methods block the runtime.
+#[derive(Debug, Clone)]
+pub struct AsyncTenant {
+    pub id: uuid::Uuid,
+    pub title: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    repository: AsyncPostgresRepository,
+    password_hash_config: PasswordHashConfig,
+    /// The instance wide policy, for tenants without one of their own
+    password_policy: PasswordPolicy,
+    breached_passwords: SharedBreachedPasswords
+}
+
+impl AsyncTenant {
+    pub(crate) fn new(tenant: Tenant, repository: AsyncPostgresRepository, password_hash_config: PasswordHashConfig, password_policy: PasswordPolicy, breached_passwords: SharedBreachedPasswords) -> Self {
+        AsyncTenant {
+            id: tenant.id,
+            title: tenant.title,
+            created_at: tenant.created_at,
+            updated_at: tenant.updated_at,
+            repository,
+            password_hash_config,
+            password_policy,
+            breached_passwords
+        }
+    }
+
+    /* Users */
+    pub async fn get_users(&self) -> Vec<User> {
+        if let Ok(users) = self.repository.find_users_by_tenant(self.id).await {
+            return users;
+        }
+        Vec::new()
+    }
+
+    pub async fn get_user_ids(&self) -> Vec<uuid::Uuid> {
+        self.get_users().await.iter().map(|u| u.id).collect()
+    }
+
+    /// Hashes the password and stores the user. Fails with
+    /// `TenetError::PasswordPolicyError` if the password breaks the tenant's
+    /// password policy or is in the breach corpus.
+    pub async fn add_user(&self, user: &User) -> Result<User, TenetError> {
+        self.get_password_policy().await?.check_with_breaches(&user.password, &user.email, &user.full_name, &self.breached_passwords)?;
+        let mut new_user = user.clone();
+        new_user.password = hash_password(user.encryption_mode.clone(), user.password.clone(), self.password_hash_config.clone()).await?;
+
+        self.repository.create_user(&new_user).await
+    }
+
+    pub async fn get_user_by_id(&self, user_id: uuid::Uuid) -> Result<User, TenetError> {
+        self.repository.find_user(self.id, user_id).await
+    }
+
+    /// Deletes the user. A user that is also a member of other tenants is
+    /// only removed from this one, see [`AsyncTenant::remove_member`].
+    pub async fn delete_user(&self, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        if self.repository.find_tenants_by_user(user_id).await?.iter().any(|t| t.id != self.id) {
+            return self.remove_member(user_id).await;
+        }
+        self.repository.delete_user(user_id).await
+    }
+
+    /// Makes an existing user, e.g. one created in another tenant, a member of this tenant.
+    pub async fn add_member(&self, user_id: uuid::Uuid) -> Result<User, TenetError> {
+        self.repository.create_membership(user_id, self.id).await?;
+        self.repository.find_user(self.id, user_id).await
+    }
+
+    /// Removes the user from this tenant together with their roles, group
+    /// memberships, sessions and refresh tokens in it. The user itself and
+    /// their memberships in other tenants are kept.
+    pub async fn remove_member(&self, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        self.repository.find_user(self.id, user_id).await?;
+        self.repository.delete_membership(user_id, self.id).await
+    }
+
+    pub async fn contains_username(&self, username: String) -> bool {
+        self.repository.find_user_by_tenant_and_email(self.id, username).await.is_ok()
+    }
+
+    pub async fn set_user_verified(&self, user_id: uuid::Uuid) -> bool {
+        if let Ok(user) = self.repository.find_user(self.id, user_id).await {
+            return self.repository.update_user(&User { email_verified: true, ..user }).await.is_ok();
+        }
+        false
+    }
+
+    /// Returns the user if the password is correct. Passwords stored with an
+    /// outdated [`PasswordHashConfig`] or a legacy encryption mode are
+    /// re-hashed on the way.
+    pub async fn authenticate_user(&self, username: String, password: String) -> Option<User> {
+        if let Ok(user) = self.repository.find_user_by_tenant_and_email(self.id, username).await
+            && let Ok(true) = verify_password(user.clone(), password.clone()).await {
+            return Some(self.rehash_password(user, password).await)
+        }
+        None
+    }
+
+    /// Async counterpart of `Context::rehash_password`.
+    async fn rehash_password(&self, user: User, password: String) -> User {
+        if !self.password_hash_config.needs_rehash(&user.encryption_mode, &user.password) {
+            return user;
+        }
+        let rehashed = match hash_password(EncryptionModes::Argon2, password, self.password_hash_config.clone()).await {
+            Ok(password) => self.repository.update_user(&User { password, encryption_mode: EncryptionModes::Argon2, ..user.clone() }).await,
+            Err(e) => Err(e)
+        };
+        match rehashed {
+            Ok(rehashed) => rehashed,
+            Err(e) => {
+                warn!("Could not re-hash the password of user {}: {}", user.id, e);
+                user
+            }
+        }
+    }
+
+    /* Applications */
+    pub async fn get_applications(&self) -> Vec<Application> {
+        if let Ok(applications) = self.repository.find_applications_by_tenant(self.id).await {
+            return applications;
+        }
+        Vec::new()
+    }
+
+    pub async fn get_application_by_id(&self, application_id: uuid::Uuid) -> Result<Application, TenetError> {
+        self.repository.find_application(self.id, application_id).await
+    }
+
+    pub async fn add_application(&self, application: &Application) -> Result<Application, TenetError> {
+        self.repository.create_application(application).await
+    }
+
+    pub async fn delete_application(&self, application_id: uuid::Uuid) -> Result<(), TenetError> {
+        self.repository.delete_application(application_id).await
+    }
+
+    /* Storage */
+    pub async fn get_storages(&self) -> Vec<Storage> {
+        if let Ok(storages) = self.repository.find_storages_by_tenant(self.id).await {
+            return storages;
+        }
+        Vec::new()
+    }
+
+    pub async fn get_storage_by_id(&self, storage_id: uuid::Uuid) -> Result<Storage, TenetError> {
+        self.repository.find_storage(self.id, storage_id).await
+    }
+
+    pub async fn add_storage(&self, storage: &Storage) -> Result<Storage, TenetError> {
+        self.repository.create_storage(storage).await
+    }
+
+    pub async fn delete_storage(&self, storage_id: uuid::Uuid) -> Result<(), TenetError> {
+        self.repository.delete_storage(storage_id).await
+    }
+
+    /* Roles */
+    pub async fn get_roles(&self) -> Vec<Role> {
+        if let Ok(roles) = self.repository.find_roles_by_tenant(self.id).await {
+            return roles;
+        }
+        Vec::new()
+    }
+
+    pub async fn get_role_by_id(&self, role_id: uuid::Uuid) -> Result<Role, TenetError> {
+        self.repository.find_role(self.id, role_id).await
+    }
+
+    /// See [`Tenant::add_role`].
+    pub async fn add_role(&self, role: &Role) -> Result<Role, TenetError> {
+        if let Some(user_id) = role.user_id {
+            self.repository.find_user(self.id, user_id).await?;
+        }
+        if let Some(application_id) = role.application_id {
+            self.repository.find_application(self.id, application_id).await?;
+        }
+        let role_definition = match role.role_definition_id {
+            Some(role_definition_id) => self.repository.find_role_definition(self.id, role_definition_id).await?,
+            None => self.repository.find_role_definition_by_name(self.id, role.role_type.to_string()).await?
+        };
+
+        self.repository.create_role(&Role {
+            role_type: role_definition.role_type,
+            role_definition_id: Some(role_definition.id),
+            ..*role
+        }).await
+    }
+
+    pub async fn delete_role(&self, role_id: uuid::Uuid) -> Result<(), TenetError> {
+        self.repository.delete_role(role_id).await
+    }
+
+    /// Returns the roles assigned to the user directly and the roles inherited
+    /// from the user's groups.
+    pub async fn get_roles_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Role>, TenetError> {
+        let mut roles = self.repository.find_roles_by_user(self.id, user_id).await?;
+        roles.extend(self.repository.find_inherited_roles_by_user(self.id, user_id).await?);
+        Ok(roles)
+    }
+
+    /* Password policy */
+    /// See [`Tenant::get_password_policy`].
+    pub async fn get_password_policy(&self) -> Result<PasswordPolicy, TenetError> {
+        match self.repository.find_password_policy(self.id).await {
+            Err(e) if e.is_not_found() => Ok(self.password_policy.clone()),
+            result => result
+        }
+    }
+
+    /// See [`Tenant::set_password_policy`].
+    pub async fn set_password_policy(&self, password_policy: &PasswordPolicy) -> Result<PasswordPolicy, TenetError> {
+        self.repository.save_password_policy(self.id, password_policy).await
+    }
+
+    /// See [`Tenant::remove_password_policy`].
+    pub async fn remove_password_policy(&self) -> Result<(), TenetError> {
+        self.repository.delete_password_policy(self.id).await
+    }
+}
+
+
+async fn hash_password(encryption_mode: EncryptionModes, password: String, config: PasswordHashConfig) -> Result<String, TenetError> {
+    tokio::task::spawn_blocking(move || encryption_mode.hash_password_with(&password, &config)).await?
+}
+
+async fn verify_password(user: User, password: String) -> Result<bool, TenetError> {
+    tokio::task::spawn_blocking(move || user.verify_password(&password)).await?
+}
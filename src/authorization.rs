@@ -0,0 +1,30 @@
+use crate::{role_type::RoleType, Role};
+
+
+/// Outcome of [`crate::Tenant::is_authorized`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthorizationDecision {
+    /// The user holds `role`, which is at least the required role type.
+    Granted { role: Role },
+    Denied { reason: DenialReason }
+}
+
+impl AuthorizationDecision {
+    pub fn is_granted(&self) -> bool {
+        matches!(self, AuthorizationDecision::Granted { .. })
+    }
+}
+
+
+/// Why an [`AuthorizationDecision`] was denied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DenialReason {
+    /// The user does not exist in the tenant
+    UnknownUser,
+    /// The application does not exist in the tenant
+    UnknownApplication,
+    /// The user has no role for the application
+    NoRole,
+    /// The user's highest role for the application is below the required one
+    InsufficientRole { effective: RoleType, required: RoleType }
+}
@@ -0,0 +1,46 @@
+//! REST/JSON server exposing Tenet's tenant administration over HTTP.
+//!
+//! Configuration is read from the environment:
+//!
+//! * `TENET_DATABASE_URL` - PostgreSQL connection string. With the `sqlite`
+//!   feature, a `sqlite://<path>` URL selects the SQLite backend instead.
+//! * `TENET_LISTEN_ADDRESS` - Address to listen on, defaults to `127.0.0.1:8080`.
+//! * `TENET_ADMIN_TOKEN` - Bearer token required by the administrative routes.
+//!   The server does not start without it.
+//!
+//! See [`stec_tenet::server::router`] for the available routes.
+
+use log::{error, info};
+use stec_tenet::Tenet;
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8080";
+
+
+fn build_tenet(database_url: String) -> Tenet {
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = database_url.strip_prefix("sqlite://") {
+        return Tenet::sqlite(path);
+    }
+
+    Tenet::new(database_url)
+}
+
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    simple_logger::init_with_level(log::Level::Info).expect("Unable to initialize logger");
+
+    let database_url = std::env::var("TENET_DATABASE_URL").unwrap_or_default();
+    let listen_address = std::env::var("TENET_LISTEN_ADDRESS").unwrap_or(DEFAULT_LISTEN_ADDRESS.to_string());
+    let admin_token = std::env::var("TENET_ADMIN_TOKEN").unwrap_or_default();
+    if admin_token.is_empty() {
+        error!("TENET_ADMIN_TOKEN is not set, refusing to serve the admin API without authentication");
+        std::process::exit(1);
+    }
+
+    let tenet = tokio::task::spawn_blocking(move || build_tenet(database_url)).await?;
+
+    let listener = tokio::net::TcpListener::bind(&listen_address).await?;
+    info!("Tenet server listening on {}", listen_address);
+    axum::serve(listener, stec_tenet::server::router(tenet, &admin_token)).await
+}
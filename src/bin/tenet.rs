@@ -0,0 +1,434 @@
+//! Admin command line tool for operating a Tenet database.
+//!
+//! Run `tenet --help` for the available commands. The database is selected
+//! with `--database-url` or `TENET_DATABASE_URL`; with the `sqlite` feature,
+//! a `sqlite://<path>` URL selects the SQLite backend.
+
+use std::io::BufRead;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use chrono::Utc;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use uuid::Uuid;
+
+use stec_tenet::application_type::ApplicationType;
+use stec_tenet::encryption_modes::EncryptionModes;
+use stec_tenet::role_type::RoleType;
+use stec_tenet::storage_type::StorageType;
+use stec_tenet::{Application, Role, Storage, Tenant, Tenet, TenetError, User};
+
+
+#[derive(Debug, Parser)]
+#[command(name = "tenet", version, about = "Administration of Tenet tenants, users and applications")]
+struct Cli {
+    /// Database connection string
+    #[arg(long, env = "TENET_DATABASE_URL", default_value = "", hide_default_value = true, global = true)]
+    database_url: String,
+
+    /// Output format
+    #[arg(long, short, value_enum, default_value_t = Output::Table, global = true)]
+    output: Output,
+
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Output {
+    Table,
+    Json
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manage tenants
+    #[command(subcommand)]
+    Tenant(TenantCommand),
+    /// Manage users of a tenant
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage roles of a tenant
+    #[command(subcommand)]
+    Role(RoleCommand),
+    /// Manage storages of a tenant
+    #[command(subcommand)]
+    Storage(StorageCommand),
+    /// Manage applications of a tenant
+    #[command(subcommand)]
+    Application(ApplicationCommand),
+    /// Run pending database migrations
+    Migrate
+}
+
+#[derive(Debug, Subcommand)]
+enum TenantCommand {
+    /// Create a new tenant
+    Create { title: String },
+    /// List all tenants
+    List,
+    /// Delete a tenant
+    Delete { tenant_id: Uuid }
+}
+
+#[derive(Debug, Subcommand)]
+enum UserCommand {
+    /// Add a user to a tenant
+    Add {
+        #[arg(long)]
+        tenant: Uuid,
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        full_name: String,
+        /// Read from stdin if omitted
+        #[arg(long)]
+        password: Option<String>,
+        /// Mark the email address as verified
+        #[arg(long)]
+        verified: bool
+    },
+    /// List the users of a tenant
+    List {
+        #[arg(long)]
+        tenant: Uuid
+    },
+    /// Set a new password for a user and revoke their sessions
+    ResetPassword {
+        #[arg(long)]
+        tenant: Uuid,
+        #[arg(long)]
+        user: Uuid,
+        /// Read from stdin if omitted
+        #[arg(long)]
+        password: Option<String>
+    },
+    /// Mark the email address of a user as verified
+    Verify {
+        #[arg(long)]
+        tenant: Uuid,
+        #[arg(long)]
+        user: Uuid
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum RoleCommand {
+    /// Assign a role for an application, or tenant-wide without --application, to a user
+    Assign {
+        #[arg(long)]
+        tenant: Uuid,
+        #[arg(long)]
+        user: Uuid,
+        #[arg(long)]
+        application: Option<Uuid>,
+        #[arg(long, value_parser = parse::<RoleType>)]
+        role: RoleType
+    },
+    /// List the roles of a tenant
+    List {
+        #[arg(long)]
+        tenant: Uuid
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum StorageCommand {
+    /// Register a storage for a tenant
+    Add {
+        #[arg(long)]
+        tenant: Uuid,
+        /// JsonFile, SqliteDatabase, PostgreSqlDatabase, PostgreSqlSchema or PostgreSqlTablePrefix
+        #[arg(long = "type", value_parser = parse::<StorageType>)]
+        storage_type: StorageType,
+        #[arg(long)]
+        path: Option<String>,
+        #[arg(long)]
+        connection_string: Option<String>,
+        #[arg(long)]
+        schema: Option<String>,
+        #[arg(long)]
+        table_prefix: Option<String>
+    },
+    /// List the storages of a tenant
+    List {
+        #[arg(long)]
+        tenant: Uuid
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum ApplicationCommand {
+    /// Register an application for a tenant
+    Add {
+        #[arg(long)]
+        tenant: Uuid,
+        #[arg(long = "type", value_parser = parse::<ApplicationType>)]
+        application_type: ApplicationType,
+        #[arg(long)]
+        storage: Uuid
+    },
+    /// List the applications of a tenant
+    List {
+        #[arg(long)]
+        tenant: Uuid
+    }
+}
+
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String> {
+    T::from_str(value).map_err(|_| format!("invalid value '{}'", value))
+}
+
+
+/// Rows that can be printed as a table.
+trait Tabular {
+    const HEADERS: &'static [&'static str];
+    fn row(&self) -> Vec<String>;
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+impl Tabular for Tenant {
+    const HEADERS: &'static [&'static str] = &["ID", "TITLE", "CREATED"];
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.title.clone(), self.created_at.to_string()]
+    }
+}
+
+impl Tabular for User {
+    const HEADERS: &'static [&'static str] = &["ID", "EMAIL", "FULL NAME", "VERIFIED", "CREATED"];
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.email.clone(), self.full_name.clone(), self.email_verified.to_string(), self.created_at.to_string()]
+    }
+}
+
+impl Tabular for Role {
+    const HEADERS: &'static [&'static str] = &["ID", "ROLE", "USER", "APPLICATION"];
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.role_type.to_string(), optional(&self.user_id), optional(&self.application_id)]
+    }
+}
+
+impl Tabular for Storage {
+    const HEADERS: &'static [&'static str] = &["ID", "TYPE", "PATH", "CONNECTION STRING", "SCHEMA", "TABLE PREFIX"];
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.storage_type.to_string(), optional(&self.path), optional(&self.connection_string), optional(&self.schema), optional(&self.table_prefix)]
+    }
+}
+
+impl Tabular for Application {
+    const HEADERS: &'static [&'static str] = &["ID", "TYPE", "STORAGE", "CREATED"];
+    fn row(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.application_type.to_string(), optional(&self.storage_id), self.created_at.to_string()]
+    }
+}
+
+
+fn render_table<T: Tabular>(items: &[T]) -> String {
+    let rows: Vec<Vec<String>> = items.iter().map(Tabular::row).collect();
+    let mut widths: Vec<usize> = T::HEADERS.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let headers: Vec<String> = T::HEADERS.iter().map(|h| h.to_string()).collect();
+    std::iter::once(&headers).chain(&rows)
+        .map(|row| row.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn print<T: Tabular + Serialize>(output: Output, items: &[T]) -> Result<(), TenetError> {
+    match output {
+        Output::Table => println!("{}", render_table(items)),
+        Output::Json => println!("{}", serde_json::to_string_pretty(items)?)
+    }
+    Ok(())
+}
+
+fn print_one<T: Tabular + Serialize>(output: Output, item: &T) -> Result<(), TenetError> {
+    match output {
+        Output::Table => print(output, std::slice::from_ref(item)),
+        Output::Json => {
+            println!("{}", serde_json::to_string_pretty(item)?);
+            Ok(())
+        }
+    }
+}
+
+/// Password hashes are never printed.
+fn without_password(mut user: User) -> User {
+    user.password = String::new();
+    user
+}
+
+fn read_password(password: Option<String>) -> Result<String, TenetError> {
+    if let Some(password) = password {
+        return Ok(password);
+    }
+
+    eprintln!("Password:");
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+
+fn build_tenet(database_url: String) -> Tenet {
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = database_url.strip_prefix("sqlite://") {
+        return Tenet::sqlite(path);
+    }
+
+    Tenet::new(database_url)
+}
+
+fn find_tenant(tenet: &Tenet, tenant_id: Uuid) -> Result<Tenant, TenetError> {
+    tenet.get_tenant_by_id(tenant_id).ok_or(TenetError::NotFoundError)
+}
+
+
+fn run(cli: Cli) -> Result<(), TenetError> {
+    // Building the instance runs any pending migrations
+    let tenet = build_tenet(cli.database_url);
+    let output = cli.output;
+
+    match cli.command {
+        Command::Migrate => {
+            eprintln!("Database migrations completed");
+            Ok(())
+        },
+
+        Command::Tenant(TenantCommand::Create { title }) => print_one(output, &tenet.create_tenant(title)?),
+        Command::Tenant(TenantCommand::List) => {
+            let tenants: Vec<Tenant> = tenet.get_tenant_ids().into_iter().filter_map(|id| tenet.get_tenant_by_id(id)).collect();
+            print(output, &tenants)
+        },
+        Command::Tenant(TenantCommand::Delete { tenant_id }) => {
+            let tenant = find_tenant(&tenet, tenant_id)?;
+            tenet.delete_tenant(tenant_id)?;
+            print_one(output, &tenant)
+        },
+
+        Command::User(UserCommand::Add { tenant, email, full_name, password, verified }) => {
+            let tenant = find_tenant(&tenet, tenant)?;
+            let password = read_password(password)?;
+            let user = User::new(email.clone(), full_name, password, EncryptionModes::Argon2, email, verified, tenant.id);
+            print_one(output, &without_password(tenant.add_user(&user)?))
+        },
+        Command::User(UserCommand::List { tenant }) => {
+            let users: Vec<User> = find_tenant(&tenet, tenant)?.get_users().into_iter().map(without_password).collect();
+            print(output, &users)
+        },
+        Command::User(UserCommand::ResetPassword { tenant, user, password }) => {
+            let tenant = find_tenant(&tenet, tenant)?;
+            print_one(output, &without_password(tenant.force_password_reset(user, read_password(password)?)?))
+        },
+        Command::User(UserCommand::Verify { tenant, user }) => {
+            let tenant = find_tenant(&tenet, tenant)?;
+            let mut user = tenant.get_user_by_id(user)?;
+            user.email_verified = true;
+            user.updated_at = Some(Utc::now().naive_utc());
+            print_one(output, &without_password(tenant.update_user(&user)?))
+        },
+
+        Command::Role(RoleCommand::Assign { tenant, user, application, role }) => {
+            let tenant = find_tenant(&tenet, tenant)?;
+            let role = match application {
+                Some(application) => Role::new(role, user, application, tenant.id),
+                None => Role::new_tenant_wide(role, user, tenant.id)
+            };
+            print_one(output, &tenant.add_role(&role)?)
+        },
+        Command::Role(RoleCommand::List { tenant }) => print(output, &find_tenant(&tenet, tenant)?.get_roles()),
+
+        Command::Storage(StorageCommand::Add { tenant, storage_type, path, connection_string, schema, table_prefix }) => {
+            let tenant = find_tenant(&tenet, tenant)?;
+            let storage = Storage {
+                id: Uuid::new_v4(),
+                storage_type,
+                path,
+                connection_string,
+                schema,
+                table_prefix,
+                created_at: Utc::now().naive_utc(),
+                updated_at: None,
+                db_tenant_id: Some(tenant.id)
+            };
+            print_one(output, &tenant.add_storage(&storage)?)
+        },
+        Command::Storage(StorageCommand::List { tenant }) => print(output, &find_tenant(&tenet, tenant)?.get_storages()),
+
+        Command::Application(ApplicationCommand::Add { tenant, application_type, storage }) => {
+            let tenant = find_tenant(&tenet, tenant)?;
+            print_one(output, &tenant.add_application(&Application::new(application_type, storage, tenant.id))?)
+        },
+        Command::Application(ApplicationCommand::List { tenant }) => print(output, &find_tenant(&tenet, tenant)?.get_applications())
+    }
+}
+
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn cli_definition_test() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_arguments_test() {
+        let cli = Cli::parse_from(["tenet", "--output", "json", "role", "assign",
+            "--tenant", "8ee1d8a4-7f0e-4bd1-a0ba-4b8d15e6b4e4",
+            "--user", "1d2cbd2b-2a84-4c3c-9b1a-46b3f1a1e0a4",
+            "--application", "5f3c8f4e-3f43-4a6a-8b0d-0c1f2c2f8f11",
+            "--role", "Administrator"]);
+        assert_eq!(Output::Json, cli.output);
+        assert!(matches!(cli.command, Command::Role(RoleCommand::Assign { role: RoleType::Administrator, .. })));
+
+        assert!(Cli::try_parse_from(["tenet", "role", "assign", "--role", "Nobody"]).is_err());
+
+        let cli = Cli::parse_from(["tenet", "role", "assign",
+            "--tenant", "8ee1d8a4-7f0e-4bd1-a0ba-4b8d15e6b4e4",
+            "--user", "1d2cbd2b-2a84-4c3c-9b1a-46b3f1a1e0a4",
+            "--role", "Administrator"]);
+        assert!(matches!(cli.command, Command::Role(RoleCommand::Assign { application: None, .. })));
+    }
+
+    #[test]
+    fn render_table_test() {
+        let mut tenant = Tenant::new("Some Tenant".to_string());
+        tenant.id = Uuid::nil();
+        let table = render_table(&[tenant.clone()]);
+
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("ID                                    TITLE"));
+        assert!(lines[1].starts_with("00000000-0000-0000-0000-000000000000  Some Tenant  "));
+    }
+}
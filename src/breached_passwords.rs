@@ -0,0 +1,274 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use sha1::{Digest, Sha1};
+
+use crate::TenetError;
+
+
+/// Magic bytes at the start of a filter written by [`BreachedPasswords::save_filter`].
+const FILTER_MAGIC: &[u8; 8] = b"TENETBF1";
+
+/// Bits of the filter per distinct hash.
+const BITS_PER_HASH: u64 = 16;
+
+/// Bits set per hash, optimal for [`BITS_PER_HASH`].
+const HASH_FUNCTIONS: u64 = 11;
+
+
+/// A breach corpus of SHA-1 password hashes, checked without network access,
+/// see [`crate::Tenet::set_breached_passwords`].
+///
+/// The hashes are kept in a bloom filter of 2 bytes per hash. Like every
+/// bloom filter it has false positives but no false negatives: about one in
+/// 2000 passwords outside the corpus is reported as breached anyway. Loading
+/// hashes needs another 8 bytes per hash until the filter is built, so build
+/// the filter of a large corpus once with [`BreachedPasswords::save_filter`].
+#[derive(Debug, Clone, Default)]
+pub struct BreachedPasswords {
+    bits: Vec<u64>,
+    len: usize
+}
+
+impl BreachedPasswords {
+    /// Loads a text file with one hex encoded SHA-1 hash per line, optionally
+    /// followed by `:count`, like the Pwned Passwords downloads. The lines do
+    /// not need to be ordered.
+    pub fn from_hash_file(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        let mut keys = Vec::new();
+        read_hashes(path.as_ref(), "", &mut keys)?;
+        Ok(Self::from_keys(keys))
+    }
+
+    /// Loads a directory of Pwned Passwords range files, as written by the
+    /// downloader without `--single`. Every file is named after the first five
+    /// hex digits of its hashes, e.g. `5BAA6.txt`, and has the remaining 35
+    /// digits per line, optionally followed by `:count`. Other files are
+    /// skipped.
+    pub fn from_range_directory(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let Some(range) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if path.is_file() && is_hex(range, 5) {
+                read_hashes(&path, range, &mut keys)?;
+            }
+        }
+        Ok(Self::from_keys(keys))
+    }
+
+    /// Loads a filter written by [`BreachedPasswords::save_filter`], which is
+    /// much faster than parsing the hashes again.
+    pub fn from_filter_file(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        let bytes = fs::read(path)?;
+        let Some(content) = bytes.strip_prefix(FILTER_MAGIC) else {
+            return Err(invalid_data("not a breached password filter".to_string()));
+        };
+        let Some((len, words)) = content.split_first_chunk::<8>() else {
+            return Err(invalid_data("truncated breached password filter".to_string()));
+        };
+        if words.len() % 8 != 0 {
+            return Err(invalid_data("truncated breached password filter".to_string()));
+        }
+        let bits = words.chunks_exact(8)
+            .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap_or_default()))
+            .collect();
+        Ok(BreachedPasswords { bits, len: u64::from_be_bytes(*len) as usize })
+    }
+
+    /// Builds the corpus from plaintext passwords, e.g. for tests or a small
+    /// list of your own.
+    pub fn from_passwords<'a>(passwords: impl IntoIterator<Item = &'a str>) -> Self {
+        Self::from_keys(passwords.into_iter().map(key).collect())
+    }
+
+    fn from_keys(mut keys: Vec<u64>) -> Self {
+        keys.sort_unstable();
+        keys.dedup();
+        let bit_count = (keys.len() as u64 * BITS_PER_HASH).max(64).next_multiple_of(64);
+        let mut bits = vec![0u64; (bit_count / 64) as usize];
+        for key in &keys {
+            for index in indices(*key, bit_count) {
+                bits[(index / 64) as usize] |= 1 << (index % 64);
+            }
+        }
+        BreachedPasswords { bits, len: keys.len() }
+    }
+
+    /// Writes the compact filter to a file.
+    pub fn save_filter(&self, path: impl AsRef<Path>) -> Result<(), TenetError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(FILTER_MAGIC)?;
+        writer.write_all(&(self.len as u64).to_be_bytes())?;
+        for word in &self.bits {
+            writer.write_all(&word.to_be_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        let bit_count = self.bits.len() as u64 * 64;
+        if bit_count == 0 {
+            return false;
+        }
+        indices(key(password), bit_count).all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    /// Returns the number of distinct hashes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Adds the first 64 bits of every hash in the file to `keys`. The hashes
+/// are the lines without `:count`, prefixed with `range`.
+fn read_hashes(path: &Path, range: &str, keys: &mut Vec<u64>) -> Result<(), TenetError> {
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let suffix = line.split(':').next().unwrap_or_default().trim();
+        if suffix.is_empty() {
+            continue;
+        }
+        if !is_hex(suffix, 40 - range.len()) {
+            return Err(invalid_data(format!("{}, line {}: not a SHA-1 hash", path.display(), number + 1)));
+        }
+        let hash = format!("{range}{suffix}");
+        keys.push(u64::from_str_radix(&hash[..16], 16).unwrap_or_default());
+    }
+    Ok(())
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The first 64 bits of the password's SHA-1 hash.
+fn key(password: &str) -> u64 {
+    let hash = Sha1::digest(password.as_bytes());
+    u64::from_be_bytes(hash[..8].try_into().unwrap_or_default())
+}
+
+/// The bits of the key by double hashing. The key is uniformly distributed
+/// already, the second hash only needs to differ from it.
+fn indices(key: u64, bit_count: u64) -> impl Iterator<Item = u64> {
+    let step = mix(key) | 1;
+    (0..HASH_FUNCTIONS).map(move |i| key.wrapping_add(i.wrapping_mul(step)) % bit_count)
+}
+
+/// The finalizer of SplitMix64.
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+fn invalid_data(message: String) -> TenetError {
+    TenetError::IoError(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+
+/// The corpus of a [`crate::Tenet`] instance. Shared by the instance, its
+/// clones and all tenants obtained from them, so swapping it takes effect
+/// everywhere at once.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedBreachedPasswords(Arc<RwLock<Option<Arc<BreachedPasswords>>>>);
+
+impl SharedBreachedPasswords {
+    pub fn set(&self, breached_passwords: Option<BreachedPasswords>) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = breached_passwords.map(Arc::new);
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        let breached_passwords = self.0.read().unwrap_or_else(|e| e.into_inner()).clone();
+        breached_passwords.is_some_and(|b| b.contains(password))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_file_test() {
+        let directory = std::env::temp_dir().join(format!("tenet-breached-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        let hash_file = directory.join("pwned-passwords-sha1.txt");
+        // SHA-1 of "password" and "123456", unordered, with and without count
+        fs::write(&hash_file, "7C4A8D09CA3762AF61E59520943DC26494F8941B:37359195\r\n\n5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8\n").unwrap();
+
+        let breached_passwords = BreachedPasswords::from_hash_file(&hash_file).unwrap();
+        assert_eq!(2, breached_passwords.len());
+        assert!(breached_passwords.contains("password"));
+        assert!(breached_passwords.contains("123456"));
+        assert!(!breached_passwords.contains("correct horse battery staple"));
+
+        let filter_file = directory.join("breached.filter");
+        breached_passwords.save_filter(&filter_file).unwrap();
+        let loaded = BreachedPasswords::from_filter_file(&filter_file).unwrap();
+        assert_eq!(2, loaded.len());
+        assert!(loaded.contains("password"));
+
+        fs::write(&hash_file, "5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8\nnot a hash\n").unwrap();
+        assert!(matches!(BreachedPasswords::from_hash_file(&hash_file), Err(TenetError::IoError(_))));
+        assert!(matches!(BreachedPasswords::from_filter_file(&hash_file), Err(TenetError::IoError(_))));
+        // 40 bytes, but not 40 hex digits
+        fs::write(&hash_file, format!("a{}a\n", "ä".repeat(19))).unwrap();
+        assert!(matches!(BreachedPasswords::from_hash_file(&hash_file), Err(TenetError::IoError(_))));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn range_directory_test() {
+        let directory = std::env::temp_dir().join(format!("tenet-ranges-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        // SHA-1 of "password" and "123456"
+        fs::write(directory.join("5BAA6.txt"), "0D6B0A6F0B49FD2A3C1B4B1CE0B0F3D4A81:3\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:10434004\r\n").unwrap();
+        fs::write(directory.join("7C4A8.txt"), "D09CA3762AF61E59520943DC26494F8941B:37359195\n").unwrap();
+        fs::write(directory.join("README.md"), "Not a range file").unwrap();
+
+        let breached_passwords = BreachedPasswords::from_range_directory(&directory).unwrap();
+        assert_eq!(3, breached_passwords.len());
+        assert!(breached_passwords.contains("password"));
+        assert!(breached_passwords.contains("123456"));
+        assert!(!breached_passwords.contains("correct horse battery staple"));
+
+        // Full hashes in a range file
+        fs::write(directory.join("7C4A8.txt"), "7C4A8D09CA3762AF61E59520943DC26494F8941B\n").unwrap();
+        assert!(matches!(BreachedPasswords::from_range_directory(&directory), Err(TenetError::IoError(_))));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn false_positive_test() {
+        let passwords: Vec<String> = (0..10_000).map(|i| format!("breached-{i}")).collect();
+        let breached_passwords = BreachedPasswords::from_passwords(passwords.iter().map(String::as_str));
+        assert_eq!(10_000, breached_passwords.len());
+        assert_eq!(20_000, breached_passwords.bits.len() * 8);
+        assert!(passwords.iter().all(|p| breached_passwords.contains(p)));
+
+        let false_positives = (0..10_000).filter(|i| breached_passwords.contains(&format!("other-{i}"))).count();
+        assert!(false_positives < 20, "{false_positives} false positives");
+        assert!(!BreachedPasswords::default().contains("password"));
+    }
+
+    #[test]
+    fn shared_test() {
+        let shared = SharedBreachedPasswords::default();
+        let clone = shared.clone();
+        assert!(!clone.contains("password"));
+        shared.set(Some(BreachedPasswords::from_passwords(["password"])));
+        assert!(clone.contains("password"));
+        shared.set(None);
+        assert!(!clone.contains("password"));
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::{Arc, OnceLock};
+
+use log::warn;
+
+use crate::breached_passwords::SharedBreachedPasswords;
+use crate::encryption_modes::{EncryptionModes, PasswordHashConfig};
+use crate::mail_template::MailTemplates;
+use crate::password_policy::{into_result, PasswordPolicy, PasswordViolation};
+use crate::mailer::{MailQueue, Mailer};
+use crate::repository::Repository;
+use crate::session::{self, SessionConfig};
+use crate::token_config::TokenConfig;
+use crate::{TenetError, User};
+
+
+/// State shared by a [`crate::Tenet`] instance and every [`crate::Tenant`] it
+/// hands out: the repository, the mailer and the instance wide configuration.
+#[derive(Debug, Clone)]
+pub(crate) struct Context {
+    pub repository: Arc<dyn Repository>,
+    pub session_config: SessionConfig,
+    pub token_config: TokenConfig,
+    pub password_hash_config: PasswordHashConfig,
+    /// Applies to tenants without a policy of their own
+    pub password_policy: PasswordPolicy,
+    pub breached_passwords: SharedBreachedPasswords,
+    pub mailer: Option<Arc<dyn Mailer>>,
+    /// Sends the mails of `mailer` that are not waited for
+    pub mail_queue: Option<MailQueue>,
+    pub mail_templates: MailTemplates,
+    /// Hash of no user's password, see [`Context::verify_dummy_password`].
+    /// Reset whenever `password_hash_config` changes.
+    pub dummy_password_hash: OnceLock<String>
+}
+
+impl Context {
+    pub fn new(repository: Arc<dyn Repository>) -> Self {
+        Context {
+            repository,
+            session_config: SessionConfig::default(),
+            token_config: TokenConfig::default(),
+            password_hash_config: PasswordHashConfig::default(),
+            password_policy: PasswordPolicy::default(),
+            breached_passwords: SharedBreachedPasswords::default(),
+            mailer: None,
+            mail_queue: None,
+            mail_templates: MailTemplates::default(),
+            dummy_password_hash: OnceLock::new()
+        }
+    }
+
+    /// Returns the policy stored for the tenant, or the instance wide policy
+    /// if it has none.
+    pub fn password_policy(&self, tenant_id: uuid::Uuid) -> Result<PasswordPolicy, TenetError> {
+        match self.repository.find_password_policy(tenant_id) {
+            Err(e) if e.is_not_found() => Ok(self.password_policy.clone()),
+            result => result
+        }
+    }
+
+    /// Checks the password of a new user against the tenant's password policy
+    /// and the breach corpus.
+    pub fn check_password(&self, tenant_id: uuid::Uuid, password: &str, user: &User) -> Result<(), TenetError> {
+        self.password_policy(tenant_id)?.check_with_breaches(password, &user.email, &user.full_name, &self.breached_passwords)
+    }
+
+    /// Like [`Context::check_password`] for a stored user, additionally
+    /// rejecting their current and recent passwords.
+    pub fn check_new_password(&self, tenant_id: uuid::Uuid, password: &str, user: &User) -> Result<(), TenetError> {
+        let policy = self.password_policy(tenant_id)?;
+        let mut violations = policy.violations_with_breaches(password, &user.email, &user.full_name, &self.breached_passwords);
+        if policy.history_length > 0 && self.is_recent_password(user, password, policy.history_length - 1)? {
+            violations.push(PasswordViolation::RecentlyUsed { history_length: policy.history_length });
+        }
+        into_result(violations)
+    }
+
+    /// Whether the password is the user's current one or one of the newest
+    /// `history_limit` entries of their password history.
+    fn is_recent_password(&self, user: &User, password: &str, history_limit: usize) -> Result<bool, TenetError> {
+        if user.verify_password(password)? {
+            return Ok(true);
+        }
+        for entry in self.repository.find_password_history(user.id, history_limit)? {
+            if entry.encryption_mode.verify_password(&entry.password, password)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Verifies the password against a hash no user has, so that rejecting an
+    /// unknown user takes as long as rejecting a wrong password.
+    pub fn verify_dummy_password(&self, password: &str) {
+        let hash = self.dummy_password_hash.get_or_init(|| {
+            EncryptionModes::Argon2.hash_password_with(&session::random_token(), &self.password_hash_config).unwrap_or_default()
+        });
+        let _ = EncryptionModes::Argon2.verify_password(hash, password);
+    }
+
+    /// Re-hashes the password of a user who just signed in with it if the
+    /// stored hash is outdated. A failure to store the new hash is logged and
+    /// the user is returned unchanged, the sign-in still succeeds.
+    pub fn rehash_password(&self, user: User, password: &str) -> User {
+        if !self.password_hash_config.needs_rehash(&user.encryption_mode, &user.password) {
+            return user;
+        }
+        let rehashed = EncryptionModes::Argon2.hash_password_with(password, &self.password_hash_config)
+            .and_then(|password| self.repository.update_user(&User { password, encryption_mode: EncryptionModes::Argon2, ..user.clone() }));
+        match rehashed {
+            Ok(rehashed) => rehashed,
+            Err(e) => {
+                warn!("Could not re-hash the password of user {}: {}", user.id, e);
+                user
+            }
+        }
+    }
+}
@@ -1,8 +1,24 @@
 use std::{str::FromStr, fmt::Display};
 
+use argon2::{Config, Variant, Version};
+use pbkdf2::password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use rand::Rng;
+
+use crate::TenetError;
+
+/// The password hashing schemes a user's password can be stored with.
+///
+/// New users should use `Argon2`. The other schemes exist for users imported
+/// from systems that used them, their hashes are verified as they are.
 #[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize, PartialEq, PartialOrd)]
 pub enum EncryptionModes {
-    Argon2
+    Argon2,
+    /// Hashes in the modular crypt format, e.g. `$2b$12$...`
+    Bcrypt,
+    /// Hashes in the PHC string format, e.g. `$pbkdf2-sha256$i=600000,l=32$...`
+    Pbkdf2Sha256,
+    /// Hashes in the PHC string format, e.g. `$scrypt$ln=17,r=8,p=1$...`
+    Scrypt
 }
 
 
@@ -12,11 +28,107 @@ impl FromStr for EncryptionModes {
     fn from_str(input: &str) -> Result<EncryptionModes, Self::Err> {
         match input {
             "Argon2"  => Ok(EncryptionModes::Argon2),
+            "Bcrypt"  => Ok(EncryptionModes::Bcrypt),
+            "Pbkdf2Sha256" => Ok(EncryptionModes::Pbkdf2Sha256),
+            "Scrypt"  => Ok(EncryptionModes::Scrypt),
             _      => Err(()),
         }
     }
 }
 
+impl EncryptionModes {
+    /// Hashes a plaintext password with this encryption mode and the default
+    /// [`PasswordHashConfig`].
+    pub fn hash_password(&self, password: &str) -> Result<String, TenetError> {
+        self.hash_password_with(password, &PasswordHashConfig::default())
+    }
+
+    /// Hashes a plaintext password with this encryption mode. The config only
+    /// applies to `Argon2`, the other modes use their crates' defaults.
+    pub fn hash_password_with(&self, password: &str, config: &PasswordHashConfig) -> Result<String, TenetError> {
+        match self {
+            EncryptionModes::Argon2 => {
+                let salt: [u8; 32] = rand::rng().random();
+                Ok(argon2::hash_encoded(password.as_bytes(), &salt, &config.argon2())?)
+            },
+            EncryptionModes::Bcrypt => Ok(bcrypt::hash(password, bcrypt::DEFAULT_COST)?),
+            EncryptionModes::Pbkdf2Sha256 => Ok(pbkdf2::Pbkdf2.hash_password(password.as_bytes(), &salt()?)?.to_string()),
+            EncryptionModes::Scrypt => Ok(scrypt::Scrypt.hash_password(password.as_bytes(), &salt()?)?.to_string())
+        }
+    }
+
+    /// Checks a plaintext password against a hash created with this encryption mode.
+    pub fn verify_password(&self, hash: &str, password: &str) -> Result<bool, TenetError> {
+        match self {
+            EncryptionModes::Argon2 => Ok(argon2::verify_encoded(hash, password.as_bytes())?),
+            EncryptionModes::Bcrypt => Ok(bcrypt::verify(password, hash)?),
+            EncryptionModes::Pbkdf2Sha256 => verify_phc(&pbkdf2::Pbkdf2, hash, password),
+            EncryptionModes::Scrypt => verify_phc(&scrypt::Scrypt, hash, password)
+        }
+    }
+}
+
+/// Argon2id parameters new passwords are hashed with, see
+/// [`crate::Tenet::with_password_hash_config`].
+///
+/// Passwords hashed with other parameters or another [`EncryptionModes`] are
+/// re-hashed with these on the user's next successful sign-in.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct PasswordHashConfig {
+    /// Memory in KiB
+    pub memory_cost: u32,
+    /// Number of passes over the memory
+    pub time_cost: u32,
+    /// Number of lanes
+    pub parallelism: u32
+}
+
+impl Default for PasswordHashConfig {
+    /// One of the settings recommended by OWASP: 19 MiB, two passes, one lane.
+    fn default() -> Self {
+        PasswordHashConfig {
+            memory_cost: 19456,
+            time_cost: 2,
+            parallelism: 1
+        }
+    }
+}
+
+impl PasswordHashConfig {
+    fn argon2(&self) -> Config<'static> {
+        Config {
+            lanes: self.parallelism,
+            mem_cost: self.memory_cost,
+            time_cost: self.time_cost,
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            ..Config::default()
+        }
+    }
+
+    /// Whether a hash stored with `encryption_mode` was created with
+    /// different settings than these.
+    pub fn needs_rehash(&self, encryption_mode: &EncryptionModes, hash: &str) -> bool {
+        let prefix = format!("$argon2id$v=19$m={},t={},p={}$", self.memory_cost, self.time_cost, self.parallelism);
+        *encryption_mode != EncryptionModes::Argon2 || !hash.starts_with(&prefix)
+    }
+}
+
+
+fn salt() -> Result<SaltString, TenetError> {
+    let salt: [u8; 16] = rand::rng().random();
+    Ok(SaltString::encode_b64(&salt)?)
+}
+
+/// Verifies a PHC string, a mismatching password is not an error.
+fn verify_phc(verifier: &impl PasswordVerifier, hash: &str, password: &str) -> Result<bool, TenetError> {
+    match verifier.verify_password(password.as_bytes(), &PasswordHash::new(hash)?) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(e.into())
+    }
+}
+
 impl Display for EncryptionModes {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
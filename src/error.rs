@@ -25,10 +25,93 @@ pub enum TenetError {
     /// Error hashing passwords
     #[error("Password hashing Error")]
     PasswordHashingError(#[from] argon2::Error),
+
+    /// Error hashing passwords with bcrypt
+    #[error("Password hashing Error")]
+    BcryptHashingError(#[from] bcrypt::BcryptError),
+
+    /// Error hashing passwords with PBKDF2 or scrypt
+    #[error("Password hashing Error")]
+    PhcHashingError(#[from] pbkdf2::password_hash::Error),
     
+    /// Error signing or verifying a JWT
+    #[cfg(feature = "jwt")]
+    #[error("JWT Error")]
+    JwtError(#[from] jsonwebtoken::errors::Error),
+
+    /// Error getting a connection from the async connection pool
+    #[cfg(feature = "async")]
+    #[error("Database Connection Error")]
+    AsyncDatabaseConnectionError(#[from] diesel_async::pooled_connection::deadpool::PoolError),
+
+    /// A blocking task, e.g. password hashing, could not be completed
+    #[cfg(feature = "async")]
+    #[error("Blocking task failed")]
+    BlockingTaskError(#[from] tokio::task::JoinError),
+
     /// The requested resource was not found
     #[error("Not found")]
     NotFoundError,
+
+    /// The value is not connected to a repository
+    #[error("Not connected to a repository")]
+    NotConnectedError,
+
+    /// The token is malformed, was not issued by this instance, is unknown or
+    /// belongs to an invitation that has already been accepted
+    #[error("Invalid token")]
+    InvalidTokenError,
+
+    /// The session or refresh token has expired
+    #[error("Session expired")]
+    SessionExpiredError,
+
+    /// The session or refresh token has been revoked
+    #[error("Session revoked")]
+    SessionRevokedError,
+
+    /// The invitation has expired
+    #[error("Invitation expired")]
+    InvitationExpiredError,
+
+    /// The invitation has been revoked
+    #[error("Invitation revoked")]
+    InvitationRevokedError,
+
+    /// The email verification or password reset token has expired
+    #[error("Token expired")]
+    TokenExpiredError,
+
+    /// The flow can only deliver its token by mail, but no mailer is set
+    #[error("No mailer configured")]
+    NoMailerError,
+
+    /// An already used refresh token was presented again; its family has been revoked
+    #[error("Refresh token reused")]
+    RefreshTokenReusedError,
+
+    /// Built-in role definitions cannot be changed or deleted
+    #[error("Built-in role definitions cannot be changed")]
+    BuiltinRoleDefinitionError,
+
+    /// The current password given to confirm a change is wrong
+    #[error("Invalid password")]
+    InvalidPasswordError,
+
+    /// The new password breaks the rules of the tenant's password policy
+    #[error("Password violates the policy: {}", .0.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "))]
+    PasswordPolicyError(Vec<crate::PasswordViolation>),
+
+    /// The mailer could not send a mail
+    #[error("Sending mail failed")]
+    MailError(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl TenetError {
+    /// Whether the error reports a missing record, from either a backend or Tenet itself.
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(self, TenetError::NotFoundError | TenetError::DatabaseError(diesel::result::Error::NotFound))
+    }
 }
 
 
@@ -0,0 +1,27 @@
+use chrono::{NaiveDateTime, Utc};
+
+
+/// A named set of users within a tenant, e.g. a team or department.
+///
+/// Roles assigned to a group with [`crate::Role::for_group`] are inherited by
+/// all of its members.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct Group {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<uuid::Uuid>
+}
+
+impl Group {
+    pub fn new(name: String, tenant_id: uuid::Uuid) -> Self {
+        Group {
+            id: uuid::Uuid::new_v4(),
+            name,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            db_tenant_id: Some(tenant_id)
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use chrono::Utc;
+use tonic::service::{interceptor::InterceptedService, Interceptor};
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{encryption_modes::EncryptionModes, role_type::RoleType, session::AdminToken, Tenant, Tenet, TenetError};
+use user_tenant_service::user_tenant_service_server::{UserTenantService, UserTenantServiceServer};
+use user_tenant_service::{
+    AuthenticationStatus, AuthorizationRequest, AuthorizationStatus, Credentials, Empty, Roles, SessionRequest, TenantList,
+    TenantReply, TenantRequest, UserList, UserReply, UserRequest, UserUpdate
+};
+
+
+/// Generated message types, client and server for `proto/usertenants.proto`.
+pub mod user_tenant_service {
+    tonic::include_proto!("usertenants");
+}
+
+
+/// gRPC `UserTenantService` on top of a [`Tenet`] instance.
+///
+/// All calls are served from the repository the given `Tenet` was built with,
+/// so the service works against any backend. Repository access and password
+/// hashing run on tokio's blocking thread pool.
+///
+/// A successful `Authenticate` creates a session in the user's tenant and
+/// returns its token; `IsAuthenticated` validates such a token with
+/// [`Tenant::validate_session`]. Expiry and renewal follow the session
+/// configuration of the `Tenet` instance, so sessions are shared with every
+/// other service on the same repository. `IsAuthorized` resolves the user
+/// from such a token as well.
+///
+/// The tenant and user administration calls need the admin token in the
+/// `authorization` metadata, as `Bearer <token>`. An empty admin token locks
+/// them completely.
+///
+/// Only available with the `grpc` cargo feature.
+#[derive(Debug, Clone)]
+pub struct Service {
+    tenet: Tenet,
+    admin_token: AdminToken
+}
+
+
+impl Service {
+    /// Creates a new service serving the data of the given Tenet instance,
+    /// with the administration calls guarded by `admin_token`.
+    pub fn new(tenet: Tenet, admin_token: &str) -> Self {
+        Service { tenet, admin_token: AdminToken::new(admin_token) }
+    }
+
+    /// Wraps the service into a tonic server that checks the admin token,
+    /// ready to be added to a router.
+    pub fn into_server(self) -> InterceptedService<UserTenantServiceServer<Self>, AdminInterceptor> {
+        let interceptor = AdminInterceptor { admin_token: self.admin_token.clone() };
+        UserTenantServiceServer::with_interceptor(self, interceptor)
+    }
+
+    /// Runs blocking Tenet calls off the async runtime.
+    async fn blocking<T, F>(&self, f: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(Tenet) -> Result<T, Status> + Send + 'static
+    {
+        let tenet = self.tenet.clone();
+        tokio::task::spawn_blocking(move || f(tenet))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+    }
+}
+
+
+/// Marks requests that carry the admin token, see [`Service::into_server`].
+#[derive(Debug, Clone)]
+pub struct AdminInterceptor {
+    admin_token: AdminToken
+}
+
+/// Request extension set by [`AdminInterceptor`] for requests with a valid admin token.
+#[derive(Clone)]
+struct Admin;
+
+impl Interceptor for AdminInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let authorized = request.metadata().get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| self.admin_token.matches(token));
+        if authorized {
+            request.extensions_mut().insert(Admin);
+        }
+        Ok(request)
+    }
+}
+
+fn require_admin<T>(request: &Request<T>) -> Result<(), Status> {
+    match request.extensions().get::<Admin>() {
+        Some(_) => Ok(()),
+        None => Err(Status::unauthenticated("Missing or invalid admin token"))
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(id).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+fn find_tenant(tenet: &Tenet, tenant_id: Uuid) -> Result<Tenant, Status> {
+    tenet.get_tenant_by_id(tenant_id).ok_or_else(|| Status::not_found(TenetError::NotFoundError.to_string()))
+}
+
+fn status(error: TenetError) -> Status {
+    use diesel::result::{DatabaseErrorKind, Error};
+
+    match error {
+        TenetError::NotFoundError | TenetError::DatabaseError(Error::NotFound) => Status::not_found(error.to_string()),
+        TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Status::already_exists(error.to_string()),
+        TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => Status::failed_precondition(error.to_string()),
+        TenetError::PasswordPolicyError(_) => Status::invalid_argument(error.to_string()),
+        _ => Status::internal(error.to_string())
+    }
+}
+
+
+impl From<&Tenant> for user_tenant_service::Tenant {
+    fn from(tenant: &Tenant) -> Self {
+        user_tenant_service::Tenant {
+            id: tenant.id.to_string(),
+            title: tenant.title.clone()
+        }
+    }
+}
+
+impl From<&crate::User> for user_tenant_service::User {
+    fn from(user: &crate::User) -> Self {
+        user_tenant_service::User {
+            id: user.id.to_string(),
+            email: user.email.clone(),
+            email_verified: user.email_verified,
+            password: String::new(), // Never hand out password hashes
+            full_name: user.full_name.clone(),
+            tenant_id: user.db_tenant_id.map(|id| id.to_string()).unwrap_or_default()
+        }
+    }
+}
+
+impl From<RoleType> for Roles {
+    fn from(role_type: RoleType) -> Self {
+        match role_type {
+            RoleType::Administrator => Roles::Administrator,
+            RoleType::User => Roles::User
+        }
+    }
+}
+
+
+#[tonic::async_trait]
+impl UserTenantService for Service {
+
+    async fn get_all_tenants(&self, request: Request<Empty>) -> Result<Response<TenantList>, Status> {
+        require_admin(&request)?;
+        let tenants = self.blocking(|tenet| {
+            Ok(tenet.get_tenant_ids().into_iter()
+                .filter_map(|id| tenet.get_tenant_by_id(id))
+                .map(|t| user_tenant_service::Tenant::from(&t))
+                .collect())
+        }).await?;
+
+        Ok(Response::new(TenantList { tenants }))
+    }
+
+
+    async fn get_tenant(&self, request: Request<TenantRequest>) -> Result<Response<user_tenant_service::Tenant>, Status> {
+        require_admin(&request)?;
+        let id = parse_id(&request.into_inner().id)?;
+
+        let tenant = self.blocking(move |tenet| find_tenant(&tenet, id)).await?;
+        Ok(Response::new(user_tenant_service::Tenant::from(&tenant)))
+    }
+
+
+    async fn get_tenant_by_user(&self, request: Request<user_tenant_service::User>) -> Result<Response<user_tenant_service::Tenant>, Status> {
+        require_admin(&request)?;
+        let user = request.into_inner();
+
+        let tenant = self.blocking(move |tenet| {
+            if user.tenant_id.is_empty() {
+                tenet.get_tenant_by_username(user.email).map_err(status)
+            } else {
+                find_tenant(&tenet, parse_id(&user.tenant_id)?)
+            }
+        }).await?;
+        Ok(Response::new(user_tenant_service::Tenant::from(&tenant)))
+    }
+
+
+    async fn create_tenant(&self, request: Request<user_tenant_service::Tenant>) -> Result<Response<user_tenant_service::Tenant>, Status> {
+        require_admin(&request)?;
+        let title = request.into_inner().title;
+
+        let tenant = self.blocking(move |tenet| tenet.create_tenant(title).map_err(status)).await?;
+        Ok(Response::new(user_tenant_service::Tenant::from(&tenant)))
+    }
+
+
+    async fn update_tenant(&self, request: Request<user_tenant_service::Tenant>) -> Result<Response<user_tenant_service::Tenant>, Status> {
+        require_admin(&request)?;
+        let tenant_request = request.into_inner();
+        let id = parse_id(&tenant_request.id)?;
+
+        let tenant = self.blocking(move |tenet| tenet.set_tenant_title(id, tenant_request.title).map_err(status)).await?;
+        Ok(Response::new(user_tenant_service::Tenant::from(&tenant)))
+    }
+
+
+    async fn delete_tenant(&self, request: Request<TenantRequest>) -> Result<Response<TenantReply>, Status> {
+        require_admin(&request)?;
+        let request_id = request.into_inner().id;
+        let id = parse_id(&request_id)?;
+
+        self.blocking(move |tenet| {
+            find_tenant(&tenet, id)?;
+            tenet.delete_tenant(id).map_err(status)
+        }).await?;
+        Ok(Response::new(TenantReply { id: request_id, success: true }))
+    }
+
+
+    async fn get_all_users(&self, request: Request<Empty>) -> Result<Response<UserList>, Status> {
+        require_admin(&request)?;
+        let users = self.blocking(|tenet| {
+            Ok(tenet.get_tenant_ids().into_iter()
+                .filter_map(|id| tenet.get_tenant_by_id(id))
+                .flat_map(|t| t.get_users())
+                .map(|u| user_tenant_service::User::from(&u))
+                .collect())
+        }).await?;
+
+        Ok(Response::new(UserList { users }))
+    }
+
+
+    async fn get_users_by_tenant(&self, request: Request<user_tenant_service::Tenant>) -> Result<Response<UserList>, Status> {
+        require_admin(&request)?;
+        let tenant_id = parse_id(&request.into_inner().id)?;
+
+        let users = self.blocking(move |tenet| {
+            let tenant = find_tenant(&tenet, tenant_id)?;
+            Ok(tenant.get_users().iter().map(user_tenant_service::User::from).collect())
+        }).await?;
+        Ok(Response::new(UserList { users }))
+    }
+
+
+    async fn get_user(&self, request: Request<UserRequest>) -> Result<Response<user_tenant_service::User>, Status> {
+        require_admin(&request)?;
+        let user_request = request.into_inner();
+        let tenant_id = parse_id(&user_request.tenant_id)?;
+        let user_id = parse_id(&user_request.id)?;
+
+        let user = self.blocking(move |tenet| find_tenant(&tenet, tenant_id)?.get_user_by_id(user_id).map_err(status)).await?;
+        Ok(Response::new(user_tenant_service::User::from(&user)))
+    }
+
+
+    async fn create_user(&self, request: Request<user_tenant_service::User>) -> Result<Response<user_tenant_service::User>, Status> {
+        require_admin(&request)?;
+        let user_request = request.into_inner();
+        let tenant_id = parse_id(&user_request.tenant_id)?;
+        if user_request.password.is_empty() {
+            return Err(Status::invalid_argument("password must not be empty"));
+        }
+
+        let user = self.blocking(move |tenet| {
+            let tenant = find_tenant(&tenet, tenant_id)?;
+            let user = crate::User::new(
+                user_request.email.clone(),
+                user_request.full_name,
+                user_request.password,
+                EncryptionModes::Argon2,
+                user_request.email,
+                user_request.email_verified,
+                tenant_id);
+            tenant.add_user(&user).map_err(status)
+        }).await?;
+        Ok(Response::new(user_tenant_service::User::from(&user)))
+    }
+
+
+    async fn update_user(&self, request: Request<UserUpdate>) -> Result<Response<user_tenant_service::User>, Status> {
+        require_admin(&request)?;
+        let user_request = request.into_inner();
+        let tenant_id = parse_id(&user_request.tenant_id)?;
+        let user_id = parse_id(&user_request.id)?;
+
+        let user = self.blocking(move |tenet| {
+            let tenant = find_tenant(&tenet, tenant_id)?;
+            let mut user = tenant.get_user_by_id(user_id).map_err(status)?;
+            if let Some(email) = user_request.email {
+                user.username = email.clone();
+                user.email = email;
+            }
+            if let Some(full_name) = user_request.full_name {
+                user.full_name = full_name;
+            }
+            if let Some(email_verified) = user_request.email_verified {
+                user.email_verified = email_verified;
+            }
+            user.updated_at = Some(Utc::now().naive_utc());
+            tenant.update_user(&user).map_err(status)
+        }).await?;
+        Ok(Response::new(user_tenant_service::User::from(&user)))
+    }
+
+
+    async fn delete_user(&self, request: Request<UserRequest>) -> Result<Response<UserReply>, Status> {
+        require_admin(&request)?;
+        let user_request = request.into_inner();
+        let tenant_id = parse_id(&user_request.tenant_id)?;
+        let user_id = parse_id(&user_request.id)?;
+
+        self.blocking(move |tenet| {
+            let tenant = find_tenant(&tenet, tenant_id)?;
+            tenant.get_user_by_id(user_id).map_err(status)?;
+            tenant.delete_user(user_id).map_err(status)
+        }).await?;
+
+        Ok(Response::new(UserReply { id: user_request.id, success: true, token: String::new() }))
+    }
+
+
+    async fn authenticate(&self, request: Request<Credentials>) -> Result<Response<UserReply>, Status> {
+        let credentials = request.into_inner();
+
+        let reply = self.blocking(move |tenet| {
+            let tenant = tenet.get_tenant_by_username(credentials.email.clone()).map_err(status)?;
+            match tenant.authenticate_user(credentials.email, credentials.password) {
+                Some(user) => {
+                    let issued = tenant.create_session(user.id).map_err(status)?;
+                    Ok(UserReply { id: user.id.to_string(), success: true, token: issued.token })
+                },
+                None => Ok(UserReply::default())
+            }
+        }).await?;
+        Ok(Response::new(reply))
+    }
+
+
+    async fn is_authenticated(&self, request: Request<SessionRequest>) -> Result<Response<AuthenticationStatus>, Status> {
+        let session_request = request.into_inner();
+        let tenant_id = parse_id(&session_request.tenant_id)?;
+
+        let authentication_status = self.blocking(move |tenet| {
+            match find_tenant(&tenet, tenant_id)?.validate_session(&session_request.token) {
+                Ok(session) => Ok(AuthenticationStatus { status: true, user_id: session.user_id.to_string() }),
+                Err(TenetError::InvalidTokenError | TenetError::SessionExpiredError | TenetError::SessionRevokedError) => Ok(AuthenticationStatus::default()),
+                Err(e) => Err(status(e))
+            }
+        }).await?;
+        Ok(Response::new(authentication_status))
+    }
+
+
+    async fn is_authorized(&self, request: Request<AuthorizationRequest>) -> Result<Response<AuthorizationStatus>, Status> {
+        let authorization_request = request.into_inner();
+        let tenant_id = parse_id(&authorization_request.tenant_id)?;
+        let application_id = parse_id(&authorization_request.application_id)?;
+
+        let role = self.blocking(move |tenet| {
+            let tenant = find_tenant(&tenet, tenant_id)?;
+            let session = match tenant.validate_session(&authorization_request.token) {
+                Ok(session) => session,
+                Err(e @ (TenetError::InvalidTokenError | TenetError::SessionExpiredError | TenetError::SessionRevokedError)) => {
+                    return Err(Status::unauthenticated(e.to_string()))
+                },
+                Err(e) => return Err(status(e))
+            };
+            let role = tenant.effective_role(session.user_id, application_id).map_err(status)?;
+
+            Ok(role.map(|r| Roles::from(r.role_type)).unwrap_or(Roles::None))
+        }).await?;
+
+        Ok(Response::new(AuthorizationStatus { role: role as i32 }))
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{NaiveDateTime, Utc};
+
+use crate::{role_type::RoleType, Role, RoleDefinition};
+
+
+/// An invitation to join a tenant, created by [`crate::Tenant::invite_user`].
+///
+/// The invited person sets their password and name with
+/// [`crate::Tenant::accept_invitation`], which creates the user, or adds an
+/// existing user of the same email as a member, and assigns `roles`.
+/// Invitations are single use and only a SHA-256 hash of the token is stored.
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct Invitation {
+    pub id: uuid::Uuid,
+    pub email: String,
+    /// Roles assigned to the user on acceptance
+    pub roles: Vec<InvitationRole>,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<uuid::Uuid>
+}
+
+impl Invitation {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().naive_utc()
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.accepted_at.is_some()
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// Whether the invitation can still be accepted.
+    pub fn is_pending(&self) -> bool {
+        !self.is_accepted() && !self.is_revoked() && !self.is_expired()
+    }
+}
+
+
+/// A role pre-assigned to an invited user, see [`Invitation::roles`].
+#[derive(Debug, Clone, Copy, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct InvitationRole {
+    pub role_definition_id: uuid::Uuid,
+    /// `None` for tenant-wide roles
+    pub application_id: Option<uuid::Uuid>
+}
+
+impl InvitationRole {
+    pub fn new(role_definition: &RoleDefinition, application_id: uuid::Uuid) -> Self {
+        InvitationRole {
+            role_definition_id: role_definition.id,
+            application_id: Some(application_id)
+        }
+    }
+
+    pub fn tenant_wide(role_definition: &RoleDefinition) -> Self {
+        InvitationRole {
+            role_definition_id: role_definition.id,
+            application_id: None
+        }
+    }
+
+    /// The role of the user who accepted the invitation. Its `role_type` is
+    /// filled in by [`crate::Tenant::add_role`].
+    pub(crate) fn to_role(self, user_id: uuid::Uuid, tenant_id: uuid::Uuid) -> Role {
+        Role {
+            role_definition_id: Some(self.role_definition_id),
+            application_id: self.application_id,
+            ..Role::new_tenant_wide(RoleType::User, user_id, tenant_id)
+        }
+    }
+}
+
+
+/// A freshly created invitation together with the token itself.
+///
+/// The token is not stored anywhere. It has already been mailed to the
+/// invited person if the [`crate::Tenet`] instance has a mailer, otherwise
+/// this is the only chance to send it, e.g. as part of a link.
+#[derive(Debug, Clone)]
+pub struct IssuedInvitation {
+    pub token: String,
+    pub invitation: Invitation
+}
@@ -0,0 +1,322 @@
+//! JWT access tokens carrying a user's tenant and roles.
+//!
+//! A [`JwtIssuer`] signs tokens for authenticated users, see
+//! [`crate::Tenant::issue_access_token`]. Downstream services check them with a
+//! [`JwtVerifier`] holding the matching secret or public key, without asking
+//! Tenet's database.
+//!
+//! Only available with the `jwt` cargo feature.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use uuid::Uuid;
+
+use crate::{role_type::RoleType, Role, TenetError};
+
+
+/// Default lifetime of issued access tokens.
+pub const DEFAULT_ACCESS_TOKEN_LIFETIME: Duration = Duration::minutes(15);
+
+
+/// Claims of an access token.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct AccessClaims {
+    /// Id of the user
+    pub sub: Uuid,
+    pub tenant_id: Uuid,
+    /// Roles of the user, by application id
+    pub roles: BTreeMap<Uuid, Vec<RoleType>>,
+    /// Tenant-wide roles of the user, which apply to every application
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tenant_roles: Vec<RoleType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    pub iat: i64,
+    pub exp: i64
+}
+
+impl AccessClaims {
+    pub(crate) fn new(user_id: Uuid, tenant_id: Uuid, roles: &[Role], lifetime: Duration) -> Self {
+        let mut roles_by_application: BTreeMap<Uuid, Vec<RoleType>> = BTreeMap::new();
+        let mut tenant_roles = Vec::new();
+        for role in roles {
+            match role.application_id {
+                Some(application_id) => roles_by_application.entry(application_id).or_default().push(role.role_type),
+                None => tenant_roles.push(role.role_type)
+            }
+        }
+
+        let now = Utc::now();
+        AccessClaims {
+            sub: user_id,
+            tenant_id,
+            roles: roles_by_application,
+            tenant_roles,
+            iss: None,
+            iat: now.timestamp(),
+            exp: (now + lifetime).timestamp()
+        }
+    }
+
+    /// Returns the roles of the user for the given application, without
+    /// tenant-wide roles.
+    pub fn roles_for(&self, application_id: Uuid) -> &[RoleType] {
+        self.roles.get(&application_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns whether the user has the given role for the application, either
+    /// directly or tenant-wide.
+    pub fn has_role(&self, application_id: Uuid, role_type: RoleType) -> bool {
+        self.roles_for(application_id).contains(&role_type) || self.tenant_roles.contains(&role_type)
+    }
+}
+
+
+/// Signs access tokens.
+#[derive(Clone)]
+pub struct JwtIssuer {
+    algorithm: Algorithm,
+    key: EncodingKey,
+    issuer: Option<String>,
+    lifetime: Duration
+}
+
+impl std::fmt::Debug for JwtIssuer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtIssuer")
+            .field("algorithm", &self.algorithm)
+            .field("issuer", &self.issuer)
+            .field("lifetime", &self.lifetime)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JwtIssuer {
+    fn new(algorithm: Algorithm, key: EncodingKey) -> Self {
+        JwtIssuer { algorithm, key, issuer: None, lifetime: DEFAULT_ACCESS_TOKEN_LIFETIME }
+    }
+
+    /// Signs with HMAC-SHA256 and a shared secret.
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(Algorithm::HS256, EncodingKey::from_secret(secret))
+    }
+
+    /// Signs with RSA-SHA256, using a PEM encoded private key file.
+    pub fn rs256_from_pem_file(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        Ok(Self::new(Algorithm::RS256, EncodingKey::from_rsa_pem(&std::fs::read(path)?)?))
+    }
+
+    /// Signs with Ed25519, using a PEM encoded private key file.
+    pub fn eddsa_from_pem_file(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        Ok(Self::new(Algorithm::EdDSA, EncodingKey::from_ed_pem(&std::fs::read(path)?)?))
+    }
+
+    /// Sets the `iss` claim of issued tokens.
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Sets how long issued tokens are valid.
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    pub(crate) fn issue(&self, user_id: Uuid, tenant_id: Uuid, roles: &[Role]) -> Result<String, TenetError> {
+        let claims = AccessClaims {
+            iss: self.issuer.clone(),
+            ..AccessClaims::new(user_id, tenant_id, roles, self.lifetime)
+        };
+        Ok(jsonwebtoken::encode(&Header::new(self.algorithm), &claims, &self.key)?)
+    }
+}
+
+
+/// Checks access tokens issued by a [`JwtIssuer`].
+#[derive(Clone)]
+pub struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation
+}
+
+impl std::fmt::Debug for JwtVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtVerifier")
+            .field("algorithms", &self.validation.algorithms)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JwtVerifier {
+    fn new(algorithm: Algorithm, key: DecodingKey) -> Self {
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = 0;
+        JwtVerifier { key, validation }
+    }
+
+    /// Verifies HMAC-SHA256 signatures with a shared secret.
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(Algorithm::HS256, DecodingKey::from_secret(secret))
+    }
+
+    /// Verifies RSA-SHA256 signatures, using a PEM encoded public key file.
+    pub fn rs256_from_pem_file(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        Ok(Self::new(Algorithm::RS256, DecodingKey::from_rsa_pem(&std::fs::read(path)?)?))
+    }
+
+    /// Verifies Ed25519 signatures, using a PEM encoded public key file.
+    pub fn eddsa_from_pem_file(path: impl AsRef<Path>) -> Result<Self, TenetError> {
+        Ok(Self::new(Algorithm::EdDSA, DecodingKey::from_ed_pem(&std::fs::read(path)?)?))
+    }
+
+    /// Only accepts tokens with the given `iss` claim.
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.validation.set_issuer(&[issuer]);
+        self
+    }
+
+    /// Accepts tokens up to the given number of seconds after their expiry,
+    /// to allow for clock skew between services.
+    pub fn with_leeway(mut self, seconds: u64) -> Self {
+        self.validation.leeway = seconds;
+        self
+    }
+
+    /// Checks signature and expiry of the token and that it was issued for the
+    /// given tenant, and returns its claims.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError::JwtError` if the signature, expiry or issuer is
+    /// not valid, and a `TenetError::InvalidTokenError` if the token belongs to
+    /// another tenant.
+    pub fn verify(&self, token: &str, tenant_id: Uuid) -> Result<AccessClaims, TenetError> {
+        let claims = jsonwebtoken::decode::<AccessClaims>(token, &self.key, &self.validation)?.claims;
+        if claims.tenant_id != tenant_id {
+            return Err(TenetError::InvalidTokenError);
+        }
+        Ok(claims)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::errors::ErrorKind;
+
+    use crate::application_type::ApplicationType;
+    use crate::{Application, Group, Storage, Tenet};
+    use crate::test_suite::user;
+
+    use super::*;
+
+    fn key_file(name: &str) -> String {
+        format!("{}/tests/keys/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn issue_and_verify(issuer: JwtIssuer, verifier: JwtVerifier) {
+        let tenet = Tenet::in_memory();
+        let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+        let other_tenant = tenet.create_tenant("Other Tenant".to_string()).unwrap();
+        let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+        let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+        let user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+        tenant.add_role(&Role::new(RoleType::Administrator, user.id, application.id, tenant.id)).unwrap();
+
+        let token = tenant.issue_access_token(user.id, &issuer).unwrap();
+        let claims = verifier.verify(&token, tenant.id).unwrap();
+        assert_eq!(user.id, claims.sub);
+        assert_eq!(tenant.id, claims.tenant_id);
+        assert!(claims.has_role(application.id, RoleType::Administrator));
+        assert!(!claims.has_role(application.id, RoleType::User));
+        assert!(claims.roles_for(Uuid::new_v4()).is_empty());
+
+        assert!(matches!(verifier.verify(&token, other_tenant.id), Err(TenetError::InvalidTokenError)));
+        assert!(other_tenant.issue_access_token(user.id, &issuer).is_err());
+    }
+
+    #[test]
+    fn hs256_test() {
+        issue_and_verify(JwtIssuer::hs256(b"secret"), JwtVerifier::hs256(b"secret"));
+
+        let token = JwtIssuer::hs256(b"secret").issue(Uuid::new_v4(), Uuid::nil(), &[]).unwrap();
+        let result = JwtVerifier::hs256(b"other").verify(&token, Uuid::nil());
+        assert!(matches!(result, Err(TenetError::JwtError(e)) if *e.kind() == ErrorKind::InvalidSignature));
+    }
+
+    #[test]
+    fn rs256_test() {
+        issue_and_verify(
+            JwtIssuer::rs256_from_pem_file(key_file("rsa_private.pem")).unwrap(),
+            JwtVerifier::rs256_from_pem_file(key_file("rsa_public.pem")).unwrap());
+    }
+
+    #[test]
+    fn eddsa_test() {
+        issue_and_verify(
+            JwtIssuer::eddsa_from_pem_file(key_file("ed25519_private.pem")).unwrap(),
+            JwtVerifier::eddsa_from_pem_file(key_file("ed25519_public.pem")).unwrap());
+
+        let token = JwtIssuer::eddsa_from_pem_file(key_file("ed25519_private.pem")).unwrap().issue(Uuid::new_v4(), Uuid::nil(), &[]).unwrap();
+        let result = JwtVerifier::rs256_from_pem_file(key_file("rsa_public.pem")).unwrap().verify(&token, Uuid::nil());
+        assert!(matches!(result, Err(TenetError::JwtError(_))));
+    }
+
+    #[test]
+    fn tenant_roles_test() {
+        let application_id = Uuid::new_v4();
+        let tenant_id = Uuid::new_v4();
+        let roles = [
+            Role::new(RoleType::User, Uuid::new_v4(), application_id, tenant_id),
+            Role::new_tenant_wide(RoleType::Administrator, Uuid::new_v4(), tenant_id)
+        ];
+
+        let token = JwtIssuer::hs256(b"secret").issue(Uuid::new_v4(), tenant_id, &roles).unwrap();
+        let claims = JwtVerifier::hs256(b"secret").verify(&token, tenant_id).unwrap();
+        assert_eq!(vec![RoleType::Administrator], claims.tenant_roles);
+        assert_eq!(&[RoleType::User], claims.roles_for(application_id));
+        assert!(claims.has_role(application_id, RoleType::Administrator));
+        assert!(claims.has_role(Uuid::new_v4(), RoleType::Administrator));
+        assert!(!claims.has_role(Uuid::new_v4(), RoleType::User));
+    }
+
+    #[test]
+    fn group_roles_test() {
+        let tenet = Tenet::in_memory();
+        let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+        let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+        let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+        let user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+        let staff = tenant.add_group(&Group::new("Staff".to_string(), tenant.id)).unwrap();
+        tenant.add_user_to_group(staff.id, user.id).unwrap();
+        let user_definition = tenant.get_role_definitions().into_iter().find(|d| d.name == "User").unwrap();
+        tenant.add_role(&Role::for_group(&user_definition, staff.id, Some(application.id), tenant.id)).unwrap();
+
+        let issuer = JwtIssuer::hs256(b"secret");
+        let token = tenant.issue_access_token(user.id, &issuer).unwrap();
+        let claims = JwtVerifier::hs256(b"secret").verify(&token, tenant.id).unwrap();
+        assert_eq!(&[RoleType::User], claims.roles_for(application.id));
+    }
+
+    #[test]
+    fn expiry_and_issuer_test() {
+        let expired = JwtIssuer::hs256(b"secret").with_lifetime(Duration::seconds(-1)).issue(Uuid::new_v4(), Uuid::nil(), &[]).unwrap();
+        let result = JwtVerifier::hs256(b"secret").verify(&expired, Uuid::nil());
+        assert!(matches!(result, Err(TenetError::JwtError(e)) if *e.kind() == ErrorKind::ExpiredSignature));
+        assert!(JwtVerifier::hs256(b"secret").with_leeway(60).verify(&expired, Uuid::nil()).is_ok());
+
+        let token = JwtIssuer::hs256(b"secret").with_issuer("tenet").issue(Uuid::new_v4(), Uuid::nil(), &[]).unwrap();
+        assert_eq!(Some("tenet".to_string()), JwtVerifier::hs256(b"secret").with_issuer("tenet").verify(&token, Uuid::nil()).unwrap().iss);
+        assert!(JwtVerifier::hs256(b"secret").with_issuer("someone else").verify(&token, Uuid::nil()).is_err());
+    }
+
+    #[test]
+    fn missing_key_file_test() {
+        assert!(matches!(JwtIssuer::rs256_from_pem_file(key_file("missing.pem")), Err(TenetError::IoError(_))));
+        assert!(matches!(JwtVerifier::eddsa_from_pem_file(key_file("rsa_public.pem")), Err(TenetError::JwtError(_))));
+    }
+}
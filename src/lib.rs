@@ -5,9 +5,24 @@
 //! ## Main Features
 //!
 //! * Multi-tenant architecture for SaaS applications
+//! * Users belonging to several tenants through memberships
+//! * Invitations by email with pre-assigned roles
+//! * Email verification and password reset through a pluggable [`Mailer`]
+//!   with SMTP (behind the `smtp` cargo feature), file and in-memory transports
 //! * User management with secure password storage
+//! * Password policies for all tenants or single ones and an offline check
+//!   against a breach corpus
 //! * Role management for different permission levels
+//! * Groups of users inheriting the roles assigned to the group
 //! * Application configuration with various storage options
+//! * Pluggable persistence through the traits in [`repository`]
+//! * In-memory backend for tests and embedded use
+//! * SQLite backend behind the `sqlite` cargo feature
+//! * Async API on top of `diesel-async` behind the `async` cargo feature
+//! * gRPC `UserTenantService` in [`grpc`] behind the `grpc` cargo feature
+//! * Sessions with expiry, sliding renewal and revocation
+//! * JWT access tokens with tenant and role claims in [`jwt`] behind the `jwt` cargo feature
+//! * REST/JSON router in [`server`] and the `tenet-server` binary behind the `server` cargo feature
 //!
 //! ## Examples
 //!
@@ -41,28 +56,81 @@ extern crate log;
 
 mod application;
 pub mod application_type;
+mod authorization;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod breached_passwords;
+mod context;
 pub mod encryption_modes;
 mod error;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod group;
+mod invitation;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+mod mail_template;
+mod mailer;
+mod memory;
+mod password_history;
+mod password_policy;
+mod refresh_token;
+pub mod repository;
 mod role;
+mod role_definition;
 pub mod role_type;
+#[cfg(feature = "server")]
+pub mod server;
+mod session;
 mod storage;
 pub mod storage_type;
 mod tenant;
+mod tenant_selection;
+mod token_config;
 mod user;
+mod user_token;
 
 mod schema;
 mod postgresql;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(test)]
+mod test_suite;
+
+use std::sync::{Arc, OnceLock};
 
 use log::info;
-use postgresql::{database::Pool, dbtenant::{DbTenant, DbTenantMessage}, dbuser::DbUser};
+use context::Context;
+use encryption_modes::PasswordHashConfig;
+use repository::Repository;
 use uuid::Uuid;
 
 pub use application::*;
+pub use authorization::*;
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncTenet, tenant::AsyncTenant};
+pub use breached_passwords::*;
 pub use error::*;
+pub use group::*;
+pub use invitation::*;
+pub use mail_template::*;
+pub use mailer::*;
+pub use memory::InMemoryRepository;
+pub use password_history::*;
+pub use password_policy::*;
+pub use postgresql::{DieselRepository, PostgresRepository};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteRepository;
+pub use refresh_token::*;
 pub use role::*;
+pub use role_definition::*;
+pub use session::*;
 pub use storage::*;
 pub use tenant::*;
+pub use tenant_selection::*;
+pub use token_config::*;
 pub use user::*;
+pub use user_token::*;
 
 /// Default database URL used when no connection string is provided.
 pub static DEFAULT_DATABASE_URL: &str = "postgres://postgres:@localhost/stec_tenet";
@@ -70,12 +138,14 @@ pub static DEFAULT_DATABASE_URL: &str = "postgres://postgres:@localhost/stec_ten
 /// Main structure for interacting with the Tenet system.
 ///
 /// This structure is the primary entry point for working with the Tenet library.
-/// It manages the database connection and provides methods for managing tenants.
+/// It owns the repository all data is read from and written to, and provides
+/// methods for managing tenants.
 ///
-/// Each `Tenet` instance owns its own connection pool, built from the connection
-/// string it was constructed with. Creating multiple `Tenet` instances - even with
-/// different connection strings, e.g. one per test - is safe: each instance talks
-/// to its own database independently and instances do not interfere with each other.
+/// Each `Tenet` instance created with [`Tenet::new`] owns its own connection pool,
+/// built from the connection string it was constructed with. Creating multiple
+/// `Tenet` instances - even with different connection strings, e.g. one per
+/// test - is safe: each instance talks to its own database independently and
+/// instances do not interfere with each other.
 ///
 /// # Example
 ///
@@ -90,7 +160,7 @@ pub static DEFAULT_DATABASE_URL: &str = "postgres://postgres:@localhost/stec_ten
 /// ```
 #[derive(Debug, Clone)]
 pub struct Tenet {
-    pool: Pool
+    context: Arc<Context>
 }
 
 
@@ -125,9 +195,108 @@ impl Tenet {
             connection_string
         };
 
-        let pool = postgresql::database::build_pool(&database_url);
+        Self::with_repository(PostgresRepository::new(&database_url))
+    }
+
+    /// Creates a new Tenet instance on top of the given repository.
+    ///
+    /// Use this to run Tenet against a backend other than PostgreSQL, or against
+    /// a mock repository in unit tests.
+    ///
+    /// # Parameters
+    ///
+    /// * `repository` - The backend all tenants, users, applications, storages
+    ///   and roles are persisted in.
+    pub fn with_repository(repository: impl Repository + 'static) -> Self {
+        Tenet { context: Arc::new(Context::new(Arc::new(repository))) }
+    }
 
-        Tenet { pool }
+    /// Creates a new Tenet instance that keeps all data in memory.
+    ///
+    /// The in-memory backend enforces the same constraints as the PostgreSQL
+    /// backend, but needs no database server. All data is lost when the last
+    /// clone of the instance is dropped.
+    pub fn in_memory() -> Self {
+        Self::with_repository(InMemoryRepository::new())
+    }
+
+    /// Creates a new Tenet instance backed by a SQLite database file.
+    ///
+    /// The file is created if it does not exist, and pending migrations are run
+    /// against it. Pass `:memory:` for a throwaway database.
+    ///
+    /// Only available with the `sqlite` cargo feature.
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite(path: &str) -> Self {
+        Self::with_repository(SqliteRepository::new(path))
+    }
+
+    /// Sets how sessions created by this instance's tenants are issued and renewed.
+    ///
+    /// Tenants obtained before this call keep the previous configuration.
+    pub fn with_session_config(mut self, session_config: SessionConfig) -> Self {
+        Arc::make_mut(&mut self.context).session_config = session_config;
+        self
+    }
+
+    /// Sets the lifetimes of the tokens mailed to users, like invitations.
+    ///
+    /// Tenants obtained before this call keep the previous configuration.
+    pub fn with_token_config(mut self, token_config: TokenConfig) -> Self {
+        Arc::make_mut(&mut self.context).token_config = token_config;
+        self
+    }
+
+    /// Sets the Argon2id parameters new passwords are hashed with. Existing
+    /// hashes are upgraded on the users' next successful sign-in.
+    ///
+    /// Tenants obtained before this call keep the previous parameters.
+    pub fn with_password_hash_config(mut self, password_hash_config: PasswordHashConfig) -> Self {
+        let context = Arc::make_mut(&mut self.context);
+        context.password_hash_config = password_hash_config;
+        context.dummy_password_hash = OnceLock::new();
+        self
+    }
+
+    /// Sets the password policy of all tenants without a policy of their own,
+    /// see [`Tenant::set_password_policy`]. It is enforced when users are added and passwords are changed.
+    ///
+    /// Tenants obtained before this call keep the previous policy.
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        Arc::make_mut(&mut self.context).password_policy = password_policy;
+        self
+    }
+
+    /// Replaces the breach corpus new passwords are checked against, `None`
+    /// turns the check off. Takes effect immediately for this instance, its
+    /// clones and all tenants obtained from them, so a newer data file can be
+    /// loaded while the instance is in use.
+    pub fn set_breached_passwords(&self, breached_passwords: Option<BreachedPasswords>) {
+        self.context.breached_passwords.set(breached_passwords);
+    }
+
+    /// Sets the mailer that delivers the tokens of [`Tenant::invite_user`],
+    /// [`Tenant::start_email_verification`] and [`Tenant::request_password_reset`].
+    ///
+    /// Tenants obtained before this call keep the previous mailer.
+    pub fn with_mailer(mut self, mailer: impl Mailer + 'static) -> Self {
+        let mailer: Arc<dyn Mailer> = Arc::new(mailer);
+        let context = Arc::make_mut(&mut self.context);
+        context.mail_queue = Some(MailQueue::new(mailer.clone()));
+        context.mailer = Some(mailer);
+        self
+    }
+
+    /// Sets the templates the mailer's mails are rendered with.
+    ///
+    /// Tenants obtained before this call keep the previous templates.
+    pub fn with_mail_templates(mut self, mail_templates: MailTemplates) -> Self {
+        Arc::make_mut(&mut self.context).mail_templates = mail_templates;
+        self
+    }
+
+    fn connect(&self, tenant: Tenant) -> Tenant {
+        tenant.connect(self.context.clone())
     }
 
     /// Returns a list of all tenant IDs.
@@ -145,7 +314,7 @@ impl Tenet {
     /// let tenant_ids = tenet.get_tenant_ids();
     /// ```
     pub fn get_tenant_ids(&self) -> Vec<Uuid> {
-        if let Ok(tenants) = DbTenant::find_all(&self.pool) {
+        if let Ok(tenants) = self.context.repository.find_all_tenants() {
             return tenants.iter().map(|t| t.id).collect();
         }
         Vec::new()
@@ -165,15 +334,13 @@ impl Tenet {
     ///
     /// Returns a `TenetError::NotFoundError` if the user or tenant is not found.
     pub fn get_tenant_id_by_username(&self, username: String) -> Result<uuid::Uuid, TenetError> {
-        let user = DbUser::find_by_email(&self.pool, username).unwrap();
-        if let Ok(tenant) = DbTenant::find(&self.pool, user.db_tenant_id.unwrap()) {
-            return Ok(tenant.id);
-        }
-
-        Err(TenetError::NotFoundError)
+        self.get_tenant_by_username(username).map(|tenant| tenant.id)
     }
 
-    /// Finds a tenant by a username of one of its users.
+    /// Finds the tenant a user was created in by their username.
+    ///
+    /// Users can be members of further tenants, see
+    /// [`Tenet::get_tenants_for_user`] and [`Tenet::login`].
     ///
     /// # Parameters
     ///
@@ -187,14 +354,48 @@ impl Tenet {
     ///
     /// Returns a `TenetError::NotFoundError` if the user or tenant is not found.
     pub fn get_tenant_by_username(&self, username: String) -> Result<Tenant, TenetError> {
-        let user = DbUser::find_by_email(&self.pool, username).unwrap();
-        if let Ok(tenant) = DbTenant::find(&self.pool, user.db_tenant_id.unwrap()) {
-            return Ok(Tenant::from_db(&tenant, self.pool.clone()));
+        let user = self.context.repository.find_user_by_email(username).map_err(|_| TenetError::NotFoundError)?;
+        let tenant_id = user.db_tenant_id.ok_or(TenetError::NotFoundError)?;
+        if let Ok(tenant) = self.context.repository.find_tenant(tenant_id) {
+            return Ok(self.connect(tenant));
         }
 
         Err(TenetError::NotFoundError)
     }
 
+    /// Returns all tenants the user is a member of, ordered by title.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError` if the tenants cannot be loaded.
+    pub fn get_tenants_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Tenant>, TenetError> {
+        let tenants = self.context.repository.find_tenants_by_user(user_id)?;
+        Ok(tenants.into_iter().map(|t| self.connect(t)).collect())
+    }
+
+    /// Authenticates a user independent of a tenant.
+    ///
+    /// Returns the user together with the tenants they are a member of, one of
+    /// which is then chosen with [`TenantSelection::select`]. Returns `None`
+    /// for unknown users and wrong passwords.
+    ///
+    /// # Parameters
+    ///
+    /// * `username` - The username (typically the email address) of the user.
+    /// * `password` - The password in plain text.
+    pub fn login(&self, username: String, password: String) -> Option<TenantSelection> {
+        let Ok(user) = self.context.repository.find_user_by_email(username) else {
+            self.context.verify_dummy_password(&password);
+            return None;
+        };
+        if !user.verify_password(&password).ok()? {
+            return None;
+        }
+        let user = self.context.rehash_password(user, &password);
+        let tenants = self.get_tenants_for_user(user.id).ok()?;
+        Some(TenantSelection { user, tenants })
+    }
+
     /// Finds a tenant by its ID.
     ///
     /// # Parameters
@@ -205,8 +406,8 @@ impl Tenet {
     ///
     /// An `Option<Tenant>` containing the tenant if found, otherwise `None`.
     pub fn get_tenant_by_id(&self, tenant_id: uuid::Uuid) -> Option<Tenant> {
-        if let Ok(db_tenant) = DbTenant::find(&self.pool, tenant_id) {
-            return Some(Tenant::from_db(&db_tenant, self.pool.clone()));
+        if let Ok(tenant) = self.context.repository.find_tenant(tenant_id) {
+            return Some(self.connect(tenant));
         }
         None
     }
@@ -226,16 +427,13 @@ impl Tenet {
     ///
     /// Returns a `TenetError` if the update fails.
     pub fn set_tenant_title(&self, tenant_id: uuid::Uuid, title: String) -> Result<Tenant, TenetError> {
-        let tenant_message = DbTenantMessage {
-            title
-        };
-        
-        let updated_tenant = DbTenant::update(&self.pool, tenant_id, tenant_message)?;
+        let updated_tenant = self.context.repository.update_tenant(tenant_id, title)?;
 
-        Ok(Tenant::from_db(&updated_tenant, self.pool.clone()))
+        Ok(self.connect(updated_tenant))
     }
 
-    /// Creates a new tenant.
+    /// Creates a new tenant, seeded with the built-in role definitions
+    /// `Administrator` and `User`.
     ///
     /// # Parameters
     ///
@@ -258,12 +456,10 @@ impl Tenet {
     /// let tenant = tenet.create_tenant("My Company".to_string()).unwrap();
     /// ```
     pub fn create_tenant(&self, title: String) -> Result<Tenant, TenetError> {
-        let tenant_message = DbTenantMessage {
-            title
-        };
-        let created_tenant = DbTenant::create(&self.pool, tenant_message)?;
+        let tenant = Tenant::new(title);
+        let created_tenant = self.context.repository.create_tenant(&tenant, &RoleDefinition::defaults(tenant.id))?;
 
-        Ok(Tenant::from_db(&created_tenant, self.pool.clone()))
+        Ok(self.connect(created_tenant))
     }
 
     /// Deletes a tenant by its ID.
@@ -280,8 +476,7 @@ impl Tenet {
     ///
     /// Returns a `TenetError` if the deletion fails.
     pub fn delete_tenant(&self, tenant_id: uuid::Uuid) -> Result<(), TenetError> {
-        DbTenant::delete(&self.pool, tenant_id)?;
-        Ok(())
+        self.context.repository.delete_tenant(tenant_id)
     }
 }
 
@@ -293,6 +488,7 @@ mod tests {
     use crate::{application_type::ApplicationType, encryption_modes::EncryptionModes, role_type::RoleType, user::User};
 
     use super::*;
+    use crate::test_suite::{repository_test_suite, user};
 
     fn test_harness(test_code: impl Fn(String)) {
         let node = Postgres::default().start().expect("Unable to start container");
@@ -306,6 +502,8 @@ mod tests {
         node.stop().expect("Failed to stop postgres container");
         //node.rm();
     }
+
+    repository_test_suite!(|test| test_harness(|connection_string| test(Tenet::new(connection_string))));
     
     /// Regression test for https://github.com/stec-ug-haftungsbeschrankt/tenet/issues/7
     ///
@@ -353,6 +551,30 @@ mod tests {
         node_b.stop().expect("Failed to stop postgres container B");
     }
 
+    #[test]
+    fn tenet_with_repository_test() {
+        test_harness(|connection_string| {
+            let tenet = Tenet::with_repository(PostgresRepository::new(&connection_string));
+
+            let tenant = tenet.create_tenant("Repository Tenant".to_string()).unwrap();
+            let loaded_tenant = tenet.get_tenant_by_id(tenant.id).unwrap();
+            assert_eq!(tenant.title, loaded_tenant.title);
+
+            let storage = Storage::new_json_file("some_path", loaded_tenant.id);
+            let storage = loaded_tenant.add_storage(&storage).unwrap();
+            assert_eq!(1, loaded_tenant.get_storages().len());
+            assert_eq!(storage.id, loaded_tenant.get_storage_by_id(storage.id).unwrap().id);
+        });
+    }
+
+    #[test]
+    fn disconnected_tenant_test() {
+        let tenant = Tenant::new("Disconnected".to_string());
+
+        assert!(tenant.get_users().is_empty());
+        assert!(matches!(tenant.get_user_by_id(uuid::Uuid::new_v4()), Err(TenetError::NotConnectedError)));
+    }
+
     #[test]
     fn create_tenant_test() {
         test_harness(|connection_string| {
@@ -394,14 +616,7 @@ mod tests {
 
             let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
 
-            let user = User::new(
-                "someone@something.de".to_string(),
-                "Danny Crane".to_string(), 
-                "password".to_string(), 
-                EncryptionModes::Argon2, 
-                "someone@something.de".to_string(), 
-                true, 
-                tenant.id);
+            let user = user("someone@something.de", tenant.id);
 
             let created_user = tenant.add_user(&user).unwrap();
 
@@ -453,14 +668,7 @@ mod tests {
             let application = Application::new(ApplicationType::Shop, storage.id, tenant.id);
             let created_application = tenant.add_application(&application).unwrap();
 
-            let user = User::new(
-                "someone@something.de".to_string(),
-                "Danny Crane".to_string(), 
-                "password".to_string(), 
-                EncryptionModes::Argon2, 
-                "someone@something.de".to_string(), 
-                true, 
-                tenant.id);
+            let user = user("someone@something.de", tenant.id);
             let created_user = tenant.add_user(&user).unwrap();
 
             let role = Role::new(RoleType::Administrator, created_user.id, created_application.id, tenant.id);
@@ -545,7 +753,7 @@ mod tests {
                 true,
                 tenant.id
             );
-            let created_user = tenant.add_user(&user).unwrap();
+            tenant.add_user(&user).unwrap();
 
             // Fetch tenant by username
             let tenant_by_username = tenet.get_tenant_by_username(email).unwrap();
@@ -614,7 +822,7 @@ mod tests {
 
             // Assign user an Administrator role
             let admin_role = Role::new(RoleType::Administrator, user.id, application.id, tenant.id);
-            let admin_role = tenant.add_role(&admin_role).unwrap();
+            tenant.add_role(&admin_role).unwrap();
 
             // User should now have one role
             let roles_after_admin = tenant.get_roles_for_user(user.id).unwrap();
@@ -623,7 +831,7 @@ mod tests {
 
             // Assign user a User role for the same application
             let user_role = Role::new(RoleType::User, user.id, application.id, tenant.id);
-            let user_role = tenant.add_role(&user_role).unwrap();
+            tenant.add_role(&user_role).unwrap();
 
             // User should now have two roles
             let roles_after_user = tenant.get_roles_for_user(user.id).unwrap();
@@ -635,4 +843,5 @@ mod tests {
             assert!(role_types.contains(&RoleType::User));
         });
     }
+
 }
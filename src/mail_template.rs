@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::Mail;
+
+
+/// The mails Tenet sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde_derive::Serialize, serde_derive::Deserialize)]
+pub enum MailKind {
+    /// Sent by [`crate::Tenant::invite_user`]
+    Invitation,
+    /// Sent by [`crate::Tenant::start_email_verification`]
+    EmailVerification,
+    /// Sent by [`crate::Tenant::request_password_reset`]
+    PasswordReset
+}
+
+
+/// Subject and body of a mail.
+///
+/// Both may contain the placeholders `{token}`, `{email}` for the recipient
+/// and `{tenant}` for the title of the tenant. Other text in braces is kept
+/// as it is.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct MailTemplate {
+    pub subject: String,
+    pub body: String
+}
+
+impl MailTemplate {
+    pub fn new(subject: String, body: String) -> Self {
+        MailTemplate { subject, body }
+    }
+
+    pub fn render(&self, to: &str, values: &[(&str, &str)]) -> Mail {
+        Mail::new(to.to_string(), fill(&self.subject, values), fill(&self.body, values))
+    }
+}
+
+/// Replaces the placeholders in a single pass, so that values containing
+/// braces are inserted verbatim.
+fn fill(text: &str, values: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let placeholder = &rest[start + 1..];
+        match values.iter().find(|(key, _)| placeholder.strip_prefix(key).is_some_and(|p| p.starts_with('}'))) {
+            Some((key, value)) => {
+                result.push_str(value);
+                rest = &placeholder[key.len() + 1..];
+            },
+            None => {
+                result.push('{');
+                rest = placeholder;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+
+/// The mail templates of a [`crate::Tenet`] instance, see
+/// [`crate::Tenet::with_mail_templates`].
+///
+/// Starts with built-in English templates. Templates set for a tenant take
+/// precedence over the ones for all tenants.
+#[derive(Debug, Clone)]
+pub struct MailTemplates {
+    templates: HashMap<MailKind, MailTemplate>,
+    tenant_templates: HashMap<(uuid::Uuid, MailKind), MailTemplate>
+}
+
+impl Default for MailTemplates {
+    fn default() -> Self {
+        let templates = HashMap::from([
+            (MailKind::Invitation, MailTemplate::new(
+                "You have been invited to {tenant}".to_string(),
+                "You have been invited to join {tenant}. Use the following code to accept the invitation:\n\n{token}\n".to_string()
+            )),
+            (MailKind::EmailVerification, MailTemplate::new(
+                "Verify your email address".to_string(),
+                "Please confirm your email address with the following code:\n\n{token}\n".to_string()
+            )),
+            (MailKind::PasswordReset, MailTemplate::new(
+                "Reset your password".to_string(),
+                "Use the following code to set a new password:\n\n{token}\n\nIf you did not ask for this, you can ignore this mail.\n".to_string()
+            ))
+        ]);
+        MailTemplates { templates, tenant_templates: HashMap::new() }
+    }
+}
+
+impl MailTemplates {
+    /// Replaces the template used for all tenants without an override.
+    pub fn with_template(mut self, kind: MailKind, template: MailTemplate) -> Self {
+        self.templates.insert(kind, template);
+        self
+    }
+
+    /// Sets the template used for a single tenant.
+    pub fn with_tenant_template(mut self, tenant_id: uuid::Uuid, kind: MailKind, template: MailTemplate) -> Self {
+        self.tenant_templates.insert((tenant_id, kind), template);
+        self
+    }
+
+    /// Returns the template the tenant's mails of the given kind are rendered with.
+    pub fn get(&self, tenant_id: uuid::Uuid, kind: MailKind) -> &MailTemplate {
+        self.tenant_templates.get(&(tenant_id, kind))
+            .unwrap_or(&self.templates[&kind])
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let template = MailTemplate::new("Welcome to {tenant}".to_string(), "{email}: {token} {unknown} {".to_string());
+        let mail = template.render("someone@something.de", &[("token", "{tenant}"), ("email", "someone@something.de"), ("tenant", "Shop")]);
+        assert_eq!("someone@something.de", mail.to);
+        assert_eq!("Welcome to Shop", mail.subject);
+        assert_eq!("someone@something.de: {tenant} {unknown} {", mail.body);
+    }
+
+    #[test]
+    fn tenant_template_test() {
+        let tenant_id = uuid::Uuid::new_v4();
+        let custom = MailTemplate::new("Passwort zurücksetzen".to_string(), "{token}".to_string());
+        let templates = MailTemplates::default().with_tenant_template(tenant_id, MailKind::PasswordReset, custom.clone());
+        assert_eq!(&custom, templates.get(tenant_id, MailKind::PasswordReset));
+        assert_eq!("Reset your password", templates.get(uuid::Uuid::new_v4(), MailKind::PasswordReset).subject);
+        assert_eq!("Verify your email address", templates.get(tenant_id, MailKind::EmailVerification).subject);
+    }
+}
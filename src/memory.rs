@@ -0,0 +1,991 @@
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::result::{DatabaseErrorKind, Error};
+use uuid::Uuid;
+
+use crate::repository::{ApplicationRepository, GroupRepository, InvitationRepository, Invitee, MembershipRepository, PasswordHistoryRepository, PasswordPolicyRepository, RefreshTokenRepository, RoleDefinitionRepository, RoleRepository, SessionRepository, StorageRepository, TenantRepository, UserRepository, UserTokenRepository};
+use crate::{Application, Group, Invitation, PasswordHistoryEntry, PasswordPolicy, RefreshToken, Role, RoleDefinition, Session, Storage, Tenant, TenetError, User, UserToken};
+
+
+/// Repository keeping all data in memory.
+///
+/// It mirrors the constraints of the PostgreSQL schema: email addresses are
+/// unique, lookups are scoped to their tenant and references between tenants,
+/// users, groups, storages, applications and roles are checked on insert and
+/// delete.
+/// Violations are reported with the same errors the PostgreSQL backend returns.
+///
+/// Data is lost when the repository is dropped, which makes it a good fit for
+/// tests and embedded use. See [`crate::Tenet::in_memory`].
+#[derive(Debug, Default)]
+pub struct InMemoryRepository {
+    data: Mutex<Data>
+}
+
+#[derive(Debug, Default)]
+struct Data {
+    tenants: Vec<Tenant>,
+    users: Vec<User>,
+    /// Pairs of user id and tenant id
+    memberships: Vec<(Uuid, Uuid)>,
+    applications: Vec<Application>,
+    storages: Vec<Storage>,
+    roles: Vec<Role>,
+    role_definitions: Vec<RoleDefinition>,
+    groups: Vec<Group>,
+    /// Pairs of group id and user id
+    group_members: Vec<(Uuid, Uuid)>,
+    sessions: Vec<Session>,
+    refresh_tokens: Vec<RefreshToken>,
+    invitations: Vec<Invitation>,
+    user_tokens: Vec<UserToken>,
+    password_history: Vec<PasswordHistoryEntry>,
+    /// Pairs of tenant id and policy
+    password_policies: Vec<(Uuid, PasswordPolicy)>
+}
+
+
+impl InMemoryRepository {
+    /// Creates an empty repository.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn data(&self) -> MutexGuard<'_, Data> {
+        // A panic while holding the lock cannot leave the vectors half-written,
+        // so the data is still consistent.
+        self.data.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+
+fn not_found() -> TenetError {
+    TenetError::DatabaseError(Error::NotFound)
+}
+
+fn unique_violation(constraint: &str) -> TenetError {
+    let message = format!("duplicate key value violates unique constraint \"{}\"", constraint);
+    TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, Box::new(message)))
+}
+
+fn foreign_key_violation(message: String) -> TenetError {
+    TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, Box::new(message)))
+}
+
+/// Fails like an insert or update of `table` whose `column` references a missing row.
+fn check_reference(table: &str, column: &str, exists: bool) -> Result<(), TenetError> {
+    if exists {
+        return Ok(());
+    }
+    Err(foreign_key_violation(format!(
+        "insert or update on table \"{}\" violates foreign key constraint \"{}_{}_fkey\"", table, table, column
+    )))
+}
+
+/// Fails like a delete from `table` that is still referenced by `referencing_table`.
+fn check_unreferenced(table: &str, referencing_table: &str, column: &str, referenced: bool) -> Result<(), TenetError> {
+    if !referenced {
+        return Ok(());
+    }
+    Err(foreign_key_violation(format!(
+        "update or delete on table \"{}\" violates foreign key constraint \"{}_{}_fkey\" on table \"{}\"",
+        table, referencing_table, column, referencing_table
+    )))
+}
+
+impl Data {
+    fn tenant_exists(&self, tenant_id: Option<Uuid>) -> bool {
+        tenant_id.is_none_or(|id| self.tenants.iter().any(|t| t.id == id))
+    }
+
+    fn user_exists(&self, user_id: Option<Uuid>) -> bool {
+        user_id.is_none_or(|id| self.users.iter().any(|u| u.id == id))
+    }
+
+    fn is_member(&self, user_id: Uuid, tenant_id: Uuid) -> bool {
+        self.memberships.contains(&(user_id, tenant_id))
+    }
+
+    fn storage_exists(&self, storage_id: Option<Uuid>) -> bool {
+        storage_id.is_none_or(|id| self.storages.iter().any(|s| s.id == id))
+    }
+
+    fn application_exists(&self, application_id: Option<Uuid>) -> bool {
+        application_id.is_none_or(|id| self.applications.iter().any(|a| a.id == id))
+    }
+
+    fn group_exists(&self, group_id: Option<Uuid>) -> bool {
+        group_id.is_none_or(|id| self.groups.iter().any(|g| g.id == id))
+    }
+
+    fn check_unique_group_name(&self, group: &Group) -> Result<(), TenetError> {
+        if self.groups.iter().any(|g| g.name == group.name && g.db_tenant_id == group.db_tenant_id && g.id != group.id) {
+            return Err(unique_violation("groups_db_tenant_id_name_key"));
+        }
+        Ok(())
+    }
+
+    fn role_definition_exists(&self, role_definition_id: Option<Uuid>) -> bool {
+        role_definition_id.is_some_and(|id| self.role_definitions.iter().any(|d| d.id == id))
+    }
+
+    /// Takes the role type from the role's definition, like the join in the database backends.
+    fn with_definition_type(&self, role: &Role) -> Role {
+        let definition = self.role_definitions.iter().find(|d| Some(d.id) == role.role_definition_id);
+        Role { role_type: definition.map_or(role.role_type, |d| d.role_type), ..*role }
+    }
+
+    fn check_unique_email(&self, email: &str, user_id: Uuid) -> Result<(), TenetError> {
+        if self.users.iter().any(|u| u.email == email && u.id != user_id) {
+            return Err(unique_violation("users_email_key"));
+        }
+        Ok(())
+    }
+}
+
+
+impl TenantRepository for InMemoryRepository {
+    fn find_all_tenants(&self) -> Result<Vec<Tenant>, TenetError> {
+        Ok(self.data().tenants.clone())
+    }
+
+    fn find_tenant(&self, tenant_id: Uuid) -> Result<Tenant, TenetError> {
+        self.data().tenants.iter()
+            .find(|t| t.id == tenant_id)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn create_tenant(&self, tenant: &Tenant, role_definitions: &[RoleDefinition]) -> Result<Tenant, TenetError> {
+        let mut data = self.data();
+        if data.tenants.iter().any(|t| t.id == tenant.id) {
+            return Err(unique_violation("tenants_pkey"));
+        }
+        if role_definitions.iter().any(|r| data.role_definitions.iter().any(|d| d.id == r.id)) {
+            return Err(unique_violation("role_definitions_pkey"));
+        }
+        if role_definitions.iter().enumerate().any(|(i, r)| role_definitions[..i].iter().any(|d| d.name == r.name)) {
+            return Err(unique_violation("role_definitions_db_tenant_id_name_key"));
+        }
+        let new_tenant = Tenant { context: None, ..tenant.clone() };
+        data.tenants.push(new_tenant.clone());
+        for role_definition in role_definitions {
+            let mut new_definition = role_definition.clone();
+            new_definition.permissions.sort();
+            new_definition.permissions.dedup();
+            data.role_definitions.push(new_definition);
+        }
+        Ok(new_tenant)
+    }
+
+    fn update_tenant(&self, tenant_id: Uuid, title: String) -> Result<Tenant, TenetError> {
+        let mut data = self.data();
+        let tenant = data.tenants.iter_mut()
+            .find(|t| t.id == tenant_id)
+            .ok_or_else(not_found)?;
+        tenant.title = title;
+        Ok(tenant.clone())
+    }
+
+    fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        check_unreferenced("tenants", "users", "db_tenant_id", data.users.iter().any(|u| u.db_tenant_id == Some(tenant_id)))?;
+        check_unreferenced("tenants", "storages", "db_tenant_id", data.storages.iter().any(|s| s.db_tenant_id == Some(tenant_id)))?;
+        check_unreferenced("tenants", "applications", "db_tenant_id", data.applications.iter().any(|a| a.db_tenant_id == Some(tenant_id)))?;
+        check_unreferenced("tenants", "roles", "db_tenant_id", data.roles.iter().any(|r| r.db_tenant_id == Some(tenant_id)))?;
+        data.role_definitions.retain(|d| d.db_tenant_id != Some(tenant_id));
+        data.memberships.retain(|(_, id)| *id != tenant_id);
+        let group_ids: Vec<Uuid> = data.groups.iter().filter(|g| g.db_tenant_id == Some(tenant_id)).map(|g| g.id).collect();
+        data.group_members.retain(|(group_id, _)| !group_ids.contains(group_id));
+        data.groups.retain(|g| g.db_tenant_id != Some(tenant_id));
+        data.invitations.retain(|i| i.db_tenant_id != Some(tenant_id));
+        data.sessions.retain(|s| s.db_tenant_id != Some(tenant_id));
+        data.refresh_tokens.retain(|r| r.db_tenant_id != Some(tenant_id));
+        data.user_tokens.retain(|t| t.db_tenant_id != Some(tenant_id));
+        data.password_policies.retain(|(id, _)| *id != tenant_id);
+        data.tenants.retain(|t| t.id != tenant_id);
+        Ok(())
+    }
+}
+
+
+impl UserRepository for InMemoryRepository {
+    fn find_users_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<User>, TenetError> {
+        let data = self.data();
+        Ok(data.users.iter()
+            .filter(|u| data.is_member(u.id, tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    fn find_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<User, TenetError> {
+        let data = self.data();
+        data.users.iter()
+            .find(|u| u.id == user_id && data.is_member(u.id, tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_user_by_email(&self, email: String) -> Result<User, TenetError> {
+        self.data().users.iter()
+            .find(|u| u.email == email)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_user_by_tenant_and_email(&self, tenant_id: Uuid, email: String) -> Result<User, TenetError> {
+        let data = self.data();
+        data.users.iter()
+            .find(|u| u.email == email && data.is_member(u.id, tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn create_user(&self, user: &User) -> Result<User, TenetError> {
+        let mut data = self.data();
+        let new_user = User {
+            id: Uuid::new_v4(),
+            username: user.email.clone(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..user.clone()
+        };
+        data.check_unique_email(&new_user.email, new_user.id)?;
+        check_reference("users", "db_tenant_id", data.tenant_exists(new_user.db_tenant_id))?;
+
+        if let Some(tenant_id) = new_user.db_tenant_id {
+            data.memberships.push((new_user.id, tenant_id));
+        }
+        data.users.push(new_user.clone());
+        Ok(new_user)
+    }
+
+    fn update_user(&self, user: &User) -> Result<User, TenetError> {
+        let mut data = self.data();
+        data.check_unique_email(&user.email, user.id)?;
+        check_reference("users", "db_tenant_id", data.tenant_exists(user.db_tenant_id))?;
+
+        let stored_user = data.users.iter_mut()
+            .find(|u| u.id == user.id)
+            .ok_or_else(not_found)?;
+        *stored_user = User {
+            username: user.email.clone(),
+            created_at: stored_user.created_at,
+            updated_at: user.updated_at.or(stored_user.updated_at),
+            ..user.clone()
+        };
+        Ok(stored_user.clone())
+    }
+
+    fn delete_user(&self, user_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        check_unreferenced("users", "roles", "user_id", data.roles.iter().any(|r| r.user_id == Some(user_id)))?;
+        data.users.retain(|u| u.id != user_id);
+        data.group_members.retain(|(_, member_id)| *member_id != user_id);
+        data.memberships.retain(|(id, _)| *id != user_id);
+        data.sessions.retain(|s| s.user_id != user_id);
+        data.refresh_tokens.retain(|t| t.user_id != user_id);
+        data.user_tokens.retain(|t| t.user_id != user_id);
+        data.password_history.retain(|e| e.user_id != user_id);
+        Ok(())
+    }
+}
+
+
+impl MembershipRepository for InMemoryRepository {
+    fn find_tenants_by_user(&self, user_id: Uuid) -> Result<Vec<Tenant>, TenetError> {
+        let data = self.data();
+        let mut tenants: Vec<Tenant> = data.tenants.iter()
+            .filter(|t| data.is_member(user_id, t.id))
+            .cloned()
+            .collect();
+        tenants.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(tenants)
+    }
+
+    fn create_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        if data.is_member(user_id, tenant_id) {
+            return Err(unique_violation("memberships_pkey"));
+        }
+        check_reference("memberships", "user_id", data.user_exists(Some(user_id)))?;
+        check_reference("memberships", "db_tenant_id", data.tenant_exists(Some(tenant_id)))?;
+
+        data.memberships.push((user_id, tenant_id));
+        Ok(())
+    }
+
+    fn delete_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        let now = Utc::now().naive_utc();
+        data.roles.retain(|r| r.db_tenant_id != Some(tenant_id) || r.user_id != Some(user_id));
+        let group_ids: Vec<Uuid> = data.groups.iter()
+            .filter(|g| g.db_tenant_id == Some(tenant_id))
+            .map(|g| g.id)
+            .collect();
+        data.group_members.retain(|(group_id, member_id)| *member_id != user_id || !group_ids.contains(group_id));
+        data.sessions.iter_mut()
+            .filter(|s| s.db_tenant_id == Some(tenant_id) && s.user_id == user_id && s.revoked_at.is_none())
+            .for_each(|s| s.revoked_at = Some(now));
+        data.refresh_tokens.iter_mut()
+            .filter(|t| t.db_tenant_id == Some(tenant_id) && t.user_id == user_id && t.revoked_at.is_none())
+            .for_each(|t| t.revoked_at = Some(now));
+        data.memberships.retain(|membership| *membership != (user_id, tenant_id));
+        Ok(())
+    }
+}
+
+
+impl ApplicationRepository for InMemoryRepository {
+    fn find_applications_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Application>, TenetError> {
+        Ok(self.data().applications.iter()
+            .filter(|a| a.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    fn find_application(&self, tenant_id: Uuid, application_id: Uuid) -> Result<Application, TenetError> {
+        self.data().applications.iter()
+            .find(|a| a.id == application_id && a.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn create_application(&self, application: &Application) -> Result<Application, TenetError> {
+        let mut data = self.data();
+        check_reference("applications", "storage_id", data.storage_exists(application.storage_id))?;
+        check_reference("applications", "db_tenant_id", data.tenant_exists(application.db_tenant_id))?;
+
+        let new_application = Application {
+            id: Uuid::new_v4(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..application.clone()
+        };
+        data.applications.push(new_application.clone());
+        Ok(new_application)
+    }
+
+    fn delete_application(&self, application_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        check_unreferenced("applications", "roles", "application_id", data.roles.iter().any(|r| r.application_id == Some(application_id)))?;
+        data.applications.retain(|a| a.id != application_id);
+        data.invitations.iter_mut()
+            .for_each(|i| i.roles.retain(|r| r.application_id != Some(application_id)));
+        Ok(())
+    }
+}
+
+
+impl StorageRepository for InMemoryRepository {
+    fn find_storages_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Storage>, TenetError> {
+        Ok(self.data().storages.iter()
+            .filter(|s| s.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    fn find_storage(&self, tenant_id: Uuid, storage_id: Uuid) -> Result<Storage, TenetError> {
+        self.data().storages.iter()
+            .find(|s| s.id == storage_id && s.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn create_storage(&self, storage: &Storage) -> Result<Storage, TenetError> {
+        let mut data = self.data();
+        check_reference("storages", "db_tenant_id", data.tenant_exists(storage.db_tenant_id))?;
+
+        let new_storage = Storage {
+            id: Uuid::new_v4(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..storage.clone()
+        };
+        data.storages.push(new_storage.clone());
+        Ok(new_storage)
+    }
+
+    fn delete_storage(&self, storage_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        check_unreferenced("storages", "applications", "storage_id", data.applications.iter().any(|a| a.storage_id == Some(storage_id)))?;
+        data.storages.retain(|s| s.id != storage_id);
+        Ok(())
+    }
+}
+
+
+impl RoleRepository for InMemoryRepository {
+    fn find_roles_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let data = self.data();
+        Ok(data.roles.iter()
+            .filter(|r| r.db_tenant_id == Some(tenant_id))
+            .map(|r| data.with_definition_type(r))
+            .collect())
+    }
+
+    fn find_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let data = self.data();
+        Ok(data.roles.iter()
+            .filter(|r| r.db_tenant_id == Some(tenant_id) && r.user_id == Some(user_id))
+            .map(|r| data.with_definition_type(r))
+            .collect())
+    }
+
+    fn find_roles_by_group(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let data = self.data();
+        Ok(data.roles.iter()
+            .filter(|r| r.db_tenant_id == Some(tenant_id) && r.group_id == Some(group_id))
+            .map(|r| data.with_definition_type(r))
+            .collect())
+    }
+
+    fn find_inherited_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let data = self.data();
+        let group_ids: Vec<Uuid> = data.group_members.iter()
+            .filter(|(_, member_id)| *member_id == user_id)
+            .map(|(group_id, _)| *group_id)
+            .collect();
+        Ok(data.roles.iter()
+            .filter(|r| r.db_tenant_id == Some(tenant_id) && r.group_id.is_some_and(|id| group_ids.contains(&id)))
+            .map(|r| data.with_definition_type(r))
+            .collect())
+    }
+
+    fn find_role(&self, tenant_id: Uuid, role_id: Uuid) -> Result<Role, TenetError> {
+        let data = self.data();
+        data.roles.iter()
+            .find(|r| r.id == role_id && r.db_tenant_id == Some(tenant_id))
+            .map(|r| data.with_definition_type(r))
+            .ok_or_else(not_found)
+    }
+
+    fn create_role(&self, role: &Role) -> Result<Role, TenetError> {
+        let mut data = self.data();
+        check_reference("roles", "role_definition_id", data.role_definition_exists(role.role_definition_id))?;
+        check_reference("roles", "user_id", data.user_exists(role.user_id))?;
+        check_reference("roles", "group_id", data.group_exists(role.group_id))?;
+        check_reference("roles", "application_id", data.application_exists(role.application_id))?;
+        check_reference("roles", "db_tenant_id", data.tenant_exists(role.db_tenant_id))?;
+
+        let new_role = Role {
+            id: Uuid::new_v4(),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            ..*role
+        };
+        data.roles.push(new_role);
+        Ok(new_role)
+    }
+
+    fn delete_role(&self, role_id: Uuid) -> Result<(), TenetError> {
+        self.data().roles.retain(|r| r.id != role_id);
+        Ok(())
+    }
+}
+
+
+impl RoleDefinitionRepository for InMemoryRepository {
+    fn find_role_definitions_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<RoleDefinition>, TenetError> {
+        Ok(self.data().role_definitions.iter()
+            .filter(|d| d.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    fn find_role_definition(&self, tenant_id: Uuid, role_definition_id: Uuid) -> Result<RoleDefinition, TenetError> {
+        self.data().role_definitions.iter()
+            .find(|d| d.id == role_definition_id && d.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_role_definition_by_name(&self, tenant_id: Uuid, name: String) -> Result<RoleDefinition, TenetError> {
+        self.data().role_definitions.iter()
+            .find(|d| d.name == name && d.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn create_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError> {
+        let mut data = self.data();
+        if data.role_definitions.iter().any(|d| d.id == role_definition.id) {
+            return Err(unique_violation("role_definitions_pkey"));
+        }
+        if data.role_definitions.iter().any(|d| d.name == role_definition.name && d.db_tenant_id == role_definition.db_tenant_id) {
+            return Err(unique_violation("role_definitions_db_tenant_id_name_key"));
+        }
+        check_reference("role_definitions", "db_tenant_id", data.tenant_exists(role_definition.db_tenant_id))?;
+
+        let mut new_definition = role_definition.clone();
+        new_definition.permissions.sort();
+        new_definition.permissions.dedup();
+        data.role_definitions.push(new_definition.clone());
+        Ok(new_definition)
+    }
+
+    fn update_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError> {
+        let mut data = self.data();
+        let tenant_id = data.role_definitions.iter()
+            .find(|d| d.id == role_definition.id)
+            .ok_or_else(not_found)?
+            .db_tenant_id;
+        if data.role_definitions.iter().any(|d| d.name == role_definition.name && d.db_tenant_id == tenant_id && d.id != role_definition.id) {
+            return Err(unique_violation("role_definitions_db_tenant_id_name_key"));
+        }
+        let stored_definition = data.role_definitions.iter_mut()
+            .find(|d| d.id == role_definition.id)
+            .ok_or_else(not_found)?;
+        stored_definition.name = role_definition.name.clone();
+        stored_definition.role_type = role_definition.role_type;
+        stored_definition.permissions = role_definition.permissions.clone();
+        stored_definition.permissions.sort();
+        stored_definition.permissions.dedup();
+        stored_definition.updated_at = Some(Utc::now().naive_utc());
+        Ok(stored_definition.clone())
+    }
+
+    fn delete_role_definition(&self, role_definition_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        check_unreferenced("role_definitions", "roles", "role_definition_id", data.roles.iter().any(|r| r.role_definition_id == Some(role_definition_id)))?;
+        data.role_definitions.retain(|d| d.id != role_definition_id);
+        data.invitations.iter_mut()
+            .for_each(|i| i.roles.retain(|r| r.role_definition_id != role_definition_id));
+        Ok(())
+    }
+}
+
+
+impl GroupRepository for InMemoryRepository {
+    fn find_groups_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Group>, TenetError> {
+        let mut groups: Vec<Group> = self.data().groups.iter()
+            .filter(|g| g.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(groups)
+    }
+
+    fn find_group(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Group, TenetError> {
+        self.data().groups.iter()
+            .find(|g| g.id == group_id && g.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_groups_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Group>, TenetError> {
+        let data = self.data();
+        let mut groups: Vec<Group> = data.groups.iter()
+            .filter(|g| g.db_tenant_id == Some(tenant_id))
+            .filter(|g| data.group_members.contains(&(g.id, user_id)))
+            .cloned()
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(groups)
+    }
+
+    fn create_group(&self, group: &Group) -> Result<Group, TenetError> {
+        let mut data = self.data();
+        if data.groups.iter().any(|g| g.id == group.id) {
+            return Err(unique_violation("groups_pkey"));
+        }
+        data.check_unique_group_name(group)?;
+        check_reference("groups", "db_tenant_id", data.tenant_exists(group.db_tenant_id))?;
+
+        data.groups.push(group.clone());
+        Ok(group.clone())
+    }
+
+    fn update_group(&self, group: &Group) -> Result<Group, TenetError> {
+        let mut data = self.data();
+        data.check_unique_group_name(group)?;
+        let stored_group = data.groups.iter_mut()
+            .find(|g| g.id == group.id)
+            .ok_or_else(not_found)?;
+        stored_group.name = group.name.clone();
+        stored_group.updated_at = Some(Utc::now().naive_utc());
+        Ok(stored_group.clone())
+    }
+
+    fn delete_group(&self, group_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        data.roles.retain(|r| r.group_id != Some(group_id));
+        data.group_members.retain(|(id, _)| *id != group_id);
+        data.groups.retain(|g| g.id != group_id);
+        Ok(())
+    }
+
+    fn find_group_members(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<User>, TenetError> {
+        let data = self.data();
+        if !data.groups.iter().any(|g| g.id == group_id && g.db_tenant_id == Some(tenant_id)) {
+            return Ok(Vec::new());
+        }
+        let mut members: Vec<User> = data.users.iter()
+            .filter(|u| data.group_members.contains(&(group_id, u.id)))
+            .cloned()
+            .collect();
+        members.sort_by(|a, b| a.email.cmp(&b.email));
+        Ok(members)
+    }
+
+    fn add_group_member(&self, group_id: Uuid, user_id: Uuid) -> Result<(), TenetError> {
+        let mut data = self.data();
+        if data.group_members.contains(&(group_id, user_id)) {
+            return Err(unique_violation("group_members_pkey"));
+        }
+        check_reference("group_members", "group_id", data.group_exists(Some(group_id)))?;
+        check_reference("group_members", "user_id", data.user_exists(Some(user_id)))?;
+
+        data.group_members.push((group_id, user_id));
+        Ok(())
+    }
+
+    fn remove_group_member(&self, group_id: Uuid, user_id: Uuid) -> Result<(), TenetError> {
+        self.data().group_members.retain(|member| *member != (group_id, user_id));
+        Ok(())
+    }
+}
+
+
+impl SessionRepository for InMemoryRepository {
+    fn find_session_by_token_hash(&self, token_hash: String) -> Result<Session, TenetError> {
+        self.data().sessions.iter()
+            .find(|s| s.token_hash == token_hash)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_session(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Session, TenetError> {
+        self.data().sessions.iter()
+            .find(|s| s.id == session_id && s.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_sessions_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Session>, TenetError> {
+        Ok(self.data().sessions.iter()
+            .filter(|s| s.db_tenant_id == Some(tenant_id) && s.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    fn create_session(&self, session: &Session) -> Result<Session, TenetError> {
+        let mut data = self.data();
+        if data.sessions.iter().any(|s| s.id == session.id) {
+            return Err(unique_violation("sessions_pkey"));
+        }
+        if data.sessions.iter().any(|s| s.token_hash == session.token_hash) {
+            return Err(unique_violation("sessions_token_hash_key"));
+        }
+        check_reference("sessions", "user_id", data.user_exists(Some(session.user_id)))?;
+        check_reference("sessions", "db_tenant_id", data.tenant_exists(session.db_tenant_id))?;
+
+        data.sessions.push(session.clone());
+        Ok(session.clone())
+    }
+
+    fn update_session(&self, session: &Session) -> Result<Session, TenetError> {
+        let mut data = self.data();
+        let stored_session = data.sessions.iter_mut()
+            .find(|s| s.id == session.id)
+            .ok_or_else(not_found)?;
+        stored_session.expires_at = session.expires_at;
+        stored_session.last_used_at = session.last_used_at;
+        stored_session.revoked_at = session.revoked_at;
+        Ok(stored_session.clone())
+    }
+}
+
+
+impl RefreshTokenRepository for InMemoryRepository {
+    fn find_refresh_token_by_token_hash(&self, token_hash: String) -> Result<RefreshToken, TenetError> {
+        self.data().refresh_tokens.iter()
+            .find(|t| t.token_hash == token_hash)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_refresh_tokens_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<RefreshToken>, TenetError> {
+        Ok(self.data().refresh_tokens.iter()
+            .filter(|t| t.db_tenant_id == Some(tenant_id) && t.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    fn create_refresh_token(&self, refresh_token: &RefreshToken) -> Result<RefreshToken, TenetError> {
+        let mut data = self.data();
+        if data.refresh_tokens.iter().any(|t| t.id == refresh_token.id) {
+            return Err(unique_violation("refresh_tokens_pkey"));
+        }
+        if data.refresh_tokens.iter().any(|t| t.token_hash == refresh_token.token_hash) {
+            return Err(unique_violation("refresh_tokens_token_hash_key"));
+        }
+        check_reference("refresh_tokens", "user_id", data.user_exists(Some(refresh_token.user_id)))?;
+        check_reference("refresh_tokens", "db_tenant_id", data.tenant_exists(refresh_token.db_tenant_id))?;
+
+        data.refresh_tokens.push(refresh_token.clone());
+        Ok(refresh_token.clone())
+    }
+
+    fn use_refresh_token(&self, refresh_token_id: Uuid, used_at: NaiveDateTime) -> Result<bool, TenetError> {
+        let mut data = self.data();
+        let stored_token = data.refresh_tokens.iter_mut()
+            .find(|t| t.id == refresh_token_id)
+            .ok_or_else(not_found)?;
+        if stored_token.used_at.is_some() {
+            return Ok(false);
+        }
+        stored_token.used_at = Some(used_at);
+        Ok(true)
+    }
+
+    fn revoke_refresh_token_family(&self, family_id: Uuid, revoked_at: NaiveDateTime) -> Result<(), TenetError> {
+        self.data().refresh_tokens.iter_mut()
+            .filter(|t| t.family_id == family_id && t.revoked_at.is_none())
+            .for_each(|t| t.revoked_at = Some(revoked_at));
+        Ok(())
+    }
+}
+
+
+impl InvitationRepository for InMemoryRepository {
+    fn find_invitation_by_token_hash(&self, token_hash: String) -> Result<Invitation, TenetError> {
+        self.data().invitations.iter()
+            .find(|i| i.token_hash == token_hash)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_invitation(&self, tenant_id: Uuid, invitation_id: Uuid) -> Result<Invitation, TenetError> {
+        self.data().invitations.iter()
+            .find(|i| i.id == invitation_id && i.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn find_invitations_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Invitation>, TenetError> {
+        let mut invitations: Vec<Invitation> = self.data().invitations.iter()
+            .filter(|i| i.db_tenant_id == Some(tenant_id))
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|i| i.created_at);
+        Ok(invitations)
+    }
+
+    fn create_invitation(&self, invitation: &Invitation) -> Result<Invitation, TenetError> {
+        let mut data = self.data();
+        if data.invitations.iter().any(|i| i.id == invitation.id) {
+            return Err(unique_violation("invitations_pkey"));
+        }
+        if data.invitations.iter().any(|i| i.token_hash == invitation.token_hash) {
+            return Err(unique_violation("invitations_token_hash_key"));
+        }
+        check_reference("invitations", "db_tenant_id", data.tenant_exists(invitation.db_tenant_id))?;
+        for role in &invitation.roles {
+            check_reference("invitation_roles", "role_definition_id", data.role_definition_exists(Some(role.role_definition_id)))?;
+            check_reference("invitation_roles", "application_id", data.application_exists(role.application_id))?;
+        }
+
+        data.invitations.push(invitation.clone());
+        Ok(invitation.clone())
+    }
+
+    fn accept_invitation(&self, invitation: &Invitation, invitee: Invitee<'_>, accepted_at: NaiveDateTime) -> Result<Option<User>, TenetError> {
+        let mut data = self.data();
+        let stored_invitation = data.invitations.iter()
+            .find(|i| i.id == invitation.id)
+            .cloned()
+            .ok_or_else(not_found)?;
+        if stored_invitation.accepted_at.is_some() || stored_invitation.revoked_at.is_some() {
+            return Ok(None);
+        }
+        let tenant_id = stored_invitation.db_tenant_id.ok_or_else(not_found)?;
+
+        // Check everything before changing anything, like a rolled back transaction would
+        let user = match invitee {
+            Invitee::New(user) => {
+                let new_user = User {
+                    id: Uuid::new_v4(),
+                    username: user.email.clone(),
+                    created_at: Utc::now().naive_utc(),
+                    updated_at: None,
+                    ..user.clone()
+                };
+                data.check_unique_email(&new_user.email, new_user.id)?;
+                check_reference("users", "db_tenant_id", data.tenant_exists(new_user.db_tenant_id))?;
+                new_user
+            },
+            Invitee::Existing(user_id) => data.users.iter()
+                .find(|u| u.id == user_id)
+                .cloned()
+                .ok_or_else(not_found)?
+        };
+        let mut roles = Vec::new();
+        for invitation_role in &stored_invitation.roles {
+            let role_type = data.role_definitions.iter()
+                .find(|d| d.id == invitation_role.role_definition_id)
+                .map(|d| d.role_type);
+            check_reference("roles", "role_definition_id", role_type.is_some())?;
+            check_reference("roles", "application_id", data.application_exists(invitation_role.application_id))?;
+            roles.push(Role {
+                id: Uuid::new_v4(),
+                role_type: role_type.unwrap(),
+                created_at: Utc::now().naive_utc(),
+                ..invitation_role.to_role(user.id, tenant_id)
+            });
+        }
+
+        if matches!(invitee, Invitee::New(_)) {
+            if let Some(home_tenant_id) = user.db_tenant_id {
+                data.memberships.push((user.id, home_tenant_id));
+            }
+            data.users.push(user.clone());
+        }
+        if !data.is_member(user.id, tenant_id) {
+            data.memberships.push((user.id, tenant_id));
+        }
+        data.roles.extend(roles);
+        if let Some(stored_invitation) = data.invitations.iter_mut().find(|i| i.id == invitation.id) {
+            stored_invitation.accepted_at = Some(accepted_at);
+        }
+        Ok(Some(user))
+    }
+
+    fn revoke_invitation(&self, invitation_id: Uuid, revoked_at: NaiveDateTime) -> Result<(), TenetError> {
+        self.data().invitations.iter_mut()
+            .filter(|i| i.id == invitation_id && i.accepted_at.is_none() && i.revoked_at.is_none())
+            .for_each(|i| i.revoked_at = Some(revoked_at));
+        Ok(())
+    }
+}
+
+impl UserTokenRepository for InMemoryRepository {
+    fn find_user_token_by_token_hash(&self, token_hash: String) -> Result<UserToken, TenetError> {
+        self.data().user_tokens.iter()
+            .find(|t| t.token_hash == token_hash)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    fn create_user_token(&self, user_token: &UserToken) -> Result<UserToken, TenetError> {
+        let mut data = self.data();
+        if data.user_tokens.iter().any(|t| t.id == user_token.id) {
+            return Err(unique_violation("user_tokens_pkey"));
+        }
+        if data.user_tokens.iter().any(|t| t.token_hash == user_token.token_hash) {
+            return Err(unique_violation("user_tokens_token_hash_key"));
+        }
+        check_reference("user_tokens", "user_id", data.user_exists(Some(user_token.user_id)))?;
+        check_reference("user_tokens", "db_tenant_id", data.tenant_exists(user_token.db_tenant_id))?;
+
+        data.user_tokens.push(user_token.clone());
+        Ok(user_token.clone())
+    }
+
+    fn use_user_token(&self, user_token_id: Uuid, used_at: NaiveDateTime) -> Result<bool, TenetError> {
+        let mut data = self.data();
+        let stored_token = data.user_tokens.iter_mut()
+            .find(|t| t.id == user_token_id)
+            .ok_or_else(not_found)?;
+        if stored_token.used_at.is_some() {
+            return Ok(false);
+        }
+        stored_token.used_at = Some(used_at);
+        Ok(true)
+    }
+}
+
+impl PasswordHistoryRepository for InMemoryRepository {
+    fn find_password_history(&self, user_id: Uuid, limit: usize) -> Result<Vec<PasswordHistoryEntry>, TenetError> {
+        let mut entries: Vec<PasswordHistoryEntry> = self.data().password_history.iter()
+            .rev()
+            .filter(|e| e.user_id == user_id)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    fn update_user_password(&self, user: &User, entry: &PasswordHistoryEntry, history_limit: usize) -> Result<User, TenetError> {
+        let mut data = self.data();
+        if data.password_history.iter().any(|e| e.id == entry.id) {
+            return Err(unique_violation("password_history_pkey"));
+        }
+        let stored_user = data.users.iter_mut()
+            .find(|u| u.id == user.id)
+            .ok_or_else(not_found)?;
+        stored_user.password = user.password.clone();
+        stored_user.encryption_mode = user.encryption_mode.clone();
+        stored_user.updated_at = user.updated_at.or(stored_user.updated_at);
+        let updated_user = stored_user.clone();
+
+        data.password_history.push(entry.clone());
+        let mut entries: Vec<&PasswordHistoryEntry> = data.password_history.iter()
+            .rev()
+            .filter(|e| e.user_id == user.id)
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        let kept: Vec<Uuid> = entries.iter().take(history_limit).map(|e| e.id).collect();
+        data.password_history.retain(|e| e.user_id != user.id || kept.contains(&e.id));
+        Ok(updated_user)
+    }
+}
+
+impl PasswordPolicyRepository for InMemoryRepository {
+    fn find_password_policy(&self, tenant_id: Uuid) -> Result<PasswordPolicy, TenetError> {
+        self.data().password_policies.iter()
+            .find(|(id, _)| *id == tenant_id)
+            .map(|(_, policy)| policy.clone())
+            .ok_or_else(not_found)
+    }
+
+    fn save_password_policy(&self, tenant_id: Uuid, policy: &PasswordPolicy) -> Result<PasswordPolicy, TenetError> {
+        let mut data = self.data();
+        check_reference("password_policies", "db_tenant_id", data.tenant_exists(Some(tenant_id)))?;
+
+        data.password_policies.retain(|(id, _)| *id != tenant_id);
+        data.password_policies.push((tenant_id, policy.clone()));
+        Ok(policy.clone())
+    }
+
+    fn delete_password_policy(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        self.data().password_policies.retain(|(id, _)| *id != tenant_id);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{SessionConfig, SessionTokenKind, Tenet, TenetError};
+    use crate::test_suite::{repository_test_suite, user};
+
+    repository_test_suite!(|test| test(Tenet::in_memory()));
+
+    #[test]
+    fn session_config_test() {
+        let expiring = Tenet::in_memory().with_session_config(SessionConfig { lifetime: chrono::Duration::zero(), ..Default::default() });
+        let tenant = expiring.create_tenant("TenantTitle".to_string()).unwrap();
+        let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+        let issued = tenant.create_session(created_user.id).unwrap();
+        assert!(matches!(tenant.validate_session(&issued.token), Err(TenetError::SessionExpiredError)));
+
+        let fixed = Tenet::in_memory().with_session_config(SessionConfig { sliding: false, ..Default::default() });
+        let tenant = fixed.create_tenant("TenantTitle".to_string()).unwrap();
+        let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+        let issued = tenant.create_session(created_user.id).unwrap();
+        assert_eq!(issued.session.expires_at, tenant.validate_session(&issued.token).unwrap().expires_at);
+
+        let signed = Tenet::in_memory().with_session_config(SessionConfig {
+            token_kind: SessionTokenKind::Signed { key: b"secret".to_vec() },
+            ..Default::default()
+        });
+        let tenant = signed.create_tenant("TenantTitle".to_string()).unwrap();
+        let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+        let issued = tenant.create_session(created_user.id).unwrap();
+        assert!(tenant.validate_session(&issued.token).is_ok());
+        assert!(matches!(tenant.validate_session(&format!("{}0", issued.token)), Err(TenetError::InvalidTokenError)));
+    }
+}
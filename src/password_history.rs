@@ -0,0 +1,31 @@
+use chrono::{NaiveDateTime, Utc};
+
+use crate::{encryption_modes::EncryptionModes, User};
+
+
+/// A password a user had before, see [`crate::PasswordPolicy::history_length`].
+///
+/// Only the hash is stored, as it was stored for the user.
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct PasswordHistoryEntry {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    #[serde(skip)]
+    pub password: String,
+    pub encryption_mode: EncryptionModes,
+    /// When the password was replaced
+    pub created_at: NaiveDateTime
+}
+
+impl PasswordHistoryEntry {
+    /// Records the user's current password.
+    pub fn new(user: &User) -> Self {
+        PasswordHistoryEntry {
+            id: uuid::Uuid::new_v4(),
+            user_id: user.id,
+            password: user.password.clone(),
+            encryption_mode: user.encryption_mode.clone(),
+            created_at: Utc::now().naive_utc()
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt::Display;
+
+use crate::breached_passwords::SharedBreachedPasswords;
+use crate::TenetError;
+
+
+/// Passwords rejected by [`PasswordPolicy::recommended`], compared case-insensitively.
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456", "12345678", "123456789", "1234567890", "password", "password1",
+    "password123", "qwerty", "qwerty123", "qwertyuiop", "abc123", "111111",
+    "123123", "000000", "iloveyou", "letmein", "welcome", "welcome1", "admin",
+    "admin123", "monkey", "dragon", "football", "baseball", "sunshine",
+    "princess", "master", "passw0rd", "trustno1", "changeme", "secret"
+];
+
+
+/// Rules new passwords must follow, see [`crate::Tenet::with_password_policy`].
+///
+/// The default only rejects empty passwords and passwords longer than 256
+/// characters. [`PasswordPolicy::recommended`] is stricter. Lengths are
+/// counted in characters.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    /// Requires a character that is neither a letter nor a digit
+    pub require_symbol: bool,
+    /// Passwords rejected regardless of case
+    pub denylist: Vec<String>,
+    /// Rejects passwords containing the user's email address, the part of it
+    /// before the `@` or their full name or a part of it
+    pub forbid_user_data: bool,
+    /// Number of the user's most recent passwords, including the current one,
+    /// a new password must differ from. `0` allows reuse.
+    pub history_length: usize
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 1,
+            max_length: 256,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            denylist: Vec::new(),
+            forbid_user_data: false,
+            history_length: 0
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Follows NIST SP 800-63B: at least 12 characters without composition
+    /// rules, a denylist of common passwords and no user data.
+    pub fn recommended() -> Self {
+        PasswordPolicy {
+            min_length: 12,
+            max_length: 128,
+            denylist: COMMON_PASSWORDS.iter().map(|p| p.to_string()).collect(),
+            forbid_user_data: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns every rule the password of the user with the given email and
+    /// full name breaks, in the order of the fields of the policy.
+    pub fn violations(&self, password: &str, email: &str, full_name: &str) -> Vec<PasswordViolation> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort { min_length: self.min_length });
+        }
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong { max_length: self.max_length });
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violations.push(PasswordViolation::MissingLowercase);
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violations.push(PasswordViolation::MissingUppercase);
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordViolation::MissingDigit);
+        }
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            violations.push(PasswordViolation::MissingSymbol);
+        }
+
+        let lowercase = password.to_lowercase();
+        if self.denylist.iter().any(|p| p.to_lowercase() == lowercase) {
+            violations.push(PasswordViolation::CommonPassword);
+        }
+        if self.forbid_user_data && contains_user_data(&lowercase, email, full_name) {
+            violations.push(PasswordViolation::ContainsUserData);
+        }
+        violations
+    }
+
+    /// Fails with `TenetError::PasswordPolicyError` listing every broken rule.
+    pub fn check(&self, password: &str, email: &str, full_name: &str) -> Result<(), TenetError> {
+        into_result(self.violations(password, email, full_name))
+    }
+
+    /// Like [`PasswordPolicy::check`], additionally rejecting passwords found
+    /// in the instance's breach corpus.
+    pub(crate) fn check_with_breaches(&self, password: &str, email: &str, full_name: &str, breached_passwords: &SharedBreachedPasswords) -> Result<(), TenetError> {
+        into_result(self.violations_with_breaches(password, email, full_name, breached_passwords))
+    }
+
+    pub(crate) fn violations_with_breaches(&self, password: &str, email: &str, full_name: &str, breached_passwords: &SharedBreachedPasswords) -> Vec<PasswordViolation> {
+        let mut violations = self.violations(password, email, full_name);
+        if breached_passwords.contains(password) {
+            violations.push(PasswordViolation::Breached);
+        }
+        violations
+    }
+}
+
+pub(crate) fn into_result(violations: Vec<PasswordViolation>) -> Result<(), TenetError> {
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(TenetError::PasswordPolicyError(violations))
+}
+
+/// Parts shorter than three characters are ignored, so that initials do not
+/// rule out most passwords.
+fn contains_user_data(lowercase_password: &str, email: &str, full_name: &str) -> bool {
+    let email = email.to_lowercase();
+    let full_name = full_name.to_lowercase();
+    let local_part = email.split('@').next().unwrap_or_default();
+
+    [email.as_str(), local_part, full_name.as_str()].into_iter()
+        .chain(full_name.split_whitespace())
+        .filter(|part| part.chars().count() >= 3)
+        .any(|part| lowercase_password.contains(part))
+}
+
+
+/// A rule of a [`PasswordPolicy`] a password breaks.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub enum PasswordViolation {
+    TooShort { min_length: usize },
+    TooLong { max_length: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    CommonPassword,
+    ContainsUserData,
+    /// Found in the breach corpus, see [`crate::BreachedPasswords`]
+    Breached,
+    /// One of the user's last `history_length` passwords
+    RecentlyUsed { history_length: usize }
+}
+
+impl Display for PasswordViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordViolation::TooShort { min_length } => write!(f, "shorter than {} characters", min_length),
+            PasswordViolation::TooLong { max_length } => write!(f, "longer than {} characters", max_length),
+            PasswordViolation::MissingLowercase => write!(f, "no lowercase letter"),
+            PasswordViolation::MissingUppercase => write!(f, "no uppercase letter"),
+            PasswordViolation::MissingDigit => write!(f, "no digit"),
+            PasswordViolation::MissingSymbol => write!(f, "no symbol"),
+            PasswordViolation::CommonPassword => write!(f, "too common"),
+            PasswordViolation::ContainsUserData => write!(f, "contains the email address or name"),
+            PasswordViolation::Breached => write!(f, "found in a data breach"),
+            PasswordViolation::RecentlyUsed { history_length } => write!(f, "one of the last {} passwords", history_length)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_test() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.violations("password", "someone@something.de", "Danny Crane").is_empty());
+        assert_eq!(vec![PasswordViolation::TooShort { min_length: 1 }], policy.violations("", "someone@something.de", "Danny Crane"));
+        assert_eq!(vec![PasswordViolation::TooLong { max_length: 256 }], policy.violations(&"a".repeat(257), "someone@something.de", "Danny Crane"));
+    }
+
+    #[test]
+    fn violations_test() {
+        let policy = PasswordPolicy {
+            min_length: 10,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..PasswordPolicy::recommended()
+        };
+        assert!(policy.violations("Correct-horse-7", "someone@something.de", "Danny Crane").is_empty());
+        assert_eq!(vec![
+            PasswordViolation::TooShort { min_length: 10 },
+            PasswordViolation::MissingUppercase,
+            PasswordViolation::MissingDigit,
+            PasswordViolation::MissingSymbol,
+            PasswordViolation::CommonPassword
+        ], policy.violations("password", "someone@something.de", "Danny Crane"));
+        // Length counts characters, not bytes
+        assert!(!policy.violations("Äöü-ßÄöü-1", "someone@something.de", "Danny Crane").contains(&PasswordViolation::TooShort { min_length: 10 }));
+    }
+
+    #[test]
+    fn user_data_test() {
+        let policy = PasswordPolicy::recommended();
+        assert!(policy.violations("my-name-is-CRANE!", "someone@something.de", "Danny Crane").contains(&PasswordViolation::ContainsUserData));
+        assert!(policy.violations("Someone rides horses", "someone@something.de", "Danny Crane").contains(&PasswordViolation::ContainsUserData));
+        assert!(policy.violations("correct horse battery staple", "someone@something.de", "Al Bo").is_empty());
+
+        let error = policy.check("Secret", "someone@something.de", "Danny Crane").unwrap_err();
+        assert!(matches!(&error, TenetError::PasswordPolicyError(v) if v.len() == 2));
+        assert_eq!("Password violates the policy: shorter than 12 characters, too common", error.to_string());
+    }
+}
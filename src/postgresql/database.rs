@@ -1,9 +1,27 @@
-use diesel::PgConnection;
-use diesel::r2d2::ConnectionManager;
+use diesel::{AsExpression, Connection, ConnectionResult, FromSqlRow, PgConnection};
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::r2d2::{ManageConnection, R2D2Connection};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::HasSqlType;
 use log::info;
+use serde::{Deserialize, Serialize};
 
 use diesel_migrations::EmbeddedMigrations;
 use crate::diesel_migrations::MigrationHarness;
+use crate::schema::sql_types::Uuid;
+
+/// A connection to any of the databases Tenet keeps its own data in.
+///
+/// The `Db*` models only use SQL both backends understand, so they are
+/// written once against this connection.
+#[derive(diesel::MultiConnection)]
+pub enum AnyConnection {
+    Postgresql(PgConnection),
+    #[cfg(feature = "sqlite")]
+    Sqlite(diesel::SqliteConnection),
+}
 
 /// Connection pool type backing a [`crate::Tenet`] instance.
 ///
@@ -11,18 +29,118 @@ use crate::diesel_migrations::MigrationHarness;
 /// constructed with. This is cheap to clone (it's an `Arc` internally), so
 /// multiple `Tenet` instances pointing at different databases can coexist in
 /// the same process without interfering with one another.
-pub type Pool = r2d2::Pool<ConnectionManager<PgConnection>>;
-pub type DbConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
+pub type Pool = r2d2::Pool<ConnectionManager>;
+pub type DbConnection = r2d2::PooledConnection<ConnectionManager>;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
+/// Opens the connections of a [`Pool`] with one fixed backend.
+///
+/// diesel's own manager would try every backend of [`AnyConnection`] in
+/// turn, and SQLite accepts any string as a file name.
+#[derive(Debug)]
+pub struct ConnectionManager {
+    database_url: String,
+    establish: fn(&str) -> ConnectionResult<AnyConnection>
+}
+
+impl ConnectionManager {
+    pub fn new(database_url: &str, establish: fn(&str) -> ConnectionResult<AnyConnection>) -> Self {
+        ConnectionManager {
+            database_url: database_url.to_string(),
+            establish
+        }
+    }
+}
+
+impl ManageConnection for ConnectionManager {
+    type Connection = AnyConnection;
+    type Error = diesel::r2d2::Error;
+
+    fn connect(&self) -> Result<AnyConnection, Self::Error> {
+        (self.establish)(&self.database_url).map_err(diesel::r2d2::Error::ConnectionError)
+    }
+
+    fn is_valid(&self, connection: &mut AnyConnection) -> Result<(), Self::Error> {
+        connection.ping().map_err(diesel::r2d2::Error::QueryError)
+    }
+
+    fn has_broken(&self, connection: &mut AnyConnection) -> bool {
+        std::thread::panicking() || connection.is_broken()
+    }
+}
+
+/// A UUID column value, see [`crate::schema::sql_types::Uuid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Uuid)]
+pub struct DbUuid(pub uuid::Uuid);
+
+impl From<uuid::Uuid> for DbUuid {
+    fn from(value: uuid::Uuid) -> Self {
+        DbUuid(value)
+    }
+}
+
+impl From<DbUuid> for uuid::Uuid {
+    fn from(value: DbUuid) -> Self {
+        value.0
+    }
+}
+
+impl ToSql<Uuid, Pg> for DbUuid {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <uuid::Uuid as ToSql<diesel::sql_types::Uuid, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+impl FromSql<Uuid, Pg> for DbUuid {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        <uuid::Uuid as FromSql<diesel::sql_types::Uuid, Pg>>::from_sql(bytes).map(DbUuid)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ToSql<Uuid, diesel::sqlite::Sqlite> for DbUuid {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::sqlite::Sqlite>) -> serialize::Result {
+        out.set_value(self.0.to_string());
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl FromSql<Uuid, diesel::sqlite::Sqlite> for DbUuid {
+    fn from_sql(bytes: diesel::sqlite::SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        let value = <String as FromSql<diesel::sql_types::Text, diesel::sqlite::Sqlite>>::from_sql(bytes)?;
+        Ok(DbUuid(uuid::Uuid::parse_str(&value)?))
+    }
+}
+
+impl HasSqlType<Uuid> for MultiBackend {
+    fn metadata(lookup: &mut Self::MetadataLookup) -> Self::TypeMetadata {
+        MultiBackend::lookup_sql_type::<Uuid>(lookup)
+    }
+}
+
+impl ToSql<Uuid, MultiBackend> for DbUuid {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, MultiBackend>) -> serialize::Result {
+        out.set_value((Uuid, self));
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Uuid, MultiBackend> for DbUuid {
+    fn from_sql(bytes: <MultiBackend as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        bytes.from_sql::<DbUuid, Uuid>()
+    }
+}
+
 /// Builds a new connection pool for the given connection string and runs
 /// any pending migrations against it.
 pub fn build_pool(connection_string: &str) -> Pool {
     info!("Initializing Tenet Database");
     info!("Tenet connection string: {}", connection_string);
 
-    let manager = ConnectionManager::<PgConnection>::new(connection_string);
+    let manager = ConnectionManager::new(connection_string, |url| PgConnection::establish(url).map(AnyConnection::Postgresql));
     let pool = Pool::new(manager).expect("Failed to create db pool");
 
     info!("Running pending database migrations...");
@@ -9,9 +9,9 @@ use diesel::{
 use diesel::prelude::*;
 
 use super::database;
-use super::database::Pool;
+use super::database::{DbUuid, Pool};
 use super::dbtenant::DbTenant;
-use crate::TenetError;
+use crate::{Application, TenetError};
 use crate::schema::applications;
 
 
@@ -20,8 +20,8 @@ use crate::schema::applications;
 #[diesel(table_name = applications)]
 pub struct DbApplicationMessage {
     pub application_type: String,
-    pub storage_id: Option<uuid::Uuid>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub storage_id: Option<DbUuid>,
+    pub db_tenant_id: Option<DbUuid>
 }
 
 
@@ -30,19 +30,19 @@ pub struct DbApplicationMessage {
 #[diesel(belongs_to(DbTenant))]
 #[diesel(table_name = applications)]
 pub struct DbApplication {
-    pub id: uuid::Uuid,
+    pub id: DbUuid,
     pub application_type: String,
-    pub storage_id: Option<uuid::Uuid>,
+    pub storage_id: Option<DbUuid>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub db_tenant_id: Option<DbUuid>
 }
 
 
 impl From<DbApplicationMessage> for DbApplication {
     fn from(application: DbApplicationMessage) -> Self {
         DbApplication {
-            id: Uuid::new_v4(),
+            id: DbUuid(Uuid::new_v4()),
             application_type: application.application_type,
             storage_id: application.storage_id,
             created_at: Utc::now().naive_utc(),
@@ -52,6 +52,16 @@ impl From<DbApplicationMessage> for DbApplication {
     }
 }
 
+impl From<&Application> for DbApplicationMessage {
+    fn from(application: &Application) -> Self {
+        DbApplicationMessage {
+            application_type: application.application_type.to_string(),
+            storage_id: application.storage_id.map(DbUuid),
+            db_tenant_id: application.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
 
 impl DbApplication {
     pub fn find_all(pool: &Pool) -> Result<Vec<Self>, TenetError> {
@@ -62,15 +72,15 @@ impl DbApplication {
 
     pub fn find_by_tenant(pool: &Pool, id: Uuid) -> Result<Vec<Self>, TenetError> {
         let mut connection = database::connection(pool)?;
-        let applications = applications::table.filter(applications::db_tenant_id.eq(id)).load(&mut connection)?;
+        let applications = applications::table.filter(applications::db_tenant_id.eq(Some(DbUuid(id)))).load(&mut connection)?;
         Ok(applications)
     }
 
     pub fn find(pool: &Pool, tenant_id: uuid::Uuid, application_id: Uuid) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
         let application = applications::table
-            .filter(applications::id.eq(application_id))
-            .filter(applications::db_tenant_id.eq(tenant_id))
+            .filter(applications::id.eq(DbUuid(application_id)))
+            .filter(applications::db_tenant_id.eq(Some(DbUuid(tenant_id))))
             .first(&mut connection)?;
         Ok(application)
     }
@@ -90,7 +100,7 @@ impl DbApplication {
         let mut connection = database::connection(pool)?;
 
         let updated_application = diesel::update(applications::table)
-            .filter(applications::id.eq(id))
+            .filter(applications::id.eq(DbUuid(id)))
             .set(application)
             .get_result(&mut connection)?;
         Ok(updated_application)
@@ -100,7 +110,7 @@ impl DbApplication {
         let mut connection = database::connection(pool)?;
 
         let result = diesel::delete(
-            applications::table.filter(applications::id.eq(id))
+            applications::table.filter(applications::id.eq(DbUuid(id)))
             )
             .execute(&mut connection)?;
         Ok(result)
@@ -0,0 +1,180 @@
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use super::dbuser::DbUser;
+use crate::{Group, TenetError};
+use crate::schema::{group_members, groups, users};
+
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, AsChangeset)]
+#[diesel(table_name = groups)]
+pub struct DbGroupMessage {
+    pub name: String,
+    pub updated_at: Option<NaiveDateTime>
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(table_name = groups)]
+pub struct DbGroup {
+    pub id: DbUuid,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = group_members)]
+pub struct DbGroupMember {
+    pub group_id: DbUuid,
+    pub user_id: DbUuid,
+    pub created_at: NaiveDateTime
+}
+
+
+impl From<&Group> for DbGroup {
+    fn from(group: &Group) -> Self {
+        DbGroup {
+            id: DbUuid(group.id),
+            name: group.name.clone(),
+            created_at: group.created_at,
+            updated_at: group.updated_at,
+            db_tenant_id: group.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
+impl From<&Group> for DbGroupMessage {
+    fn from(group: &Group) -> Self {
+        DbGroupMessage {
+            name: group.name.clone(),
+            updated_at: Some(Utc::now().naive_utc())
+        }
+    }
+}
+
+impl From<&DbGroup> for Group {
+    fn from(value: &DbGroup) -> Self {
+        Group {
+            id: value.id.0,
+            name: value.name.clone(),
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+
+
+impl DbGroup {
+    pub fn find_by_tenant(pool: &Pool, tenant_id: Uuid) -> Result<Vec<Self>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let groups = groups::table
+            .filter(groups::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .order(groups::name)
+            .load(&mut connection)?;
+        Ok(groups)
+    }
+
+    pub fn find(pool: &Pool, tenant_id: Uuid, id: Uuid) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let group = groups::table
+            .filter(groups::id.eq(DbUuid(id)))
+            .filter(groups::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .first(&mut connection)?;
+        Ok(group)
+    }
+
+    pub fn find_by_user(pool: &Pool, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Self>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let groups = groups::table
+            .inner_join(group_members::table)
+            .filter(groups::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .filter(group_members::user_id.eq(DbUuid(user_id)))
+            .order(groups::name)
+            .select(groups::all_columns)
+            .load(&mut connection)?;
+        Ok(groups)
+    }
+
+    pub fn create(pool: &Pool, group: DbGroup) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let db_group = diesel::insert_into(groups::table)
+            .values(group)
+            .get_result(&mut connection)?;
+        Ok(db_group)
+    }
+
+    pub fn update(pool: &Pool, id: Uuid, group: DbGroupMessage) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let updated_group = diesel::update(groups::table)
+            .filter(groups::id.eq(DbUuid(id)))
+            .set(group)
+            .get_result(&mut connection)?;
+        Ok(updated_group)
+    }
+
+    pub fn delete(pool: &Pool, id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let result = diesel::delete(
+            groups::table.filter(groups::id.eq(DbUuid(id)))
+            )
+            .execute(&mut connection)?;
+        Ok(result)
+    }
+
+    pub fn find_members(pool: &Pool, tenant_id: Uuid, id: Uuid) -> Result<Vec<DbUser>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let users = users::table
+            .inner_join(group_members::table.inner_join(groups::table))
+            .filter(groups::id.eq(DbUuid(id)))
+            .filter(groups::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .order(users::email)
+            .select(users::all_columns)
+            .load(&mut connection)?;
+        Ok(users)
+    }
+
+    pub fn add_member(pool: &Pool, id: Uuid, user_id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let member = DbGroupMember {
+            group_id: DbUuid(id),
+            user_id: DbUuid(user_id),
+            created_at: Utc::now().naive_utc()
+        };
+        let result = diesel::insert_into(group_members::table)
+            .values(member)
+            .execute(&mut connection)?;
+        Ok(result)
+    }
+
+    pub fn remove_member(pool: &Pool, id: Uuid, user_id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let result = diesel::delete(
+            group_members::table
+                .filter(group_members::group_id.eq(DbUuid(id)))
+                .filter(group_members::user_id.eq(DbUuid(user_id)))
+            )
+            .execute(&mut connection)?;
+        Ok(result)
+    }
+}
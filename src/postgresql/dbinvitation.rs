@@ -0,0 +1,217 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{AnyConnection, DbUuid, Pool};
+use super::dbmembership::DbMembership;
+use super::dbrole::DbRole;
+use super::dbtenant::DbTenant;
+use super::dbuser::DbUser;
+use crate::{Invitation, InvitationRole, TenetError};
+use crate::schema::{invitation_roles, invitations, memberships, roles, users};
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(table_name = invitations)]
+pub struct DbInvitation {
+    pub id: DbUuid,
+    pub email: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = invitation_roles)]
+pub struct DbInvitationRole {
+    pub id: DbUuid,
+    pub invitation_id: DbUuid,
+    pub role_definition_id: DbUuid,
+    pub application_id: Option<DbUuid>
+}
+
+/// An invitation together with its roles.
+pub type DbInvitationWithRoles = (DbInvitation, Vec<InvitationRole>);
+
+
+impl From<&Invitation> for DbInvitation {
+    fn from(invitation: &Invitation) -> Self {
+        DbInvitation {
+            id: DbUuid(invitation.id),
+            email: invitation.email.clone(),
+            token_hash: invitation.token_hash.clone(),
+            created_at: invitation.created_at,
+            expires_at: invitation.expires_at,
+            accepted_at: invitation.accepted_at,
+            revoked_at: invitation.revoked_at,
+            db_tenant_id: invitation.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
+impl From<&DbInvitationWithRoles> for Invitation {
+    fn from((value, roles): &DbInvitationWithRoles) -> Self {
+        Invitation {
+            id: value.id.0,
+            email: value.email.clone(),
+            roles: roles.clone(),
+            token_hash: value.token_hash.clone(),
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            accepted_at: value.accepted_at,
+            revoked_at: value.revoked_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+
+
+impl DbInvitation {
+    pub fn find_by_token_hash(pool: &Pool, token_hash: String) -> Result<DbInvitationWithRoles, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let invitation = invitations::table
+            .filter(invitations::token_hash.eq(token_hash))
+            .first::<DbInvitation>(&mut connection)?;
+        single(with_roles(&mut connection, vec![invitation])?)
+    }
+
+    pub fn find(pool: &Pool, tenant_id: Uuid, id: Uuid) -> Result<DbInvitationWithRoles, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let invitation = invitations::table
+            .filter(invitations::id.eq(DbUuid(id)))
+            .filter(invitations::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .first::<DbInvitation>(&mut connection)?;
+        single(with_roles(&mut connection, vec![invitation])?)
+    }
+
+    pub fn find_by_tenant(pool: &Pool, tenant_id: Uuid) -> Result<Vec<DbInvitationWithRoles>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let invitations = invitations::table
+            .filter(invitations::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .order(invitations::created_at)
+            .load::<DbInvitation>(&mut connection)?;
+        with_roles(&mut connection, invitations)
+    }
+
+    pub fn create(pool: &Pool, invitation: DbInvitation, roles: &[InvitationRole]) -> Result<DbInvitationWithRoles, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        connection.transaction(|connection| {
+            let db_invitation: DbInvitation = diesel::insert_into(invitations::table)
+                .values(invitation)
+                .get_result(connection)?;
+            let db_invitation_roles: Vec<DbInvitationRole> = roles.iter()
+                .map(|role| DbInvitationRole {
+                    id: DbUuid(Uuid::new_v4()),
+                    invitation_id: db_invitation.id,
+                    role_definition_id: DbUuid(role.role_definition_id),
+                    application_id: role.application_id.map(DbUuid)
+                })
+                .collect();
+            // AnyConnection does not support batch inserts
+            for db_invitation_role in db_invitation_roles {
+                diesel::insert_into(invitation_roles::table)
+                    .values(db_invitation_role)
+                    .execute(connection)?;
+            }
+            single(with_roles(connection, vec![db_invitation])?)
+        })
+    }
+
+    /// Sets `accepted_at` unless the invitation is accepted or revoked, then stores `new_user` with
+    /// their home membership, `membership` unless it exists and `roles` in the same transaction.
+    ///
+    /// Returns the invited user, or `None` if the invitation could not be accepted.
+    pub fn accept(pool: &Pool, id: Uuid, accepted_at: NaiveDateTime, new_user: Option<DbUser>, membership: DbMembership, roles: Vec<DbRole>) -> Result<Option<DbUser>, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        connection.transaction(|connection| {
+            let updated = diesel::update(invitations::table)
+                .filter(invitations::id.eq(DbUuid(id)))
+                .filter(invitations::accepted_at.is_null())
+                .filter(invitations::revoked_at.is_null())
+                .set(invitations::accepted_at.eq(Some(accepted_at)))
+                .execute(connection)?;
+            if updated == 0 {
+                return Ok(None);
+            }
+
+            if let Some(new_user) = new_user {
+                let db_user: DbUser = diesel::insert_into(users::table)
+                    .values(new_user)
+                    .get_result(connection)?;
+                if let Some(tenant_id) = db_user.db_tenant_id {
+                    DbMembership::insert(connection, db_user.id.0, tenant_id.0)?;
+                }
+            }
+            let user_id = membership.user_id;
+            // ON CONFLICT is not part of the SQL both backends share, so it
+            // runs on the inner connection.
+            let insert_membership = diesel::insert_into(memberships::table)
+                .values(membership)
+                .on_conflict_do_nothing();
+            match &mut **connection {
+                AnyConnection::Postgresql(connection) => insert_membership.execute(connection)?,
+                #[cfg(feature = "sqlite")]
+                AnyConnection::Sqlite(connection) => insert_membership.execute(connection)?,
+            };
+            for role in roles {
+                diesel::insert_into(roles::table)
+                    .values(role)
+                    .execute(connection)?;
+            }
+            let user = users::table
+                .filter(users::id.eq(user_id))
+                .first(connection)?;
+            Ok(Some(user))
+        })
+    }
+
+    pub fn revoke(pool: &Pool, id: Uuid, revoked_at: NaiveDateTime) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let updated = diesel::update(invitations::table)
+            .filter(invitations::id.eq(DbUuid(id)))
+            .filter(invitations::accepted_at.is_null())
+            .filter(invitations::revoked_at.is_null())
+            .set(invitations::revoked_at.eq(Some(revoked_at)))
+            .execute(&mut connection)?;
+        Ok(updated)
+    }
+}
+
+fn with_roles(connection: &mut AnyConnection, invitations: Vec<DbInvitation>) -> Result<Vec<DbInvitationWithRoles>, TenetError> {
+    let ids: Vec<&DbUuid> = invitations.iter().map(|i| &i.id).collect();
+    let db_invitation_roles: Vec<DbInvitationRole> = invitation_roles::table
+        .filter(invitation_roles::invitation_id.eq_any(ids))
+        .load(connection)?;
+
+    Ok(invitations.into_iter()
+        .map(|i| {
+            let roles = db_invitation_roles.iter()
+                .filter(|r| r.invitation_id == i.id)
+                .map(|r| InvitationRole {
+                    role_definition_id: r.role_definition_id.0,
+                    application_id: r.application_id.map(Uuid::from)
+                })
+                .collect();
+            (i, roles)
+        })
+        .collect())
+}
+
+fn single(mut invitations: Vec<DbInvitationWithRoles>) -> Result<DbInvitationWithRoles, TenetError> {
+    invitations.pop().ok_or(TenetError::DatabaseError(diesel::result::Error::NotFound))
+}
@@ -0,0 +1,87 @@
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{AnyConnection, DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use crate::TenetError;
+use crate::schema::{group_members, groups, memberships, refresh_tokens, roles, sessions, tenants};
+
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = memberships)]
+pub struct DbMembership {
+    pub user_id: DbUuid,
+    pub db_tenant_id: DbUuid,
+    pub created_at: NaiveDateTime
+}
+
+
+
+impl DbMembership {
+    pub fn find_tenants_by_user(pool: &Pool, user_id: Uuid) -> Result<Vec<DbTenant>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let tenants = tenants::table
+            .inner_join(memberships::table)
+            .filter(memberships::user_id.eq(DbUuid(user_id)))
+            .order(tenants::title)
+            .select(tenants::all_columns)
+            .load(&mut connection)?;
+        Ok(tenants)
+    }
+
+    pub fn create(pool: &Pool, user_id: Uuid, tenant_id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+        DbMembership::insert(&mut connection, user_id, tenant_id)
+    }
+
+    pub fn insert(connection: &mut AnyConnection, user_id: Uuid, tenant_id: Uuid) -> Result<usize, TenetError> {
+        let membership = DbMembership {
+            user_id: DbUuid(user_id),
+            db_tenant_id: DbUuid(tenant_id),
+            created_at: Utc::now().naive_utc()
+        };
+        let result = diesel::insert_into(memberships::table)
+            .values(membership)
+            .execute(connection)?;
+        Ok(result)
+    }
+
+    /// Removes the membership together with the user's roles and group
+    /// memberships in the tenant and revokes their sessions and refresh
+    /// tokens there, all in one transaction.
+    pub fn delete(pool: &Pool, user_id: Uuid, tenant_id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let now = Utc::now().naive_utc();
+
+        connection.transaction(|connection| {
+            diesel::delete(roles::table.filter(roles::db_tenant_id.eq(DbUuid(tenant_id))).filter(roles::user_id.eq(DbUuid(user_id))))
+                .execute(connection)?;
+            let group_ids = groups::table
+                .filter(groups::db_tenant_id.eq(DbUuid(tenant_id)))
+                .select(groups::id);
+            diesel::delete(group_members::table.filter(group_members::user_id.eq(DbUuid(user_id))).filter(group_members::group_id.eq_any(group_ids)))
+                .execute(connection)?;
+            diesel::update(sessions::table.filter(sessions::db_tenant_id.eq(DbUuid(tenant_id))).filter(sessions::user_id.eq(DbUuid(user_id))).filter(sessions::revoked_at.is_null()))
+                .set(sessions::revoked_at.eq(now))
+                .execute(connection)?;
+            diesel::update(refresh_tokens::table.filter(refresh_tokens::db_tenant_id.eq(DbUuid(tenant_id))).filter(refresh_tokens::user_id.eq(DbUuid(user_id))).filter(refresh_tokens::revoked_at.is_null()))
+                .set(refresh_tokens::revoked_at.eq(now))
+                .execute(connection)?;
+            let result = diesel::delete(
+                memberships::table
+                    .filter(memberships::user_id.eq(DbUuid(user_id)))
+                    .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
+                )
+                .execute(connection)?;
+            Ok(result)
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{DbUuid, Pool};
+use super::dbuser::{DbUser, DbUserMessage};
+use crate::{encryption_modes::EncryptionModes, PasswordHistoryEntry, TenetError};
+use crate::schema::{password_history, users};
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = password_history)]
+pub struct DbPasswordHistoryEntry {
+    pub id: DbUuid,
+    pub user_id: DbUuid,
+    pub password: String,
+    pub encryption_mode: String,
+    pub created_at: NaiveDateTime
+}
+
+
+impl From<&PasswordHistoryEntry> for DbPasswordHistoryEntry {
+    fn from(entry: &PasswordHistoryEntry) -> Self {
+        DbPasswordHistoryEntry {
+            id: DbUuid(entry.id),
+            user_id: DbUuid(entry.user_id),
+            password: entry.password.clone(),
+            encryption_mode: entry.encryption_mode.to_string(),
+            created_at: entry.created_at
+        }
+    }
+}
+
+impl From<&DbPasswordHistoryEntry> for PasswordHistoryEntry {
+    fn from(value: &DbPasswordHistoryEntry) -> Self {
+        PasswordHistoryEntry {
+            id: value.id.0,
+            user_id: value.user_id.0,
+            password: value.password.clone(),
+            encryption_mode: EncryptionModes::from_str(&value.encryption_mode).unwrap(),
+            created_at: value.created_at
+        }
+    }
+}
+
+
+
+impl DbPasswordHistoryEntry {
+    /// Returns the newest `limit` entries of the user, newest first.
+    pub fn find_by_user(pool: &Pool, user_id: Uuid, limit: usize) -> Result<Vec<Self>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let entries = password_history::table
+            .filter(password_history::user_id.eq(DbUuid(user_id)))
+            .order(password_history::created_at.desc())
+            .limit(limit as i64)
+            .load(&mut connection)?;
+        Ok(entries)
+    }
+
+    /// Updates the user with a new password and stores the entry for their
+    /// previous one in one transaction, then removes all but the newest
+    /// `history_limit` entries of the user.
+    pub fn create_with_user(pool: &Pool, entry: DbPasswordHistoryEntry, user: DbUserMessage, history_limit: usize) -> Result<DbUser, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        connection.transaction(|connection| {
+            let user_id = entry.user_id;
+            let db_user = diesel::update(users::table)
+                .filter(users::id.eq(user_id))
+                .set(user)
+                .get_result(connection)?;
+            diesel::insert_into(password_history::table)
+                .values(entry)
+                .execute(connection)?;
+
+            let kept: Vec<DbUuid> = password_history::table
+                .filter(password_history::user_id.eq(user_id))
+                .order(password_history::created_at.desc())
+                .limit(history_limit as i64)
+                .select(password_history::id)
+                .load(connection)?;
+            diesel::delete(password_history::table)
+                .filter(password_history::user_id.eq(user_id))
+                .filter(diesel::dsl::not(password_history::id.eq_any(kept)))
+                .execute(connection)?;
+            Ok(db_user)
+        })
+    }
+}
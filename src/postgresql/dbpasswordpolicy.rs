@@ -0,0 +1,85 @@
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+use super::database;
+use super::database::{AnyConnection, DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use crate::{PasswordPolicy, TenetError};
+use crate::schema::password_policies;
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(primary_key(db_tenant_id))]
+#[diesel(table_name = password_policies)]
+pub struct DbPasswordPolicy {
+    pub db_tenant_id: DbUuid,
+    /// The policy serialized as JSON
+    pub policy: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>
+}
+
+
+impl DbPasswordPolicy {
+    pub fn new(tenant_id: Uuid, policy: &PasswordPolicy) -> Result<Self, TenetError> {
+        Ok(DbPasswordPolicy {
+            db_tenant_id: DbUuid(tenant_id),
+            policy: serde_json::to_string(policy)?,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None
+        })
+    }
+
+    pub fn policy(&self) -> Result<PasswordPolicy, TenetError> {
+        Ok(serde_json::from_str(&self.policy)?)
+    }
+
+    pub fn find_by_tenant(pool: &Pool, tenant_id: Uuid) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let policy = password_policies::table
+            .filter(password_policies::db_tenant_id.eq(DbUuid(tenant_id)))
+            .first(&mut connection)?;
+        Ok(policy)
+    }
+
+    /// Stores the policy of the tenant, replacing a previous one.
+    pub fn save(pool: &Pool, policy: DbPasswordPolicy) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        // ON CONFLICT is not part of the SQL both backends share, so the
+        // upsert runs on the inner connection.
+        let upsert = diesel::insert_into(password_policies::table)
+            .values(policy)
+            .on_conflict(password_policies::db_tenant_id)
+            .do_update()
+            .set((
+                password_policies::policy.eq(excluded(password_policies::policy)),
+                password_policies::updated_at.eq(Some(Utc::now().naive_utc()))
+            ));
+        let db_policy = match &mut *connection {
+            AnyConnection::Postgresql(connection) => upsert.get_result(connection)?,
+            #[cfg(feature = "sqlite")]
+            AnyConnection::Sqlite(connection) => upsert.get_result(connection)?,
+        };
+        Ok(db_policy)
+    }
+
+    pub fn delete(pool: &Pool, tenant_id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let result = diesel::delete(
+            password_policies::table.filter(password_policies::db_tenant_id.eq(DbUuid(tenant_id)))
+            )
+            .execute(&mut connection)?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use crate::{RefreshToken, TenetError};
+use crate::schema::refresh_tokens;
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(table_name = refresh_tokens)]
+pub struct DbRefreshToken {
+    pub id: DbUuid,
+    pub family_id: DbUuid,
+    pub user_id: DbUuid,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
+}
+
+
+impl From<&RefreshToken> for DbRefreshToken {
+    fn from(refresh_token: &RefreshToken) -> Self {
+        DbRefreshToken {
+            id: DbUuid(refresh_token.id),
+            family_id: DbUuid(refresh_token.family_id),
+            user_id: DbUuid(refresh_token.user_id),
+            token_hash: refresh_token.token_hash.clone(),
+            created_at: refresh_token.created_at,
+            expires_at: refresh_token.expires_at,
+            used_at: refresh_token.used_at,
+            revoked_at: refresh_token.revoked_at,
+            db_tenant_id: refresh_token.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
+impl From<&DbRefreshToken> for RefreshToken {
+    fn from(value: &DbRefreshToken) -> Self {
+        RefreshToken {
+            id: value.id.0,
+            family_id: value.family_id.0,
+            user_id: value.user_id.0,
+            token_hash: value.token_hash.clone(),
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            used_at: value.used_at,
+            revoked_at: value.revoked_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+
+
+impl DbRefreshToken {
+    pub fn find_by_token_hash(pool: &Pool, token_hash: String) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let refresh_token = refresh_tokens::table
+            .filter(refresh_tokens::token_hash.eq(token_hash))
+            .first(&mut connection)?;
+        Ok(refresh_token)
+    }
+
+    pub fn find_by_user(pool: &Pool, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Self>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let refresh_tokens = refresh_tokens::table
+            .filter(refresh_tokens::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .filter(refresh_tokens::user_id.eq(DbUuid(user_id)))
+            .load(&mut connection)?;
+        Ok(refresh_tokens)
+    }
+
+    pub fn create(pool: &Pool, refresh_token: DbRefreshToken) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let db_refresh_token = diesel::insert_into(refresh_tokens::table)
+            .values(refresh_token)
+            .get_result(&mut connection)?;
+        Ok(db_refresh_token)
+    }
+
+    /// Sets `used_at` unless it is already set, returns the number of changed rows.
+    pub fn mark_used(pool: &Pool, id: Uuid, used_at: NaiveDateTime) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let updated = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::id.eq(DbUuid(id)))
+            .filter(refresh_tokens::used_at.is_null())
+            .set(refresh_tokens::used_at.eq(Some(used_at)))
+            .execute(&mut connection)?;
+        Ok(updated)
+    }
+
+    pub fn revoke_family(pool: &Pool, family_id: Uuid, revoked_at: NaiveDateTime) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let updated = diesel::update(refresh_tokens::table)
+            .filter(refresh_tokens::family_id.eq(DbUuid(family_id)))
+            .filter(refresh_tokens::revoked_at.is_null())
+            .set(refresh_tokens::revoked_at.eq(Some(revoked_at)))
+            .execute(&mut connection)?;
+        Ok(updated)
+    }
+}
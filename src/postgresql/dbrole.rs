@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use chrono::{NaiveDateTime, Utc};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
@@ -9,20 +11,21 @@ use diesel::{
 use diesel::prelude::*;
 
 use super::database;
-use super::database::Pool;
+use super::database::{DbUuid, Pool};
 use super::dbtenant::DbTenant;
-use crate::TenetError;
-use crate::schema::roles;
+use crate::{role_type::RoleType, Role, TenetError};
+use crate::schema::{group_members, role_definitions, roles};
 
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, AsChangeset)]
 #[diesel(belongs_to(DbTenant))]
 #[diesel(table_name = roles)]
 pub struct DbRoleMessage {
-    pub role_type: String,
-    pub user_id: Option<uuid::Uuid>,
-    pub application_id: Option<uuid::Uuid>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub role_definition_id: DbUuid,
+    pub user_id: Option<DbUuid>,
+    pub group_id: Option<DbUuid>,
+    pub application_id: Option<DbUuid>,
+    pub db_tenant_id: Option<DbUuid>
 }
 
 
@@ -31,22 +34,27 @@ pub struct DbRoleMessage {
 #[diesel(belongs_to(DbTenant))]
 #[diesel(table_name = roles)]
 pub struct DbRole {
-    pub id: uuid::Uuid,
-    pub role_type: String,
-    pub user_id: Option<uuid::Uuid>,
-    pub application_id: Option<uuid::Uuid>,
+    pub id: DbUuid,
+    pub role_definition_id: DbUuid,
+    pub user_id: Option<DbUuid>,
+    pub group_id: Option<DbUuid>,
+    pub application_id: Option<DbUuid>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub db_tenant_id: Option<DbUuid>
 }
 
+/// A role together with the role type of its definition.
+pub type DbRoleWithType = (DbRole, String);
+
 
 impl From<DbRoleMessage> for DbRole {
     fn from(role: DbRoleMessage) -> Self {
         DbRole {
-            id: Uuid::new_v4(),
-            role_type: role.role_type,
+            id: DbUuid(Uuid::new_v4()),
+            role_definition_id: role.role_definition_id,
             user_id: role.user_id,
+            group_id: role.group_id,
             application_id: role.application_id,
             created_at: Utc::now().naive_utc(),
             updated_at: None,
@@ -55,55 +63,124 @@ impl From<DbRoleMessage> for DbRole {
     }
 }
 
+impl From<&DbRoleWithType> for Role {
+    fn from((value, role_type): &DbRoleWithType) -> Self {
+        Role {
+            id: value.id.0,
+            role_type: RoleType::from_str(role_type).unwrap(),
+            role_definition_id: Some(value.role_definition_id.0),
+            user_id: value.user_id.map(Uuid::from),
+            group_id: value.group_id.map(Uuid::from),
+            application_id: value.application_id.map(Uuid::from),
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+impl From<&Role> for DbRoleMessage {
+    fn from(role: &Role) -> Self {
+        DbRoleMessage {
+            // An unresolved definition fails the foreign key like any other missing reference
+            role_definition_id: DbUuid(role.role_definition_id.unwrap_or_default()),
+            user_id: role.user_id.map(DbUuid),
+            group_id: role.group_id.map(DbUuid),
+            application_id: role.application_id.map(DbUuid),
+            db_tenant_id: role.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
 
 
 impl DbRole {
-    pub fn find_all(pool: &Pool) -> Result<Vec<Self>, TenetError> {
+    pub fn find_all(pool: &Pool) -> Result<Vec<DbRoleWithType>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let roles = roles::table
+            .inner_join(role_definitions::table)
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)?;
+        Ok(roles)
+    }
+
+    pub fn find_by_tenant(pool: &Pool, id: Uuid) -> Result<Vec<DbRoleWithType>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let roles = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(Some(DbUuid(id))))
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)?;
+        Ok(roles)
+    }
+
+    pub fn find_by_user(pool: &Pool, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<DbRoleWithType>, TenetError> {
         let mut connection = database::connection(pool)?;
-        let roles = roles::table.load::<DbRole>(&mut connection)?;
+        let roles = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .filter(roles::user_id.eq(Some(DbUuid(user_id))))
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)?;
         Ok(roles)
     }
 
-    pub fn find_by_tenant(pool: &Pool, id: Uuid) -> Result<Vec<Self>, TenetError> {
+    pub fn find_by_group(pool: &Pool, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<DbRoleWithType>, TenetError> {
         let mut connection = database::connection(pool)?;
-        let roles = roles::table.filter(roles::db_tenant_id.eq(id)).load(&mut connection)?;
+        let roles = roles::table
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .filter(roles::group_id.eq(Some(DbUuid(group_id))))
+            .select((roles::all_columns, role_definitions::role_type))
+            .load(&mut connection)?;
         Ok(roles)
     }
 
-    pub fn find_by_user(pool: &Pool, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Self>, TenetError> {
+    pub fn find_inherited_by_user(pool: &Pool, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<DbRoleWithType>, TenetError> {
         let mut connection = database::connection(pool)?;
+        let group_ids = group_members::table
+            .filter(group_members::user_id.eq(DbUuid(user_id)))
+            .select(group_members::group_id.nullable());
         let roles = roles::table
-            .filter(roles::db_tenant_id.eq(tenant_id))
-            .filter(roles::user_id.eq(user_id))
+            .inner_join(role_definitions::table)
+            .filter(roles::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .filter(roles::group_id.eq_any(group_ids))
+            .select((roles::all_columns, role_definitions::role_type))
             .load(&mut connection)?;
         Ok(roles)
     }
 
-    pub fn find(pool: &Pool, tenant_id: uuid::Uuid, id: Uuid) -> Result<Self, TenetError> {
+    pub fn find(pool: &Pool, tenant_id: uuid::Uuid, id: Uuid) -> Result<DbRoleWithType, TenetError> {
         let mut connection = database::connection(pool)?;
         let role = roles::table
-            .filter(roles::id.eq(id))
-            .filter(roles::db_tenant_id.eq(tenant_id))
+            .inner_join(role_definitions::table)
+            .filter(roles::id.eq(DbUuid(id)))
+            .filter(roles::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .select((roles::all_columns, role_definitions::role_type))
             .first(&mut connection)?;
         Ok(role)
     }
 
-    pub fn create(pool: &Pool, role: DbRoleMessage) -> Result<Self, TenetError> {
+    pub fn create(pool: &Pool, role: DbRoleMessage) -> Result<DbRoleWithType, TenetError> {
         let mut connection = database::connection(pool)?;
 
         let new_role = DbRole::from(role);
 
-        let db_role = diesel::insert_into(roles::table)
+        let db_role: DbRole = diesel::insert_into(roles::table)
             .values(new_role)
             .get_result(&mut connection)?;
-        Ok(db_role)
+        let role_type = role_definitions::table
+            .filter(role_definitions::id.eq(&db_role.role_definition_id))
+            .select(role_definitions::role_type)
+            .first(&mut connection)?;
+        Ok((db_role, role_type))
     }
 
-    pub fn update(pool: &Pool, id: Uuid, role: DbRoleMessage) -> Result<Self, TenetError> {
+    pub fn update(pool: &Pool, id: Uuid, role: DbRoleMessage) -> Result<DbRole, TenetError> {
         let mut connection = database::connection(pool)?;
 
         let updated_role = diesel::update(roles::table)
-            .filter(roles::id.eq(id))
+            .filter(roles::id.eq(DbUuid(id)))
             .set(role)
             .get_result(&mut connection)?;
         Ok(updated_role)
@@ -113,9 +190,9 @@ impl DbRole {
         let mut connection = database::connection(pool)?;
 
         let result = diesel::delete(
-            roles::table.filter(roles::id.eq(id))
+            roles::table.filter(roles::id.eq(DbUuid(id)))
             )
             .execute(&mut connection)?;
         Ok(result)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,204 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{AnyConnection, DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use crate::{role_type::RoleType, RoleDefinition, TenetError};
+use crate::schema::{permissions, role_definitions};
+
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, AsChangeset)]
+#[diesel(table_name = role_definitions)]
+pub struct DbRoleDefinitionMessage {
+    pub name: String,
+    pub role_type: String,
+    pub updated_at: Option<NaiveDateTime>
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(table_name = role_definitions)]
+pub struct DbRoleDefinition {
+    pub id: DbUuid,
+    pub name: String,
+    pub role_type: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Queryable, Insertable)]
+#[diesel(table_name = permissions)]
+pub struct DbPermission {
+    pub id: DbUuid,
+    pub role_definition_id: DbUuid,
+    pub name: String,
+    pub created_at: NaiveDateTime
+}
+
+/// A role definition together with the names of its permissions.
+pub type DbRoleDefinitionWithPermissions = (DbRoleDefinition, Vec<String>);
+
+
+impl From<&RoleDefinition> for DbRoleDefinition {
+    fn from(role_definition: &RoleDefinition) -> Self {
+        DbRoleDefinition {
+            id: DbUuid(role_definition.id),
+            name: role_definition.name.clone(),
+            role_type: role_definition.role_type.to_string(),
+            created_at: role_definition.created_at,
+            updated_at: role_definition.updated_at,
+            db_tenant_id: role_definition.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
+impl From<&RoleDefinition> for DbRoleDefinitionMessage {
+    fn from(role_definition: &RoleDefinition) -> Self {
+        DbRoleDefinitionMessage {
+            name: role_definition.name.clone(),
+            role_type: role_definition.role_type.to_string(),
+            updated_at: Some(Utc::now().naive_utc())
+        }
+    }
+}
+
+impl From<&DbRoleDefinitionWithPermissions> for RoleDefinition {
+    fn from((value, permissions): &DbRoleDefinitionWithPermissions) -> Self {
+        RoleDefinition {
+            id: value.id.0,
+            name: value.name.clone(),
+            role_type: RoleType::from_str(&value.role_type).unwrap(),
+            permissions: permissions.clone(),
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+
+
+impl DbRoleDefinition {
+    pub fn find_by_tenant(pool: &Pool, tenant_id: Uuid) -> Result<Vec<DbRoleDefinitionWithPermissions>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let role_definitions = role_definitions::table
+            .filter(role_definitions::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .order(role_definitions::name)
+            .load::<DbRoleDefinition>(&mut connection)?;
+        with_permissions(&mut connection, role_definitions)
+    }
+
+    pub fn find(pool: &Pool, tenant_id: Uuid, id: Uuid) -> Result<DbRoleDefinitionWithPermissions, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let role_definition = role_definitions::table
+            .filter(role_definitions::id.eq(DbUuid(id)))
+            .filter(role_definitions::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .first::<DbRoleDefinition>(&mut connection)?;
+        single(with_permissions(&mut connection, vec![role_definition])?)
+    }
+
+    pub fn find_by_name(pool: &Pool, tenant_id: Uuid, name: String) -> Result<DbRoleDefinitionWithPermissions, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let role_definition = role_definitions::table
+            .filter(role_definitions::name.eq(name))
+            .filter(role_definitions::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .first::<DbRoleDefinition>(&mut connection)?;
+        single(with_permissions(&mut connection, vec![role_definition])?)
+    }
+
+    pub fn create(pool: &Pool, role_definition: DbRoleDefinition, permissions: &[String]) -> Result<DbRoleDefinitionWithPermissions, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        connection.transaction(|connection| DbRoleDefinition::insert(connection, role_definition, permissions))
+    }
+
+    pub fn insert(connection: &mut AnyConnection, role_definition: DbRoleDefinition, permissions: &[String]) -> Result<DbRoleDefinitionWithPermissions, TenetError> {
+        let db_role_definition: DbRoleDefinition = diesel::insert_into(role_definitions::table)
+            .values(role_definition)
+            .get_result(connection)?;
+        insert_permissions(connection, DbUuid(db_role_definition.id.0), permissions)?;
+        single(with_permissions(connection, vec![db_role_definition])?)
+    }
+
+    pub fn update(pool: &Pool, id: Uuid, role_definition: DbRoleDefinitionMessage, permissions: &[String]) -> Result<DbRoleDefinitionWithPermissions, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        connection.transaction(|connection| {
+            let updated_role_definition: DbRoleDefinition = diesel::update(role_definitions::table)
+                .filter(role_definitions::id.eq(DbUuid(id)))
+                .set(role_definition)
+                .get_result(connection)?;
+            diesel::delete(permissions::table.filter(permissions::role_definition_id.eq(DbUuid(id))))
+                .execute(connection)?;
+            insert_permissions(connection, DbUuid(id), permissions)?;
+            single(with_permissions(connection, vec![updated_role_definition])?)
+        })
+    }
+
+    pub fn delete(pool: &Pool, id: Uuid) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let result = diesel::delete(
+            role_definitions::table.filter(role_definitions::id.eq(DbUuid(id)))
+            )
+            .execute(&mut connection)?;
+        Ok(result)
+    }
+}
+
+fn insert_permissions(connection: &mut AnyConnection, role_definition_id: DbUuid, names: &[String]) -> Result<(), TenetError> {
+    let mut names = names.to_vec();
+    names.sort();
+    names.dedup();
+
+    let db_permissions: Vec<DbPermission> = names.into_iter()
+        .map(|name| DbPermission {
+            id: DbUuid(Uuid::new_v4()),
+            role_definition_id,
+            name,
+            created_at: Utc::now().naive_utc()
+        })
+        .collect();
+    // AnyConnection does not support batch inserts
+    for db_permission in db_permissions {
+        diesel::insert_into(permissions::table)
+            .values(db_permission)
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+fn with_permissions(connection: &mut AnyConnection, role_definitions: Vec<DbRoleDefinition>) -> Result<Vec<DbRoleDefinitionWithPermissions>, TenetError> {
+    let ids: Vec<&DbUuid> = role_definitions.iter().map(|d| &d.id).collect();
+    let db_permissions: Vec<DbPermission> = permissions::table
+        .filter(permissions::role_definition_id.eq_any(ids))
+        .order(permissions::name)
+        .load(connection)?;
+
+    Ok(role_definitions.into_iter()
+        .map(|d| {
+            let names = db_permissions.iter()
+                .filter(|p| p.role_definition_id == d.id)
+                .map(|p| p.name.clone())
+                .collect();
+            (d, names)
+        })
+        .collect())
+}
+
+fn single(mut role_definitions: Vec<DbRoleDefinitionWithPermissions>) -> Result<DbRoleDefinitionWithPermissions, TenetError> {
+    role_definitions.pop().ok_or(TenetError::DatabaseError(diesel::result::Error::NotFound))
+}
@@ -0,0 +1,131 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use crate::{Session, TenetError};
+use crate::schema::sessions;
+
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, AsChangeset)]
+#[diesel(table_name = sessions)]
+#[diesel(treat_none_as_null = true)]
+pub struct DbSessionMessage {
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>
+}
+
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(table_name = sessions)]
+pub struct DbSession {
+    pub id: DbUuid,
+    pub user_id: DbUuid,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
+}
+
+
+impl From<&Session> for DbSession {
+    fn from(session: &Session) -> Self {
+        DbSession {
+            id: DbUuid(session.id),
+            user_id: DbUuid(session.user_id),
+            token_hash: session.token_hash.clone(),
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            last_used_at: session.last_used_at,
+            revoked_at: session.revoked_at,
+            db_tenant_id: session.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
+impl From<&Session> for DbSessionMessage {
+    fn from(session: &Session) -> Self {
+        DbSessionMessage {
+            expires_at: session.expires_at,
+            last_used_at: session.last_used_at,
+            revoked_at: session.revoked_at
+        }
+    }
+}
+
+impl From<&DbSession> for Session {
+    fn from(value: &DbSession) -> Self {
+        Session {
+            id: value.id.0,
+            user_id: value.user_id.0,
+            token_hash: value.token_hash.clone(),
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            last_used_at: value.last_used_at,
+            revoked_at: value.revoked_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+
+
+impl DbSession {
+    pub fn find_by_token_hash(pool: &Pool, token_hash: String) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let session = sessions::table
+            .filter(sessions::token_hash.eq(token_hash))
+            .first(&mut connection)?;
+        Ok(session)
+    }
+
+    pub fn find(pool: &Pool, tenant_id: Uuid, id: Uuid) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let session = sessions::table
+            .filter(sessions::id.eq(DbUuid(id)))
+            .filter(sessions::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .first(&mut connection)?;
+        Ok(session)
+    }
+
+    pub fn find_by_user(pool: &Pool, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Self>, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let sessions = sessions::table
+            .filter(sessions::db_tenant_id.eq(Some(DbUuid(tenant_id))))
+            .filter(sessions::user_id.eq(DbUuid(user_id)))
+            .load(&mut connection)?;
+        Ok(sessions)
+    }
+
+    pub fn create(pool: &Pool, session: DbSession) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let db_session = diesel::insert_into(sessions::table)
+            .values(session)
+            .get_result(&mut connection)?;
+        Ok(db_session)
+    }
+
+    pub fn update(pool: &Pool, id: Uuid, session: DbSessionMessage) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let updated_session = diesel::update(sessions::table)
+            .filter(sessions::id.eq(DbUuid(id)))
+            .set(session)
+            .get_result(&mut connection)?;
+        Ok(updated_session)
+    }
+}
@@ -9,9 +9,9 @@ use diesel::{
 use diesel::prelude::*;
 
 use super::database;
-use super::database::Pool;
+use super::database::{DbUuid, Pool};
 use super::dbtenant::DbTenant;
-use crate::TenetError;
+use crate::{Storage, TenetError};
 use crate::schema::storages;
 
 
@@ -24,7 +24,7 @@ pub struct DbStorageMessage {
     pub connection_string: Option<String>,
     pub schema: Option<String>,
     pub table_prefix: Option<String>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub db_tenant_id: Option<DbUuid>
 }
 
 
@@ -32,7 +32,7 @@ pub struct DbStorageMessage {
 #[diesel(belongs_to(DbTenant))]
 #[diesel(table_name = storages)]
 pub struct DbStorage {
-    pub id: uuid::Uuid,
+    pub id: DbUuid,
     pub storage_type: String,
     pub path: Option<String>,
     pub connection_string: Option<String>,
@@ -40,13 +40,13 @@ pub struct DbStorage {
     pub table_prefix: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub db_tenant_id: Option<DbUuid>
 }
 
 impl From<DbStorageMessage> for DbStorage {
     fn from(storage: DbStorageMessage) -> Self {
         DbStorage { 
-            id: uuid::Uuid::new_v4(), 
+            id: DbUuid(Uuid::new_v4()), 
             storage_type: storage.storage_type, 
             path: storage.path, 
             connection_string: storage.connection_string, 
@@ -59,6 +59,19 @@ impl From<DbStorageMessage> for DbStorage {
     }
 }
 
+impl From<&Storage> for DbStorageMessage {
+    fn from(storage: &Storage) -> Self {
+        DbStorageMessage {
+            storage_type: storage.storage_type.to_string(),
+            path: storage.path.clone(),
+            connection_string: storage.connection_string.clone(),
+            schema: storage.schema.clone(),
+            table_prefix: storage.table_prefix.clone(),
+            db_tenant_id: storage.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
 
 impl DbStorage {
     pub fn find_all(pool: &Pool) -> Result<Vec<Self>, TenetError> {
@@ -69,15 +82,15 @@ impl DbStorage {
 
     pub fn find_by_tenant(pool: &Pool, id: Uuid) -> Result<Vec<Self>, TenetError> {
         let mut connection = database::connection(pool)?;
-        let storages = storages::table.filter(storages::db_tenant_id.eq(id)).load(&mut connection)?;
+        let storages = storages::table.filter(storages::db_tenant_id.eq(Some(DbUuid(id)))).load(&mut connection)?;
         Ok(storages)
     }
 
     pub fn find(pool: &Pool, tenant_id: uuid::Uuid, id: Uuid) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
         let storage = storages::table
-            .filter(storages::id.eq(id))
-            .filter(storages::db_tenant_id.eq(tenant_id))
+            .filter(storages::id.eq(DbUuid(id)))
+            .filter(storages::db_tenant_id.eq(Some(DbUuid(tenant_id))))
             .first(&mut connection)?;
         Ok(storage)
     }
@@ -97,7 +110,7 @@ impl DbStorage {
         let mut connection = database::connection(pool)?;
 
         let updated_storage = diesel::update(storages::table)
-            .filter(storages::id.eq(id))
+            .filter(storages::id.eq(DbUuid(id)))
             .set(storage)
             .get_result(&mut connection)?;
         Ok(updated_storage)
@@ -107,7 +120,7 @@ impl DbStorage {
         let mut connection = database::connection(pool)?;
 
         let result = diesel::delete(
-            storages::table.filter(storages::id.eq(id))
+            storages::table.filter(storages::id.eq(DbUuid(id)))
             )
             .execute(&mut connection)?;
         Ok(result)
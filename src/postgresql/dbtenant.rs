@@ -1,5 +1,5 @@
 
-use chrono::{NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 use diesel::{
@@ -9,10 +9,11 @@ use diesel::{
 };
 use diesel::prelude::*;
 
-use crate::TenetError;
+use crate::{RoleDefinition, Tenant, TenetError};
 use crate::schema::tenants;
 use super::database;
-use super::database::Pool;
+use super::database::{DbUuid, Pool};
+use super::dbroledefinition::DbRoleDefinition;
 
 
 #[derive(Serialize, Deserialize, AsChangeset)]
@@ -25,20 +26,20 @@ pub struct DbTenantMessage {
 #[derive(Debug, Serialize, Deserialize, Queryable, Insertable)]
 #[diesel(table_name = tenants)]
 pub struct DbTenant {
-    pub id: uuid::Uuid,
+    pub id: DbUuid,
     pub title: String,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>
 }
 
 
-impl From<DbTenantMessage> for DbTenant {
-    fn from(tenant: DbTenantMessage) -> Self {
+impl From<&Tenant> for DbTenant {
+    fn from(tenant: &Tenant) -> Self {
         DbTenant {
-            id: Uuid::new_v4(),
-            title: tenant.title,
-            created_at: Utc::now().naive_utc(),
-            updated_at: None
+            id: DbUuid(tenant.id),
+            title: tenant.title.clone(),
+            created_at: tenant.created_at,
+            updated_at: tenant.updated_at
         }
     }
 }
@@ -53,7 +54,7 @@ impl DbTenant {
 
     pub fn find(pool: &Pool, id: Uuid) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
-        let tenant = tenants::table.filter(tenants::id.eq(id)).first(&mut connection)?;
+        let tenant = tenants::table.filter(tenants::id.eq(DbUuid(id))).first(&mut connection)?;
         Ok(tenant)
     }
 
@@ -63,22 +64,25 @@ impl DbTenant {
         Ok(tenant)
     }
 
-    pub fn create(pool: &Pool, tenant: DbTenantMessage) -> Result<Self, TenetError> {
+    pub fn create(pool: &Pool, tenant: DbTenant, role_definitions: &[RoleDefinition]) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
 
-        let new_tenant = DbTenant::from(tenant);
-
-        let db_tenant = diesel::insert_into(tenants::table)
-            .values(new_tenant)
-            .get_result(&mut connection)?;
-        Ok(db_tenant)
+        connection.transaction(|connection| {
+            let db_tenant = diesel::insert_into(tenants::table)
+                .values(tenant)
+                .get_result(connection)?;
+            for role_definition in role_definitions {
+                DbRoleDefinition::insert(connection, DbRoleDefinition::from(role_definition), &role_definition.permissions)?;
+            }
+            Ok(db_tenant)
+        })
     }
 
     pub fn update(pool: &Pool, id: Uuid, tenant: DbTenantMessage) -> Result<Self, TenetError> {
         let mut conn = database::connection(pool)?;
 
         let db_tenant = diesel::update(tenants::table)
-            .filter(tenants::id.eq(id))
+            .filter(tenants::id.eq(DbUuid(id)))
             .set(tenant)
             .get_result(&mut conn)?;
         Ok(db_tenant)
@@ -88,7 +92,7 @@ impl DbTenant {
         let mut connection = database::connection(pool)?;
 
         let res = diesel::delete(
-                tenants::table.filter(tenants::id.eq(id))
+                tenants::table.filter(tenants::id.eq(DbUuid(id)))
             )
             .execute(&mut connection)?;
         Ok(res)
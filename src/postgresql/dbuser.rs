@@ -7,14 +7,13 @@ use diesel::{
     Insertable,
 };
 use diesel::prelude::*;
-use argon2::Config;
-use rand::Rng;
 
 use super::database;
-use super::database::Pool;
+use super::database::{DbUuid, Pool};
+use super::dbmembership::DbMembership;
 use super::dbtenant::DbTenant;
-use crate::TenetError;
-use crate::schema::users;
+use crate::{TenetError, User};
+use crate::schema::{memberships, users};
 
 
 
@@ -27,7 +26,8 @@ pub struct DbUserMessage {
     pub password: String,
     pub encryption_mode: String,
     pub full_name: String,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub updated_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
 }
 
 
@@ -36,7 +36,7 @@ pub struct DbUserMessage {
 #[diesel(belongs_to(DbTenant))]
 #[diesel(table_name = users)]
 pub struct DbUser {
-    pub id: uuid::Uuid,
+    pub id: DbUuid,
     pub email: String,
     pub email_verified: bool,
     #[serde(skip_serializing)]
@@ -45,14 +45,14 @@ pub struct DbUser {
     pub full_name: String,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
-    pub db_tenant_id: Option<uuid::Uuid>
+    pub db_tenant_id: Option<DbUuid>
 }
 
 
 impl From<DbUserMessage> for DbUser {
     fn from(user: DbUserMessage) -> Self {
         DbUser {
-            id: Uuid::new_v4(),
+            id: DbUuid(Uuid::new_v4()),
             email: user.email,
             email_verified: user.email_verified,
             password: user.password,
@@ -73,24 +73,45 @@ impl From<DbUser> for DbUserMessage {
             password: user.password,
             encryption_mode: user.encryption_mode,
             full_name: user.full_name,
+            updated_at: user.updated_at,
             db_tenant_id: user.db_tenant_id
         }
     }
 }
 
+impl From<&User> for DbUserMessage {
+    fn from(user: &User) -> Self {
+        DbUserMessage {
+            email: user.email.clone(),
+            email_verified: user.email_verified,
+            password: user.password.clone(),
+            encryption_mode: user.encryption_mode.to_string(),
+            full_name: user.full_name.clone(),
+            updated_at: user.updated_at,
+            db_tenant_id: user.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
 
 impl DbUser {
     pub fn find_by_tenant(pool: &Pool, tenant_id: Uuid) -> Result<Vec<Self>, TenetError> {
         let mut connection = database::connection(pool)?;
-        let users = users::table.filter(users::db_tenant_id.eq(tenant_id)).load(&mut connection)?;
+        let users = users::table
+            .inner_join(memberships::table)
+            .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
+            .select(users::all_columns)
+            .load(&mut connection)?;
         Ok(users)
     }
 
     pub fn find(pool: &Pool, tenant_id: uuid::Uuid, user_id: Uuid) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
         let user = users::table
-            .filter(users::id.eq(user_id))
-            .filter(users::db_tenant_id.eq(tenant_id))
+            .inner_join(memberships::table)
+            .filter(users::id.eq(DbUuid(user_id)))
+            .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
+            .select(users::all_columns)
             .first(&mut connection)?;
         Ok(user)
     }
@@ -106,8 +127,10 @@ impl DbUser {
     pub fn find_by_tenant_and_email(pool: &Pool, tenant_id: uuid::Uuid, email: String) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
         let user = users::table
-            .filter(users::db_tenant_id.eq(tenant_id))
+            .inner_join(memberships::table)
+            .filter(memberships::db_tenant_id.eq(DbUuid(tenant_id)))
             .filter(users::email.eq(email))
+            .select(users::all_columns)
             .first(&mut connection)?;
         Ok(user)
     }
@@ -115,20 +138,24 @@ impl DbUser {
     pub fn create(pool: &Pool, user: DbUserMessage) -> Result<Self, TenetError> {
         let mut connection = database::connection(pool)?;
 
-        let mut new_user = DbUser::from(user);
-        new_user.hash_password()?;
-
-        let db_user = diesel::insert_into(users::table)
-            .values(new_user)
-            .get_result(&mut connection)?;
-        Ok(db_user)
+        let new_user = DbUser::from(user);
+
+        connection.transaction(|connection| {
+            let db_user: DbUser = diesel::insert_into(users::table)
+                .values(new_user)
+                .get_result(connection)?;
+            if let Some(tenant_id) = db_user.db_tenant_id {
+                DbMembership::insert(connection, db_user.id.0, tenant_id.0)?;
+            }
+            Ok(db_user)
+        })
     }
 
     pub fn update(pool: &Pool, id: Uuid, user: DbUserMessage) -> Result<Self, TenetError> {
         let mut conn = database::connection(pool)?;
 
         let user = diesel::update(users::table)
-            .filter(users::id.eq(id))
+            .filter(users::id.eq(DbUuid(id)))
             .set(user)
             .get_result(&mut conn)?;
         Ok(user)
@@ -138,24 +165,9 @@ impl DbUser {
         let mut conn = database::connection(pool)?;
 
         let res = diesel::delete(
-            users::table.filter(users::id.eq(id))
+            users::table.filter(users::id.eq(DbUuid(id)))
             )
             .execute(&mut conn)?;
         Ok(res)
     }
-
-    fn hash_password(&mut self) -> Result<(), TenetError> {
-        let salt: [u8; 32] = rand::rng().random();
-        // Alternative would be the low_memory variant. Can be time consuming.
-        // See https://github.com/sru-systems/rust-argon2/issues/52
-        let config = Config::original(); 
-
-        self.password = argon2::hash_encoded(self.password.as_bytes(), &salt, &config)?;
-
-        Ok(())
-    }
-
-    pub fn verify_password(&self, password: &str) -> Result<bool, TenetError> {
-        Ok(argon2::verify_encoded(&self.password, password.as_bytes())?)
-    }
 }
@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+use serde::{Serialize, Deserialize};
+use diesel::{
+    self,
+    Queryable,
+    Insertable,
+};
+use diesel::prelude::*;
+
+use super::database;
+use super::database::{DbUuid, Pool};
+use super::dbtenant::DbTenant;
+use crate::{TenetError, UserToken, UserTokenPurpose};
+use crate::schema::user_tokens;
+
+
+#[derive(Debug, Serialize, Deserialize, Identifiable, Associations, PartialEq, Queryable, Insertable)]
+#[diesel(belongs_to(DbTenant))]
+#[diesel(table_name = user_tokens)]
+pub struct DbUserToken {
+    pub id: DbUuid,
+    pub user_id: DbUuid,
+    pub purpose: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<DbUuid>
+}
+
+
+impl From<&UserToken> for DbUserToken {
+    fn from(user_token: &UserToken) -> Self {
+        DbUserToken {
+            id: DbUuid(user_token.id),
+            user_id: DbUuid(user_token.user_id),
+            purpose: user_token.purpose.to_string(),
+            token_hash: user_token.token_hash.clone(),
+            created_at: user_token.created_at,
+            expires_at: user_token.expires_at,
+            used_at: user_token.used_at,
+            db_tenant_id: user_token.db_tenant_id.map(DbUuid)
+        }
+    }
+}
+
+impl From<&DbUserToken> for UserToken {
+    fn from(value: &DbUserToken) -> Self {
+        UserToken {
+            id: value.id.0,
+            user_id: value.user_id.0,
+            purpose: UserTokenPurpose::from_str(&value.purpose).unwrap(),
+            token_hash: value.token_hash.clone(),
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            used_at: value.used_at,
+            db_tenant_id: value.db_tenant_id.map(Uuid::from)
+        }
+    }
+}
+
+
+
+impl DbUserToken {
+    pub fn find_by_token_hash(pool: &Pool, token_hash: String) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+        let user_token = user_tokens::table
+            .filter(user_tokens::token_hash.eq(token_hash))
+            .first(&mut connection)?;
+        Ok(user_token)
+    }
+
+    pub fn create(pool: &Pool, user_token: DbUserToken) -> Result<Self, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let db_user_token = diesel::insert_into(user_tokens::table)
+            .values(user_token)
+            .get_result(&mut connection)?;
+        Ok(db_user_token)
+    }
+
+    /// Sets `used_at` unless it is already set, returns the number of changed rows.
+    pub fn mark_used(pool: &Pool, id: Uuid, used_at: NaiveDateTime) -> Result<usize, TenetError> {
+        let mut connection = database::connection(pool)?;
+
+        let updated = diesel::update(user_tokens::table)
+            .filter(user_tokens::id.eq(DbUuid(id)))
+            .filter(user_tokens::used_at.is_null())
+            .set(user_tokens::used_at.eq(Some(used_at)))
+            .execute(&mut connection)?;
+        Ok(updated)
+    }
+}
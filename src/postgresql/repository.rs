@@ -0,0 +1,453 @@
+use std::marker::PhantomData;
+
+use chrono::NaiveDateTime;
+use diesel::pg::Pg;
+use uuid::Uuid;
+
+use super::database::{self, DbUuid, Pool};
+use super::dbapplication::{DbApplication, DbApplicationMessage};
+use super::dbgroup::{DbGroup, DbGroupMessage};
+use super::dbinvitation::DbInvitation;
+use super::dbmembership::DbMembership;
+use super::dbrole::{DbRole, DbRoleMessage};
+use super::dbroledefinition::{DbRoleDefinition, DbRoleDefinitionMessage};
+use super::dbrefreshtoken::DbRefreshToken;
+use super::dbsession::{DbSession, DbSessionMessage};
+use super::dbstorage::{DbStorage, DbStorageMessage};
+use super::dbtenant::{DbTenant, DbTenantMessage};
+use super::dbuser::{DbUser, DbUserMessage};
+use super::dbusertoken::DbUserToken;
+use super::dbpasswordhistory::DbPasswordHistoryEntry;
+use super::dbpasswordpolicy::DbPasswordPolicy;
+use crate::repository::{ApplicationRepository, GroupRepository, InvitationRepository, Invitee, MembershipRepository, PasswordHistoryRepository, PasswordPolicyRepository, RefreshTokenRepository, RoleDefinitionRepository, RoleRepository, SessionRepository, StorageRepository, TenantRepository, UserRepository, UserTokenRepository};
+use crate::{Application, Group, Invitation, PasswordHistoryEntry, PasswordPolicy, RefreshToken, Role, RoleDefinition, Session, Storage, Tenant, TenetError, User, UserToken};
+
+
+/// Repository backed by a SQL database, accessed through diesel.
+///
+/// The models only use SQL that PostgreSQL and SQLite share, so one
+/// implementation serves both. `B` is the diesel backend the pool connects
+/// to; it only selects the constructor.
+#[derive(Debug)]
+pub struct DieselRepository<B> {
+    pool: Pool,
+    backend: PhantomData<B>
+}
+
+impl<B> DieselRepository<B> {
+    pub(crate) fn with_pool(pool: Pool) -> Self {
+        DieselRepository {
+            pool,
+            backend: PhantomData
+        }
+    }
+}
+
+impl<B> Clone for DieselRepository<B> {
+    fn clone(&self) -> Self {
+        DieselRepository::with_pool(self.pool.clone())
+    }
+}
+
+/// The PostgreSQL backend used by [`crate::Tenet::new`].
+pub type PostgresRepository = DieselRepository<Pg>;
+
+impl PostgresRepository {
+    /// Connects to the database and runs any pending migrations.
+    pub fn new(connection_string: &str) -> Self {
+        DieselRepository::with_pool(database::build_pool(connection_string))
+    }
+}
+
+
+impl<B> TenantRepository for DieselRepository<B> {
+    fn find_all_tenants(&self) -> Result<Vec<Tenant>, TenetError> {
+        let tenants = DbTenant::find_all(&self.pool)?;
+        Ok(tenants.iter().map(Tenant::from).collect())
+    }
+
+    fn find_tenant(&self, tenant_id: Uuid) -> Result<Tenant, TenetError> {
+        let tenant = DbTenant::find(&self.pool, tenant_id)?;
+        Ok(Tenant::from(&tenant))
+    }
+
+    fn create_tenant(&self, tenant: &Tenant, role_definitions: &[RoleDefinition]) -> Result<Tenant, TenetError> {
+        let tenant = DbTenant::create(&self.pool, DbTenant::from(tenant), role_definitions)?;
+        Ok(Tenant::from(&tenant))
+    }
+
+    fn update_tenant(&self, tenant_id: Uuid, title: String) -> Result<Tenant, TenetError> {
+        let tenant = DbTenant::update(&self.pool, tenant_id, DbTenantMessage { title })?;
+        Ok(Tenant::from(&tenant))
+    }
+
+    fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        DbTenant::delete(&self.pool, tenant_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> UserRepository for DieselRepository<B> {
+    fn find_users_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<User>, TenetError> {
+        let users = DbUser::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(users.iter().map(User::from).collect())
+    }
+
+    fn find_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<User, TenetError> {
+        let user = DbUser::find(&self.pool, tenant_id, user_id)?;
+        Ok(User::from(&user))
+    }
+
+    fn find_user_by_email(&self, email: String) -> Result<User, TenetError> {
+        let user = DbUser::find_by_email(&self.pool, email)?;
+        Ok(User::from(&user))
+    }
+
+    fn find_user_by_tenant_and_email(&self, tenant_id: Uuid, email: String) -> Result<User, TenetError> {
+        let user = DbUser::find_by_tenant_and_email(&self.pool, tenant_id, email)?;
+        Ok(User::from(&user))
+    }
+
+    fn create_user(&self, user: &User) -> Result<User, TenetError> {
+        let user = DbUser::create(&self.pool, DbUserMessage::from(user))?;
+        Ok(User::from(&user))
+    }
+
+    fn update_user(&self, user: &User) -> Result<User, TenetError> {
+        let updated_user = DbUser::update(&self.pool, user.id, DbUserMessage::from(user))?;
+        Ok(User::from(&updated_user))
+    }
+
+    fn delete_user(&self, user_id: Uuid) -> Result<(), TenetError> {
+        DbUser::delete(&self.pool, user_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> MembershipRepository for DieselRepository<B> {
+    fn find_tenants_by_user(&self, user_id: Uuid) -> Result<Vec<Tenant>, TenetError> {
+        let tenants = DbMembership::find_tenants_by_user(&self.pool, user_id)?;
+        Ok(tenants.iter().map(Tenant::from).collect())
+    }
+
+    fn create_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError> {
+        DbMembership::create(&self.pool, user_id, tenant_id)?;
+        Ok(())
+    }
+
+    fn delete_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError> {
+        DbMembership::delete(&self.pool, user_id, tenant_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> ApplicationRepository for DieselRepository<B> {
+    fn find_applications_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Application>, TenetError> {
+        let applications = DbApplication::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(applications.iter().map(Application::from).collect())
+    }
+
+    fn find_application(&self, tenant_id: Uuid, application_id: Uuid) -> Result<Application, TenetError> {
+        let application = DbApplication::find(&self.pool, tenant_id, application_id)?;
+        Ok(Application::from(&application))
+    }
+
+    fn create_application(&self, application: &Application) -> Result<Application, TenetError> {
+        let application = DbApplication::create(&self.pool, DbApplicationMessage::from(application))?;
+        Ok(Application::from(&application))
+    }
+
+    fn delete_application(&self, application_id: Uuid) -> Result<(), TenetError> {
+        DbApplication::delete(&self.pool, application_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> StorageRepository for DieselRepository<B> {
+    fn find_storages_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Storage>, TenetError> {
+        let storages = DbStorage::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(storages.iter().map(Storage::from).collect())
+    }
+
+    fn find_storage(&self, tenant_id: Uuid, storage_id: Uuid) -> Result<Storage, TenetError> {
+        let storage = DbStorage::find(&self.pool, tenant_id, storage_id)?;
+        Ok(Storage::from(&storage))
+    }
+
+    fn create_storage(&self, storage: &Storage) -> Result<Storage, TenetError> {
+        let storage = DbStorage::create(&self.pool, DbStorageMessage::from(storage))?;
+        Ok(Storage::from(&storage))
+    }
+
+    fn delete_storage(&self, storage_id: Uuid) -> Result<(), TenetError> {
+        DbStorage::delete(&self.pool, storage_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> RoleRepository for DieselRepository<B> {
+    fn find_roles_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let roles = DbRole::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    fn find_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let roles = DbRole::find_by_user(&self.pool, tenant_id, user_id)?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    fn find_roles_by_group(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let roles = DbRole::find_by_group(&self.pool, tenant_id, group_id)?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    fn find_inherited_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError> {
+        let roles = DbRole::find_inherited_by_user(&self.pool, tenant_id, user_id)?;
+        Ok(roles.iter().map(Role::from).collect())
+    }
+
+    fn find_role(&self, tenant_id: Uuid, role_id: Uuid) -> Result<Role, TenetError> {
+        let role = DbRole::find(&self.pool, tenant_id, role_id)?;
+        Ok(Role::from(&role))
+    }
+
+    fn create_role(&self, role: &Role) -> Result<Role, TenetError> {
+        let role = DbRole::create(&self.pool, DbRoleMessage::from(role))?;
+        Ok(Role::from(&role))
+    }
+
+    fn delete_role(&self, role_id: Uuid) -> Result<(), TenetError> {
+        DbRole::delete(&self.pool, role_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> RoleDefinitionRepository for DieselRepository<B> {
+    fn find_role_definitions_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<RoleDefinition>, TenetError> {
+        let role_definitions = DbRoleDefinition::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(role_definitions.iter().map(RoleDefinition::from).collect())
+    }
+
+    fn find_role_definition(&self, tenant_id: Uuid, role_definition_id: Uuid) -> Result<RoleDefinition, TenetError> {
+        let role_definition = DbRoleDefinition::find(&self.pool, tenant_id, role_definition_id)?;
+        Ok(RoleDefinition::from(&role_definition))
+    }
+
+    fn find_role_definition_by_name(&self, tenant_id: Uuid, name: String) -> Result<RoleDefinition, TenetError> {
+        let role_definition = DbRoleDefinition::find_by_name(&self.pool, tenant_id, name)?;
+        Ok(RoleDefinition::from(&role_definition))
+    }
+
+    fn create_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError> {
+        let created = DbRoleDefinition::create(&self.pool, DbRoleDefinition::from(role_definition), &role_definition.permissions)?;
+        Ok(RoleDefinition::from(&created))
+    }
+
+    fn update_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError> {
+        let updated = DbRoleDefinition::update(&self.pool, role_definition.id, DbRoleDefinitionMessage::from(role_definition), &role_definition.permissions)?;
+        Ok(RoleDefinition::from(&updated))
+    }
+
+    fn delete_role_definition(&self, role_definition_id: Uuid) -> Result<(), TenetError> {
+        DbRoleDefinition::delete(&self.pool, role_definition_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> GroupRepository for DieselRepository<B> {
+    fn find_groups_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Group>, TenetError> {
+        let groups = DbGroup::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(groups.iter().map(Group::from).collect())
+    }
+
+    fn find_group(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Group, TenetError> {
+        let group = DbGroup::find(&self.pool, tenant_id, group_id)?;
+        Ok(Group::from(&group))
+    }
+
+    fn find_groups_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Group>, TenetError> {
+        let groups = DbGroup::find_by_user(&self.pool, tenant_id, user_id)?;
+        Ok(groups.iter().map(Group::from).collect())
+    }
+
+    fn create_group(&self, group: &Group) -> Result<Group, TenetError> {
+        let group = DbGroup::create(&self.pool, DbGroup::from(group))?;
+        Ok(Group::from(&group))
+    }
+
+    fn update_group(&self, group: &Group) -> Result<Group, TenetError> {
+        let group = DbGroup::update(&self.pool, group.id, DbGroupMessage::from(group))?;
+        Ok(Group::from(&group))
+    }
+
+    fn delete_group(&self, group_id: Uuid) -> Result<(), TenetError> {
+        DbGroup::delete(&self.pool, group_id)?;
+        Ok(())
+    }
+
+    fn find_group_members(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<User>, TenetError> {
+        let users = DbGroup::find_members(&self.pool, tenant_id, group_id)?;
+        Ok(users.iter().map(User::from).collect())
+    }
+
+    fn add_group_member(&self, group_id: Uuid, user_id: Uuid) -> Result<(), TenetError> {
+        DbGroup::add_member(&self.pool, group_id, user_id)?;
+        Ok(())
+    }
+
+    fn remove_group_member(&self, group_id: Uuid, user_id: Uuid) -> Result<(), TenetError> {
+        DbGroup::remove_member(&self.pool, group_id, user_id)?;
+        Ok(())
+    }
+}
+
+
+impl<B> SessionRepository for DieselRepository<B> {
+    fn find_session_by_token_hash(&self, token_hash: String) -> Result<Session, TenetError> {
+        let session = DbSession::find_by_token_hash(&self.pool, token_hash)?;
+        Ok(Session::from(&session))
+    }
+
+    fn find_session(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Session, TenetError> {
+        let session = DbSession::find(&self.pool, tenant_id, session_id)?;
+        Ok(Session::from(&session))
+    }
+
+    fn find_sessions_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Session>, TenetError> {
+        let sessions = DbSession::find_by_user(&self.pool, tenant_id, user_id)?;
+        Ok(sessions.iter().map(Session::from).collect())
+    }
+
+    fn create_session(&self, session: &Session) -> Result<Session, TenetError> {
+        let session = DbSession::create(&self.pool, DbSession::from(session))?;
+        Ok(Session::from(&session))
+    }
+
+    fn update_session(&self, session: &Session) -> Result<Session, TenetError> {
+        let session = DbSession::update(&self.pool, session.id, DbSessionMessage::from(session))?;
+        Ok(Session::from(&session))
+    }
+}
+
+
+impl<B> RefreshTokenRepository for DieselRepository<B> {
+    fn find_refresh_token_by_token_hash(&self, token_hash: String) -> Result<RefreshToken, TenetError> {
+        let refresh_token = DbRefreshToken::find_by_token_hash(&self.pool, token_hash)?;
+        Ok(RefreshToken::from(&refresh_token))
+    }
+
+    fn find_refresh_tokens_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<RefreshToken>, TenetError> {
+        let refresh_tokens = DbRefreshToken::find_by_user(&self.pool, tenant_id, user_id)?;
+        Ok(refresh_tokens.iter().map(RefreshToken::from).collect())
+    }
+
+    fn create_refresh_token(&self, refresh_token: &RefreshToken) -> Result<RefreshToken, TenetError> {
+        let refresh_token = DbRefreshToken::create(&self.pool, DbRefreshToken::from(refresh_token))?;
+        Ok(RefreshToken::from(&refresh_token))
+    }
+
+    fn use_refresh_token(&self, refresh_token_id: Uuid, used_at: NaiveDateTime) -> Result<bool, TenetError> {
+        Ok(DbRefreshToken::mark_used(&self.pool, refresh_token_id, used_at)? == 1)
+    }
+
+    fn revoke_refresh_token_family(&self, family_id: Uuid, revoked_at: NaiveDateTime) -> Result<(), TenetError> {
+        DbRefreshToken::revoke_family(&self.pool, family_id, revoked_at)?;
+        Ok(())
+    }
+}
+
+impl<B> InvitationRepository for DieselRepository<B> {
+    fn find_invitation_by_token_hash(&self, token_hash: String) -> Result<Invitation, TenetError> {
+        let invitation = DbInvitation::find_by_token_hash(&self.pool, token_hash)?;
+        Ok(Invitation::from(&invitation))
+    }
+
+    fn find_invitation(&self, tenant_id: Uuid, invitation_id: Uuid) -> Result<Invitation, TenetError> {
+        let invitation = DbInvitation::find(&self.pool, tenant_id, invitation_id)?;
+        Ok(Invitation::from(&invitation))
+    }
+
+    fn find_invitations_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Invitation>, TenetError> {
+        let invitations = DbInvitation::find_by_tenant(&self.pool, tenant_id)?;
+        Ok(invitations.iter().map(Invitation::from).collect())
+    }
+
+    fn create_invitation(&self, invitation: &Invitation) -> Result<Invitation, TenetError> {
+        let invitation = DbInvitation::create(&self.pool, DbInvitation::from(invitation), &invitation.roles)?;
+        Ok(Invitation::from(&invitation))
+    }
+
+    fn accept_invitation(&self, invitation: &Invitation, invitee: Invitee<'_>, accepted_at: NaiveDateTime) -> Result<Option<User>, TenetError> {
+        let tenant_id = invitation.db_tenant_id.ok_or(TenetError::NotFoundError)?;
+        let (new_user, user_id) = match invitee {
+            Invitee::New(user) => {
+                let new_user = DbUser::from(DbUserMessage::from(user));
+                let user_id = new_user.id.0;
+                (Some(new_user), user_id)
+            },
+            Invitee::Existing(user_id) => (None, user_id)
+        };
+        let membership = DbMembership { user_id: DbUuid(user_id), db_tenant_id: DbUuid(tenant_id), created_at: accepted_at };
+        let roles = invitation.roles.iter()
+            .map(|role| DbRole::from(DbRoleMessage::from(&role.to_role(user_id, tenant_id))))
+            .collect();
+
+        let user = DbInvitation::accept(&self.pool, invitation.id, accepted_at, new_user, membership, roles)?;
+        Ok(user.as_ref().map(User::from))
+    }
+
+    fn revoke_invitation(&self, invitation_id: Uuid, revoked_at: NaiveDateTime) -> Result<(), TenetError> {
+        DbInvitation::revoke(&self.pool, invitation_id, revoked_at)?;
+        Ok(())
+    }
+}
+
+impl<B> UserTokenRepository for DieselRepository<B> {
+    fn find_user_token_by_token_hash(&self, token_hash: String) -> Result<UserToken, TenetError> {
+        let user_token = DbUserToken::find_by_token_hash(&self.pool, token_hash)?;
+        Ok(UserToken::from(&user_token))
+    }
+
+    fn create_user_token(&self, user_token: &UserToken) -> Result<UserToken, TenetError> {
+        let user_token = DbUserToken::create(&self.pool, DbUserToken::from(user_token))?;
+        Ok(UserToken::from(&user_token))
+    }
+
+    fn use_user_token(&self, user_token_id: Uuid, used_at: NaiveDateTime) -> Result<bool, TenetError> {
+        Ok(DbUserToken::mark_used(&self.pool, user_token_id, used_at)? == 1)
+    }
+}
+
+impl<B> PasswordHistoryRepository for DieselRepository<B> {
+    fn find_password_history(&self, user_id: Uuid, limit: usize) -> Result<Vec<PasswordHistoryEntry>, TenetError> {
+        let entries = DbPasswordHistoryEntry::find_by_user(&self.pool, user_id, limit)?;
+        Ok(entries.iter().map(PasswordHistoryEntry::from).collect())
+    }
+
+    fn update_user_password(&self, user: &User, entry: &PasswordHistoryEntry, history_limit: usize) -> Result<User, TenetError> {
+        let updated_user = DbPasswordHistoryEntry::create_with_user(&self.pool, DbPasswordHistoryEntry::from(entry), DbUserMessage::from(user), history_limit)?;
+        Ok(User::from(&updated_user))
+    }
+}
+
+impl<B> PasswordPolicyRepository for DieselRepository<B> {
+    fn find_password_policy(&self, tenant_id: Uuid) -> Result<PasswordPolicy, TenetError> {
+        DbPasswordPolicy::find_by_tenant(&self.pool, tenant_id)?.policy()
+    }
+
+    fn save_password_policy(&self, tenant_id: Uuid, policy: &PasswordPolicy) -> Result<PasswordPolicy, TenetError> {
+        DbPasswordPolicy::save(&self.pool, DbPasswordPolicy::new(tenant_id, policy)?)?.policy()
+    }
+
+    fn delete_password_policy(&self, tenant_id: Uuid) -> Result<(), TenetError> {
+        DbPasswordPolicy::delete(&self.pool, tenant_id)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{NaiveDateTime, Utc};
+
+
+/// A refresh token, created by [`crate::Tenant::issue_refresh_token`].
+///
+/// Refresh tokens are single use: [`crate::Tenant::refresh`] marks the token
+/// as used and issues its successor in the same family. Presenting a used
+/// token again revokes the whole family. Only a SHA-256 hash of the token is
+/// stored.
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct RefreshToken {
+    pub id: uuid::Uuid,
+    /// Shared by all tokens descending from the same login
+    pub family_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<uuid::Uuid>
+}
+
+impl RefreshToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().naive_utc()
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used_at.is_some()
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+
+/// A freshly created refresh token together with the token itself.
+///
+/// The token is not stored anywhere, so this is the only chance to pass it on
+/// to the client.
+#[derive(Debug, Clone)]
+pub struct IssuedRefreshToken {
+    pub token: String,
+    pub refresh_token: RefreshToken
+}
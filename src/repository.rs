@@ -0,0 +1,389 @@
+//! Persistence abstraction used by [`crate::Tenet`] and [`crate::Tenant`].
+//!
+//! Every read and write performed by `Tenet` and `Tenant` goes through the
+//! traits in this module. The diesel/PostgreSQL backend ([`crate::PostgresRepository`])
+//! is one implementation; other backends, or mocks for downstream unit tests,
+//! can be plugged in with [`crate::Tenet::with_repository`].
+//!
+//! Implementations store values as they are given. In particular, passwords
+//! passed to [`UserRepository::create_user`] are already hashed.
+
+use std::fmt::Debug;
+
+use uuid::Uuid;
+
+use chrono::NaiveDateTime;
+
+use crate::{Application, Group, Invitation, PasswordHistoryEntry, PasswordPolicy, RefreshToken, Role, RoleDefinition, Session, Storage, Tenant, TenetError, User, UserToken};
+
+
+/// Persistence operations for tenants.
+///
+/// Returned `Tenant` values are not connected to a repository; `Tenet` takes
+/// care of connecting them before handing them out.
+pub trait TenantRepository {
+    /// Returns all tenants.
+    fn find_all_tenants(&self) -> Result<Vec<Tenant>, TenetError>;
+
+    /// Returns the tenant with the given id.
+    fn find_tenant(&self, tenant_id: Uuid) -> Result<Tenant, TenetError>;
+
+    /// Stores a new tenant as given, including its id, together with its
+    /// initial role definitions. Either all of them are stored or none.
+    fn create_tenant(&self, tenant: &Tenant, role_definitions: &[RoleDefinition]) -> Result<Tenant, TenetError>;
+
+    /// Changes the title of an existing tenant.
+    fn update_tenant(&self, tenant_id: Uuid, title: String) -> Result<Tenant, TenetError>;
+
+    /// Removes the tenant with the given id.
+    fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for users.
+///
+/// A user belongs to every tenant it has a membership in, see
+/// [`MembershipRepository`]. Its `db_tenant_id` is the tenant it was created in.
+pub trait UserRepository {
+    /// Returns all members of a tenant.
+    fn find_users_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<User>, TenetError>;
+
+    /// Returns the user with the given id, if it is a member of the tenant.
+    fn find_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<User, TenetError>;
+
+    /// Returns the user with the given email address, regardless of its tenant.
+    fn find_user_by_email(&self, email: String) -> Result<User, TenetError>;
+
+    /// Returns the user with the given email address, if it is a member of the tenant.
+    fn find_user_by_tenant_and_email(&self, tenant_id: Uuid, email: String) -> Result<User, TenetError>;
+
+    /// Stores a new user together with its membership in `db_tenant_id`. The
+    /// password must already be hashed.
+    fn create_user(&self, user: &User) -> Result<User, TenetError>;
+
+    /// Overwrites the stored user with the given values.
+    fn update_user(&self, user: &User) -> Result<User, TenetError>;
+
+    /// Removes the user with the given id.
+    fn delete_user(&self, user_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for memberships of users in tenants.
+///
+/// Memberships are removed together with their user or tenant.
+pub trait MembershipRepository {
+    /// Returns all tenants the user is a member of.
+    fn find_tenants_by_user(&self, user_id: Uuid) -> Result<Vec<Tenant>, TenetError>;
+
+    /// Makes the user a member of the tenant. Adding an existing membership is
+    /// a unique violation.
+    fn create_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError>;
+
+    /// Removes the membership together with the user's roles and group
+    /// memberships in the tenant and revokes their sessions and refresh
+    /// tokens there, all at once.
+    fn delete_membership(&self, user_id: Uuid, tenant_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for applications.
+pub trait ApplicationRepository {
+    /// Returns all applications of a tenant.
+    fn find_applications_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Application>, TenetError>;
+
+    /// Returns the application with the given id, if it belongs to the tenant.
+    fn find_application(&self, tenant_id: Uuid, application_id: Uuid) -> Result<Application, TenetError>;
+
+    /// Stores a new application.
+    fn create_application(&self, application: &Application) -> Result<Application, TenetError>;
+
+    /// Removes the application with the given id.
+    fn delete_application(&self, application_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for storages.
+pub trait StorageRepository {
+    /// Returns all storages of a tenant.
+    fn find_storages_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Storage>, TenetError>;
+
+    /// Returns the storage with the given id, if it belongs to the tenant.
+    fn find_storage(&self, tenant_id: Uuid, storage_id: Uuid) -> Result<Storage, TenetError>;
+
+    /// Stores a new storage.
+    fn create_storage(&self, storage: &Storage) -> Result<Storage, TenetError>;
+
+    /// Removes the storage with the given id.
+    fn delete_storage(&self, storage_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for roles.
+pub trait RoleRepository {
+    /// Returns all roles of a tenant.
+    fn find_roles_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Role>, TenetError>;
+
+    /// Returns all roles assigned directly to a user of the tenant.
+    fn find_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError>;
+
+    /// Returns all roles assigned to a group of the tenant.
+    fn find_roles_by_group(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<Role>, TenetError>;
+
+    /// Returns the roles of all groups of the tenant the user is a member of.
+    fn find_inherited_roles_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Role>, TenetError>;
+
+    /// Returns the role with the given id, if it belongs to the tenant.
+    fn find_role(&self, tenant_id: Uuid, role_id: Uuid) -> Result<Role, TenetError>;
+
+    /// Stores a new role. Its `role_definition_id` must reference a definition
+    /// of the tenant, whose role type the stored role is expected to carry.
+    fn create_role(&self, role: &Role) -> Result<Role, TenetError>;
+
+    /// Removes the role with the given id.
+    fn delete_role(&self, role_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for role definitions and their permissions.
+///
+/// Role definitions are removed together with their tenant, permissions
+/// together with their definition.
+pub trait RoleDefinitionRepository {
+    /// Returns all role definitions of a tenant.
+    fn find_role_definitions_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<RoleDefinition>, TenetError>;
+
+    /// Returns the role definition with the given id, if it belongs to the tenant.
+    fn find_role_definition(&self, tenant_id: Uuid, role_definition_id: Uuid) -> Result<RoleDefinition, TenetError>;
+
+    /// Returns the role definition with the given name, if it belongs to the tenant.
+    fn find_role_definition_by_name(&self, tenant_id: Uuid, name: String) -> Result<RoleDefinition, TenetError>;
+
+    /// Stores a new role definition with its permissions as given, including its id.
+    fn create_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError>;
+
+    /// Changes name, role type and permissions of an existing role definition.
+    fn update_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError>;
+
+    /// Removes the role definition with the given id.
+    fn delete_role_definition(&self, role_definition_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for groups and their members.
+///
+/// Groups are removed together with their tenant. Memberships and the roles
+/// assigned to a group are removed together with the group, memberships also
+/// together with the user.
+pub trait GroupRepository {
+    /// Returns all groups of a tenant.
+    fn find_groups_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Group>, TenetError>;
+
+    /// Returns the group with the given id, if it belongs to the tenant.
+    fn find_group(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Group, TenetError>;
+
+    /// Returns all groups of the tenant the user is a member of.
+    fn find_groups_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Group>, TenetError>;
+
+    /// Stores a new group as given, including its id.
+    fn create_group(&self, group: &Group) -> Result<Group, TenetError>;
+
+    /// Changes the name of an existing group.
+    fn update_group(&self, group: &Group) -> Result<Group, TenetError>;
+
+    /// Removes the group with the given id.
+    fn delete_group(&self, group_id: Uuid) -> Result<(), TenetError>;
+
+    /// Returns the members of a group of the tenant.
+    fn find_group_members(&self, tenant_id: Uuid, group_id: Uuid) -> Result<Vec<User>, TenetError>;
+
+    /// Adds a user to a group. Adding an existing member is a unique violation.
+    fn add_group_member(&self, group_id: Uuid, user_id: Uuid) -> Result<(), TenetError>;
+
+    /// Removes a user from a group.
+    fn remove_group_member(&self, group_id: Uuid, user_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for sessions.
+///
+/// Sessions are removed together with their user.
+pub trait SessionRepository {
+    /// Returns the session stored under the given token hash, regardless of its tenant.
+    fn find_session_by_token_hash(&self, token_hash: String) -> Result<Session, TenetError>;
+
+    /// Returns the session with the given id, if it belongs to the tenant.
+    fn find_session(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Session, TenetError>;
+
+    /// Returns all sessions of a user of the tenant.
+    fn find_sessions_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<Session>, TenetError>;
+
+    /// Stores a new session as given, including its id.
+    fn create_session(&self, session: &Session) -> Result<Session, TenetError>;
+
+    /// Changes the expiry, last use and revocation of an existing session.
+    fn update_session(&self, session: &Session) -> Result<Session, TenetError>;
+}
+
+
+/// Persistence operations for refresh tokens.
+///
+/// Refresh tokens are removed together with their user.
+pub trait RefreshTokenRepository {
+    /// Returns the refresh token stored under the given token hash, regardless of its tenant.
+    fn find_refresh_token_by_token_hash(&self, token_hash: String) -> Result<RefreshToken, TenetError>;
+
+    /// Returns all refresh tokens of a user of the tenant.
+    fn find_refresh_tokens_by_user(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<RefreshToken>, TenetError>;
+
+    /// Stores a new refresh token as given, including its id.
+    fn create_refresh_token(&self, refresh_token: &RefreshToken) -> Result<RefreshToken, TenetError>;
+
+    /// Marks an unused refresh token as used.
+    ///
+    /// Returns `false` if the token had already been used. Check and update must
+    /// happen atomically, so that a token cannot be rotated twice.
+    fn use_refresh_token(&self, refresh_token_id: Uuid, used_at: NaiveDateTime) -> Result<bool, TenetError>;
+
+    /// Revokes all not yet revoked refresh tokens of a family.
+    fn revoke_refresh_token_family(&self, family_id: Uuid, revoked_at: NaiveDateTime) -> Result<(), TenetError>;
+}
+
+
+/// Persistence operations for invitations.
+///
+/// Invitations and their roles are removed together with their tenant, and
+/// a role is dropped from pending invitations when its role definition or
+/// application is deleted.
+pub trait InvitationRepository {
+    /// Returns the invitation stored under the given token hash, regardless of its tenant.
+    fn find_invitation_by_token_hash(&self, token_hash: String) -> Result<Invitation, TenetError>;
+
+    /// Returns a single invitation of the tenant.
+    fn find_invitation(&self, tenant_id: Uuid, invitation_id: Uuid) -> Result<Invitation, TenetError>;
+
+    /// Returns all invitations of the tenant, oldest first.
+    fn find_invitations_by_tenant(&self, tenant_id: Uuid) -> Result<Vec<Invitation>, TenetError>;
+
+    /// Stores a new invitation with its roles as given, including its id.
+    fn create_invitation(&self, invitation: &Invitation) -> Result<Invitation, TenetError>;
+
+    /// Accepts an invitation that is neither accepted nor revoked: marks it as
+    /// accepted, creates the invitee like [`UserRepository::create_user`] or
+    /// makes the existing user a member of the invitation's tenant unless they
+    /// already are, and assigns the invitation's roles to the user.
+    ///
+    /// Returns the user, or `None` if the invitation has already been accepted
+    /// or revoked. Everything happens atomically, so that an invitation cannot
+    /// be accepted twice and a failure leaves nothing behind.
+    fn accept_invitation(&self, invitation: &Invitation, invitee: Invitee<'_>, accepted_at: NaiveDateTime) -> Result<Option<User>, TenetError>;
+
+    /// Revokes an invitation unless it has already been accepted or revoked.
+    fn revoke_invitation(&self, invitation_id: Uuid, revoked_at: NaiveDateTime) -> Result<(), TenetError>;
+}
+
+
+/// The user accepting an invitation, see [`InvitationRepository::accept_invitation`].
+#[derive(Debug, Clone, Copy)]
+pub enum Invitee<'a> {
+    /// A user to create, with the password already hashed
+    New(&'a User),
+    /// The id of an existing user
+    Existing(Uuid)
+}
+
+
+/// Persistence operations for single use user tokens, e.g. for email verification.
+///
+/// User tokens are removed together with their user.
+pub trait UserTokenRepository {
+    /// Returns the user token stored under the given token hash, regardless of its tenant.
+    fn find_user_token_by_token_hash(&self, token_hash: String) -> Result<UserToken, TenetError>;
+
+    /// Stores a new user token as given, including its id.
+    fn create_user_token(&self, user_token: &UserToken) -> Result<UserToken, TenetError>;
+
+    /// Marks an unused user token as used.
+    ///
+    /// Returns `false` if the token had already been used. Check and update must
+    /// happen atomically, so that a token cannot be used twice.
+    fn use_user_token(&self, user_token_id: Uuid, used_at: NaiveDateTime) -> Result<bool, TenetError>;
+}
+
+
+/// Persistence operations for the previous passwords of users.
+///
+/// Entries are removed together with their user.
+pub trait PasswordHistoryRepository {
+    /// Returns the newest `limit` entries of the user's password history, newest first.
+    fn find_password_history(&self, user_id: Uuid, limit: usize) -> Result<Vec<PasswordHistoryEntry>, TenetError>;
+
+    /// Updates the user with a new password, stores the entry for their
+    /// previous one, including its id, and removes all but the newest
+    /// `history_limit` entries of the user's history. Either all of it is
+    /// stored or nothing.
+    fn update_user_password(&self, user: &User, entry: &PasswordHistoryEntry, history_limit: usize) -> Result<User, TenetError>;
+}
+
+
+/// Persistence operations for the password policies of single tenants.
+///
+/// A tenant has at most one policy, which is removed together with the tenant.
+pub trait PasswordPolicyRepository {
+    /// Returns the policy of the tenant, or a not found error if it has none.
+    fn find_password_policy(&self, tenant_id: Uuid) -> Result<PasswordPolicy, TenetError>;
+
+    /// Stores the policy of the tenant, replacing a previous one.
+    fn save_password_policy(&self, tenant_id: Uuid, policy: &PasswordPolicy) -> Result<PasswordPolicy, TenetError>;
+
+    /// Removes the policy of the tenant, if any.
+    fn delete_password_policy(&self, tenant_id: Uuid) -> Result<(), TenetError>;
+}
+
+
+/// A complete persistence backend for Tenet.
+///
+/// This trait is implemented automatically for every type that implements
+/// all of the individual repository traits.
+pub trait Repository:
+    TenantRepository
+    + UserRepository
+    + MembershipRepository
+    + ApplicationRepository
+    + StorageRepository
+    + RoleRepository
+    + RoleDefinitionRepository
+    + GroupRepository
+    + SessionRepository
+    + RefreshTokenRepository
+    + InvitationRepository
+    + UserTokenRepository
+    + PasswordHistoryRepository
+    + PasswordPolicyRepository
+    + Debug
+    + Send
+    + Sync
+{
+}
+
+impl<T> Repository for T
+where
+    T: TenantRepository
+        + UserRepository
+        + MembershipRepository
+        + ApplicationRepository
+        + StorageRepository
+        + RoleRepository
+        + RoleDefinitionRepository
+        + GroupRepository
+        + SessionRepository
+        + RefreshTokenRepository
+        + InvitationRepository
+        + UserTokenRepository
+        + PasswordHistoryRepository
+        + PasswordPolicyRepository
+        + Debug
+        + Send
+        + Sync
+{
+}
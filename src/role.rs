@@ -1,15 +1,25 @@
-use std::str::FromStr;
-
 use chrono::{NaiveDateTime, Utc};
 
-use crate::{postgresql::dbrole::DbRole, role_type::RoleType};
+use crate::{role_type::RoleType, RoleDefinition};
 
 
-#[derive(Debug, Clone, Copy, serde_derive::Serialize, serde_derive::Deserialize)]
+/// A role of a user or of a group, either for one application or, without
+/// `application_id`, tenant-wide for all applications of the tenant.
+///
+/// Members of a group inherit the group's roles.
+#[derive(Debug, Clone, Copy, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
 pub struct Role { 
     pub id: uuid::Uuid,
+    /// Role type of the referenced definition
     pub role_type: RoleType,
+    /// `None` until the role is added, [`crate::Tenant::add_role`] then picks
+    /// the tenant's built-in definition for `role_type`.
+    pub role_definition_id: Option<uuid::Uuid>,
+    /// `None` for roles of a group
     pub user_id: Option<uuid::Uuid>,
+    /// `None` for roles of a user
+    pub group_id: Option<uuid::Uuid>,
+    /// `None` for tenant-wide roles
     pub application_id: Option<uuid::Uuid>,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
@@ -17,30 +27,80 @@ pub struct Role {
 }
 
 
-impl From<&DbRole> for Role {
-    fn from(value: &DbRole) -> Self {
-        Role {
-            id: value.id,
-            role_type: RoleType::from_str(&value.role_type).unwrap(),
-            user_id: value.user_id,
-            application_id: value.application_id,
-            created_at: value.created_at,
-            updated_at: value.updated_at,
-            db_tenant_id: value.db_tenant_id
-        }
-    }
-}
-
 impl Role {
     pub fn new(role_type: RoleType, user_id: uuid::Uuid, application_id: uuid::Uuid, tenant_id: uuid::Uuid) -> Self {
         Role { 
             id: uuid::Uuid::new_v4(), 
             user_id: Some(user_id), 
+            group_id: None,
             application_id: Some(application_id),
             role_type,
+            role_definition_id: None,
             created_at: Utc::now().naive_utc(),
             updated_at: None,
             db_tenant_id: Some(tenant_id)
          }
     }
-}
\ No newline at end of file
+
+    /// Creates a role assigning a custom role definition.
+    pub fn with_definition(role_definition: &RoleDefinition, user_id: uuid::Uuid, application_id: uuid::Uuid, tenant_id: uuid::Uuid) -> Self {
+        Role {
+            role_definition_id: Some(role_definition.id),
+            ..Role::new(role_definition.role_type, user_id, application_id, tenant_id)
+        }
+    }
+
+    /// Creates a role that applies to all applications of the tenant, e.g. for
+    /// a tenant owner.
+    pub fn new_tenant_wide(role_type: RoleType, user_id: uuid::Uuid, tenant_id: uuid::Uuid) -> Self {
+        Role {
+            id: uuid::Uuid::new_v4(),
+            user_id: Some(user_id),
+            group_id: None,
+            application_id: None,
+            role_type,
+            role_definition_id: None,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            db_tenant_id: Some(tenant_id)
+        }
+    }
+
+    /// Creates a tenant-wide role assigning a custom role definition.
+    pub fn tenant_wide_with_definition(role_definition: &RoleDefinition, user_id: uuid::Uuid, tenant_id: uuid::Uuid) -> Self {
+        Role {
+            role_definition_id: Some(role_definition.id),
+            ..Role::new_tenant_wide(role_definition.role_type, user_id, tenant_id)
+        }
+    }
+
+    /// Creates a role for a group, inherited by all of its members. Without
+    /// `application_id` it applies tenant-wide.
+    pub fn for_group(role_definition: &RoleDefinition, group_id: uuid::Uuid, application_id: Option<uuid::Uuid>, tenant_id: uuid::Uuid) -> Self {
+        Role {
+            id: uuid::Uuid::new_v4(),
+            user_id: None,
+            group_id: Some(group_id),
+            application_id,
+            role_type: role_definition.role_type,
+            role_definition_id: Some(role_definition.id),
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            db_tenant_id: Some(tenant_id)
+        }
+    }
+
+    pub fn is_tenant_wide(&self) -> bool {
+        self.application_id.is_none()
+    }
+
+    /// Whether the role is inherited from a group rather than assigned to the user.
+    pub fn is_inherited(&self) -> bool {
+        self.group_id.is_some()
+    }
+
+    /// Whether the role applies to the application, either directly or tenant-wide.
+    pub fn applies_to(&self, application_id: uuid::Uuid) -> bool {
+        self.application_id.is_none_or(|id| id == application_id)
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::{NaiveDateTime, Utc};
+
+use crate::role_type::RoleType;
+
+
+/// Permission granting everything, held by the built-in Administrator definition.
+pub const ALL_PERMISSIONS: &str = "*";
+
+
+/// A tenant-defined role made of named permissions, like `orders:read` or
+/// `catalog:write`.
+///
+/// Every tenant starts with the built-in definitions `Administrator` and
+/// `User`, see [`RoleDefinition::defaults`]. `role_type` is the built-in level
+/// the definition counts as for [`crate::Tenant::is_authorized`].
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct RoleDefinition {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub role_type: RoleType,
+    pub permissions: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<uuid::Uuid>
+}
+
+impl RoleDefinition {
+    pub fn new(name: String, role_type: RoleType, permissions: Vec<String>, tenant_id: uuid::Uuid) -> Self {
+        RoleDefinition {
+            id: uuid::Uuid::new_v4(),
+            name,
+            role_type,
+            permissions,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            db_tenant_id: Some(tenant_id)
+        }
+    }
+
+    /// The built-in definitions seeded for every new tenant. Administrator
+    /// holds all permissions, User none.
+    pub fn defaults(tenant_id: uuid::Uuid) -> Vec<RoleDefinition> {
+        vec![
+            RoleDefinition::new(RoleType::Administrator.to_string(), RoleType::Administrator, vec![ALL_PERMISSIONS.to_string()], tenant_id),
+            RoleDefinition::new(RoleType::User.to_string(), RoleType::User, Vec::new(), tenant_id)
+        ]
+    }
+
+    /// Whether this is one of the built-in definitions seeded by
+    /// [`RoleDefinition::defaults`]. They cannot be changed or deleted.
+    pub fn is_builtin(&self) -> bool {
+        [RoleType::Administrator, RoleType::User].iter().any(|role_type| self.name == role_type.to_string())
+    }
+
+    /// Whether one of the definition's permissions covers `permission`.
+    ///
+    /// `*` covers everything and `orders:*` covers every permission starting
+    /// with `orders:`.
+    pub fn grants(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| permission_covers(granted, permission))
+    }
+}
+
+fn permission_covers(granted: &str, permission: &str) -> bool {
+    match granted.strip_suffix('*') {
+        Some(prefix) => (prefix.is_empty() || prefix.ends_with(':')) && permission.starts_with(prefix),
+        None => granted == permission
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grants_test() {
+        let definition = RoleDefinition::new("Clerk".to_string(), RoleType::User, vec!["orders:*".to_string(), "catalog:read".to_string()], uuid::Uuid::new_v4());
+        assert!(definition.grants("orders:read"));
+        assert!(definition.grants("orders:write"));
+        assert!(definition.grants("catalog:read"));
+        assert!(!definition.grants("catalog:write"));
+        assert!(!definition.grants("ordersx:read"));
+
+        let defaults = RoleDefinition::defaults(uuid::Uuid::new_v4());
+        assert!(defaults[0].grants("anything:at_all"));
+        assert!(!defaults[1].grants("orders:read"));
+    }
+}
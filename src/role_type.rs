@@ -18,7 +18,7 @@ use std::{str::FromStr, fmt::Display};
 /// let parsed_role = RoleType::from_str("Administrator").unwrap();
 /// assert_eq!(parsed_role, RoleType::Administrator);
 /// ```
-#[derive(Debug, Clone, Copy, serde_derive::Serialize, serde_derive::Deserialize, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, serde_derive::Serialize, serde_derive::Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RoleType {
     /// Administrator role with comprehensive permissions
     Administrator = 99,
@@ -1,6 +1,21 @@
 // @generated automatically by Diesel CLI.
+//
+// Shared by the PostgreSQL and SQLite backends. Ids use the `Uuid` type from
+// `sql_types` below instead of diesel's, see `crate::postgresql::database::DbUuid`.
+
+pub mod sql_types {
+    /// A UUID column: the native `uuid` type in PostgreSQL and TEXT in
+    /// SQLite, which has no UUID type.
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(oid = 2950, array_oid = 2951))]
+    #[diesel(sqlite_type(name = "Text"))]
+    pub struct Uuid;
+}
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
     applications (id) {
         id -> Uuid,
         application_type -> Text,
@@ -12,10 +27,145 @@ diesel::table! {
 }
 
 diesel::table! {
-    roles (id) {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    group_members (group_id, user_id) {
+        group_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    groups (id) {
+        id -> Uuid,
+        name -> Text,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+        db_tenant_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    invitation_roles (id) {
+        id -> Uuid,
+        invitation_id -> Uuid,
+        role_definition_id -> Uuid,
+        application_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    invitations (id) {
+        id -> Uuid,
+        email -> Text,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        accepted_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        db_tenant_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    memberships (user_id, db_tenant_id) {
+        user_id -> Uuid,
+        db_tenant_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    password_history (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        password -> Text,
+        encryption_mode -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    password_policies (db_tenant_id) {
+        db_tenant_id -> Uuid,
+        policy -> Text,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    permissions (id) {
+        id -> Uuid,
+        role_definition_id -> Uuid,
+        name -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    refresh_tokens (id) {
         id -> Uuid,
+        family_id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        db_tenant_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    role_definitions (id) {
+        id -> Uuid,
+        name -> Text,
         role_type -> Text,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+        db_tenant_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    roles (id) {
+        id -> Uuid,
+        role_definition_id -> Uuid,
         user_id -> Nullable<Uuid>,
+        group_id -> Nullable<Uuid>,
         application_id -> Nullable<Uuid>,
         created_at -> Timestamp,
         updated_at -> Nullable<Timestamp>,
@@ -24,6 +174,25 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        last_used_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        db_tenant_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
     storages (id) {
         id -> Uuid,
         storage_type -> Text,
@@ -38,6 +207,9 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
     tenants (id) {
         id -> Uuid,
         title -> Text,
@@ -47,6 +219,25 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
+    user_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        purpose -> Text,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        db_tenant_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Uuid;
+
     users (id) {
         id -> Uuid,
         email -> Text,
@@ -62,16 +253,49 @@ diesel::table! {
 
 diesel::joinable!(applications -> storages (storage_id));
 diesel::joinable!(applications -> tenants (db_tenant_id));
+diesel::joinable!(group_members -> groups (group_id));
+diesel::joinable!(group_members -> users (user_id));
+diesel::joinable!(groups -> tenants (db_tenant_id));
+diesel::joinable!(invitation_roles -> applications (application_id));
+diesel::joinable!(invitation_roles -> invitations (invitation_id));
+diesel::joinable!(invitation_roles -> role_definitions (role_definition_id));
+diesel::joinable!(invitations -> tenants (db_tenant_id));
+diesel::joinable!(memberships -> tenants (db_tenant_id));
+diesel::joinable!(memberships -> users (user_id));
+diesel::joinable!(password_history -> users (user_id));
+diesel::joinable!(password_policies -> tenants (db_tenant_id));
+diesel::joinable!(permissions -> role_definitions (role_definition_id));
+diesel::joinable!(refresh_tokens -> tenants (db_tenant_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(role_definitions -> tenants (db_tenant_id));
 diesel::joinable!(roles -> applications (application_id));
+diesel::joinable!(roles -> groups (group_id));
+diesel::joinable!(roles -> role_definitions (role_definition_id));
 diesel::joinable!(roles -> tenants (db_tenant_id));
 diesel::joinable!(roles -> users (user_id));
+diesel::joinable!(sessions -> tenants (db_tenant_id));
+diesel::joinable!(sessions -> users (user_id));
 diesel::joinable!(storages -> tenants (db_tenant_id));
+diesel::joinable!(user_tokens -> tenants (db_tenant_id));
+diesel::joinable!(user_tokens -> users (user_id));
 diesel::joinable!(users -> tenants (db_tenant_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     applications,
+    group_members,
+    groups,
+    invitation_roles,
+    invitations,
+    memberships,
+    password_history,
+    password_policies,
+    permissions,
+    refresh_tokens,
+    role_definitions,
     roles,
+    sessions,
     storages,
     tenants,
+    user_tokens,
     users,
 );
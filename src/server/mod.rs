@@ -0,0 +1,442 @@
+use axum::extract::{Path, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use chrono::Utc;
+use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{application_type::ApplicationType, encryption_modes::EncryptionModes, role_type::RoleType, session::AdminToken, storage_type::StorageType};
+use crate::{Application, Role, Storage, Tenant, Tenet, TenetError, User};
+
+
+/// Builds the REST/JSON router for the given Tenet instance.
+///
+/// Tenant scoped routes follow the `/<tenant-id>/<application-id>` URL schema:
+///
+/// | Route                                           | Methods             |
+/// |-------------------------------------------------|---------------------|
+/// | `/tenants`                                      | `GET`, `POST`       |
+/// | `/login`                                        | `POST`              |
+/// | `/{tenant_id}`                                  | `GET`, `PUT`, `DELETE` |
+/// | `/{tenant_id}/users`                            | `GET`, `POST`       |
+/// | `/{tenant_id}/users/{user_id}`                  | `GET`, `PUT`, `DELETE` |
+/// | `/{tenant_id}/users/{user_id}/roles`            | `GET`               |
+/// | `/{tenant_id}/members/{user_id}`                | `PUT`, `DELETE`     |
+/// | `/{tenant_id}/storages`                         | `GET`, `POST`       |
+/// | `/{tenant_id}/storages/{storage_id}`            | `GET`, `DELETE`     |
+/// | `/{tenant_id}/applications`                     | `GET`, `POST`       |
+/// | `/{tenant_id}/applications/{application_id}`    | `GET`, `DELETE`     |
+/// | `/{tenant_id}/roles`                            | `GET`, `POST`       |
+/// | `/{tenant_id}/roles/{role_id}`                  | `GET`, `DELETE`     |
+/// | `/{tenant_id}/authenticate`                     | `POST`              |
+/// | `/{tenant_id}/{application_id}`                 | `GET`               |
+/// | `/{tenant_id}/{application_id}/authenticate`    | `POST`              |
+///
+/// `/login` authenticates a user of any tenant and returns the tenants they
+/// are a member of; the client then continues with the chosen tenant's routes.
+///
+/// Only `/login` and the two `authenticate` routes are public. All other
+/// routes administer tenants and require `admin_token` as bearer token, i.e.
+/// an `Authorization: Bearer <admin_token>` header, and answer `401` without
+/// it. An empty `admin_token` rejects every administrative request.
+///
+/// Responses use the serde representation of [`Tenant`], [`User`],
+/// [`Application`], [`Storage`] and [`Role`]. Password hashes are never
+/// returned. Errors are returned as `{"error": "..."}`.
+///
+/// Only available with the `server` cargo feature.
+pub fn router(tenet: Tenet, admin_token: &str) -> Router {
+    let admin_routes = Router::new()
+        .route("/tenants", get(get_tenants).post(create_tenant))
+        .route("/{tenant_id}", get(get_tenant).put(update_tenant).delete(delete_tenant))
+        .route("/{tenant_id}/users", get(get_users).post(create_user))
+        .route("/{tenant_id}/users/{user_id}", get(get_user).put(update_user).delete(delete_user))
+        .route("/{tenant_id}/users/{user_id}/roles", get(get_roles_for_user))
+        .route("/{tenant_id}/members/{user_id}", put(add_member).delete(remove_member))
+        .route("/{tenant_id}/storages", get(get_storages).post(create_storage))
+        .route("/{tenant_id}/storages/{storage_id}", get(get_storage).delete(delete_storage))
+        .route("/{tenant_id}/applications", get(get_applications).post(create_application))
+        .route("/{tenant_id}/applications/{application_id}", get(get_application).delete(delete_application))
+        .route("/{tenant_id}/roles", get(get_roles).post(create_role))
+        .route("/{tenant_id}/roles/{role_id}", get(get_role).delete(delete_role))
+        .route("/{tenant_id}/{application_id}", get(get_application))
+        .route_layer(middleware::from_fn_with_state(AdminToken::new(admin_token), require_admin_token));
+
+    Router::new()
+        .route("/login", post(login))
+        .route("/{tenant_id}/authenticate", post(authenticate))
+        .route("/{tenant_id}/{application_id}/authenticate", post(authenticate_for_application))
+        .merge(admin_routes)
+        .with_state(tenet)
+}
+
+
+/* Authentication */
+
+async fn require_admin_token(State(admin_token): State<AdminToken>, request: Request, next: Next) -> Response {
+    let authorized = request.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| admin_token.matches(token));
+    if !authorized {
+        return ApiError(StatusCode::UNAUTHORIZED, "Missing or invalid admin token".to_string()).into_response();
+    }
+    next.run(request).await
+}
+
+
+/* Request and response bodies */
+
+#[derive(Debug, Deserialize)]
+pub struct TenantRequest {
+    pub title: String
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewUser {
+    pub email: String,
+    pub full_name: String,
+    pub password: String,
+    #[serde(default)]
+    pub email_verified: bool
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserUpdate {
+    pub email: Option<String>,
+    pub full_name: Option<String>,
+    pub email_verified: Option<bool>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewStorage {
+    pub storage_type: StorageType,
+    pub path: Option<String>,
+    pub connection_string: Option<String>,
+    pub schema: Option<String>,
+    pub table_prefix: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewApplication {
+    pub application_type: ApplicationType,
+    pub storage_id: Uuid
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewRole {
+    pub role_type: RoleType,
+    pub user_id: Uuid,
+    /// Omitted for tenant-wide roles
+    pub application_id: Option<Uuid>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String
+}
+
+#[derive(Debug, Serialize)]
+pub struct Authentication {
+    pub user: User,
+    pub roles: Vec<Role>
+}
+
+#[derive(Debug, Serialize)]
+pub struct Login {
+    pub user: User,
+    pub tenants: Vec<Tenant>
+}
+
+
+/* Errors */
+
+/// Error response of the REST API, rendered as `{"error": "..."}`.
+#[derive(Debug)]
+pub struct ApiError(StatusCode, String);
+
+impl From<TenetError> for ApiError {
+    fn from(error: TenetError) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error};
+
+        let status = match error {
+            TenetError::NotFoundError | TenetError::DatabaseError(Error::NotFound) => StatusCode::NOT_FOUND,
+            TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => StatusCode::CONFLICT,
+            TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => StatusCode::CONFLICT,
+            TenetError::PasswordPolicyError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR
+        };
+        ApiError(status, error.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+
+/// Runs blocking Tenet calls off the async runtime.
+async fn blocking<T, F>(tenet: Tenet, f: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(Tenet) -> ApiResult<T> + Send + 'static
+{
+    tokio::task::spawn_blocking(move || f(tenet))
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+fn find_tenant(tenet: &Tenet, tenant_id: Uuid) -> ApiResult<Tenant> {
+    tenet.get_tenant_by_id(tenant_id).ok_or(ApiError::from(TenetError::NotFoundError))
+}
+
+fn without_password(mut user: User) -> User {
+    user.password = String::new();
+    user
+}
+
+
+/* Tenants */
+
+async fn get_tenants(State(tenet): State<Tenet>) -> ApiResult<Json<Vec<Tenant>>> {
+    blocking(tenet, |tenet| {
+        Ok(Json(tenet.get_tenant_ids().into_iter().filter_map(|id| tenet.get_tenant_by_id(id)).collect()))
+    }).await
+}
+
+async fn create_tenant(State(tenet): State<Tenet>, Json(request): Json<TenantRequest>) -> ApiResult<(StatusCode, Json<Tenant>)> {
+    blocking(tenet, |tenet| Ok((StatusCode::CREATED, Json(tenet.create_tenant(request.title)?)))).await
+}
+
+async fn get_tenant(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>) -> ApiResult<Json<Tenant>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?))).await
+}
+
+async fn update_tenant(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>, Json(request): Json<TenantRequest>) -> ApiResult<Json<Tenant>> {
+    blocking(tenet, move |tenet| {
+        find_tenant(&tenet, tenant_id)?;
+        Ok(Json(tenet.set_tenant_title(tenant_id, request.title)?))
+    }).await
+}
+
+async fn delete_tenant(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>) -> ApiResult<StatusCode> {
+    blocking(tenet, move |tenet| {
+        find_tenant(&tenet, tenant_id)?;
+        tenet.delete_tenant(tenant_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }).await
+}
+
+
+/* Users */
+
+async fn get_users(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>) -> ApiResult<Json<Vec<User>>> {
+    blocking(tenet, move |tenet| {
+        Ok(Json(find_tenant(&tenet, tenant_id)?.get_users().into_iter().map(without_password).collect()))
+    }).await
+}
+
+async fn create_user(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>, Json(request): Json<NewUser>) -> ApiResult<(StatusCode, Json<User>)> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        let user = User::new(
+            request.email.clone(),
+            request.full_name,
+            request.password,
+            EncryptionModes::Argon2,
+            request.email,
+            request.email_verified,
+            tenant_id);
+        Ok((StatusCode::CREATED, Json(without_password(tenant.add_user(&user)?))))
+    }).await
+}
+
+async fn get_user(State(tenet): State<Tenet>, Path((tenant_id, user_id)): Path<(Uuid, Uuid)>) -> ApiResult<Json<User>> {
+    blocking(tenet, move |tenet| {
+        Ok(Json(without_password(find_tenant(&tenet, tenant_id)?.get_user_by_id(user_id)?)))
+    }).await
+}
+
+async fn update_user(State(tenet): State<Tenet>, Path((tenant_id, user_id)): Path<(Uuid, Uuid)>, Json(request): Json<UserUpdate>) -> ApiResult<Json<User>> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        let mut user = tenant.get_user_by_id(user_id)?;
+        if let Some(email) = request.email {
+            user.username = email.clone();
+            user.email = email;
+        }
+        if let Some(full_name) = request.full_name {
+            user.full_name = full_name;
+        }
+        if let Some(email_verified) = request.email_verified {
+            user.email_verified = email_verified;
+        }
+        user.updated_at = Some(Utc::now().naive_utc());
+        Ok(Json(without_password(tenant.update_user(&user)?)))
+    }).await
+}
+
+async fn delete_user(State(tenet): State<Tenet>, Path((tenant_id, user_id)): Path<(Uuid, Uuid)>) -> ApiResult<StatusCode> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        tenant.get_user_by_id(user_id)?;
+        tenant.delete_user(user_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }).await
+}
+
+async fn add_member(State(tenet): State<Tenet>, Path((tenant_id, user_id)): Path<(Uuid, Uuid)>) -> ApiResult<Json<User>> {
+    blocking(tenet, move |tenet| {
+        let user = find_tenant(&tenet, tenant_id)?.add_member(user_id)?;
+        Ok(Json(without_password(user)))
+    }).await
+}
+
+async fn remove_member(State(tenet): State<Tenet>, Path((tenant_id, user_id)): Path<(Uuid, Uuid)>) -> ApiResult<StatusCode> {
+    blocking(tenet, move |tenet| {
+        find_tenant(&tenet, tenant_id)?.remove_member(user_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }).await
+}
+
+async fn get_roles_for_user(State(tenet): State<Tenet>, Path((tenant_id, user_id)): Path<(Uuid, Uuid)>) -> ApiResult<Json<Vec<Role>>> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        tenant.get_user_by_id(user_id)?;
+        Ok(Json(tenant.get_roles_for_user(user_id)?))
+    }).await
+}
+
+async fn login(State(tenet): State<Tenet>, Json(credentials): Json<Credentials>) -> ApiResult<Json<Login>> {
+    blocking(tenet, move |tenet| {
+        tenet.login(credentials.username, credentials.password)
+            .map(|selection| Json(Login { user: without_password(selection.user), tenants: selection.tenants }))
+            .ok_or(ApiError(StatusCode::UNAUTHORIZED, "Authentication failed".to_string()))
+    }).await
+}
+
+async fn authenticate(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>, Json(credentials): Json<Credentials>) -> ApiResult<Json<User>> {
+    blocking(tenet, move |tenet| {
+        find_tenant(&tenet, tenant_id)?
+            .authenticate_user(credentials.username, credentials.password)
+            .map(|user| Json(without_password(user)))
+            .ok_or(ApiError(StatusCode::UNAUTHORIZED, "Authentication failed".to_string()))
+    }).await
+}
+
+async fn authenticate_for_application(State(tenet): State<Tenet>, Path((tenant_id, application_id)): Path<(Uuid, Uuid)>, Json(credentials): Json<Credentials>) -> ApiResult<Json<Authentication>> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        tenant.get_application_by_id(application_id)?;
+
+        let user = tenant.authenticate_user(credentials.username, credentials.password)
+            .ok_or(ApiError(StatusCode::UNAUTHORIZED, "Authentication failed".to_string()))?;
+        let roles = tenant.get_roles_for_user_in_application(user.id, application_id)?;
+        Ok(Json(Authentication { user: without_password(user), roles }))
+    }).await
+}
+
+
+/* Storages */
+
+async fn get_storages(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>) -> ApiResult<Json<Vec<Storage>>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?.get_storages()))).await
+}
+
+async fn create_storage(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>, Json(request): Json<NewStorage>) -> ApiResult<(StatusCode, Json<Storage>)> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        let storage = Storage {
+            id: Uuid::new_v4(),
+            storage_type: request.storage_type,
+            path: request.path,
+            connection_string: request.connection_string,
+            schema: request.schema,
+            table_prefix: request.table_prefix,
+            created_at: Utc::now().naive_utc(),
+            updated_at: None,
+            db_tenant_id: Some(tenant_id)
+        };
+        Ok((StatusCode::CREATED, Json(tenant.add_storage(&storage)?)))
+    }).await
+}
+
+async fn get_storage(State(tenet): State<Tenet>, Path((tenant_id, storage_id)): Path<(Uuid, Uuid)>) -> ApiResult<Json<Storage>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?.get_storage_by_id(storage_id)?))).await
+}
+
+async fn delete_storage(State(tenet): State<Tenet>, Path((tenant_id, storage_id)): Path<(Uuid, Uuid)>) -> ApiResult<StatusCode> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        tenant.get_storage_by_id(storage_id)?;
+        tenant.delete_storage(storage_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }).await
+}
+
+
+/* Applications */
+
+async fn get_applications(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>) -> ApiResult<Json<Vec<Application>>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?.get_applications()))).await
+}
+
+async fn create_application(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>, Json(request): Json<NewApplication>) -> ApiResult<(StatusCode, Json<Application>)> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        let application = Application::new(request.application_type, request.storage_id, tenant_id);
+        Ok((StatusCode::CREATED, Json(tenant.add_application(&application)?)))
+    }).await
+}
+
+async fn get_application(State(tenet): State<Tenet>, Path((tenant_id, application_id)): Path<(Uuid, Uuid)>) -> ApiResult<Json<Application>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?.get_application_by_id(application_id)?))).await
+}
+
+async fn delete_application(State(tenet): State<Tenet>, Path((tenant_id, application_id)): Path<(Uuid, Uuid)>) -> ApiResult<StatusCode> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        tenant.get_application_by_id(application_id)?;
+        tenant.delete_application(application_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }).await
+}
+
+
+/* Roles */
+
+async fn get_roles(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>) -> ApiResult<Json<Vec<Role>>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?.get_roles()))).await
+}
+
+async fn create_role(State(tenet): State<Tenet>, Path(tenant_id): Path<Uuid>, Json(request): Json<NewRole>) -> ApiResult<(StatusCode, Json<Role>)> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        let role = match request.application_id {
+            Some(application_id) => Role::new(request.role_type, request.user_id, application_id, tenant_id),
+            None => Role::new_tenant_wide(request.role_type, request.user_id, tenant_id)
+        };
+        Ok((StatusCode::CREATED, Json(tenant.add_role(&role)?)))
+    }).await
+}
+
+async fn get_role(State(tenet): State<Tenet>, Path((tenant_id, role_id)): Path<(Uuid, Uuid)>) -> ApiResult<Json<Role>> {
+    blocking(tenet, move |tenet| Ok(Json(find_tenant(&tenet, tenant_id)?.get_role_by_id(role_id)?))).await
+}
+
+async fn delete_role(State(tenet): State<Tenet>, Path((tenant_id, role_id)): Path<(Uuid, Uuid)>) -> ApiResult<StatusCode> {
+    blocking(tenet, move |tenet| {
+        let tenant = find_tenant(&tenet, tenant_id)?;
+        tenant.get_role_by_id(role_id)?;
+        tenant.delete_role(role_id)?;
+        Ok(StatusCode::NO_CONTENT)
+    }).await
+}
@@ -0,0 +1,217 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::TenetError;
+
+
+/// A login session of a user, created by [`crate::Tenant::create_session`].
+///
+/// Only a SHA-256 hash of the session token is stored, the token itself is
+/// handed out once in [`IssuedSession`].
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct Session {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<uuid::Uuid>
+}
+
+impl Session {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().naive_utc()
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+}
+
+
+/// A freshly created session together with its token.
+///
+/// The token is not stored anywhere, so this is the only chance to pass it on
+/// to the client.
+#[derive(Debug, Clone)]
+pub struct IssuedSession {
+    pub token: String,
+    pub session: Session
+}
+
+
+/// Format of the session tokens handed out to clients.
+#[derive(Clone, Default)]
+pub enum SessionTokenKind {
+    /// 256 random bits, hex encoded. Every validation needs a repository lookup.
+    #[default]
+    Opaque,
+    /// `<session-id>.<random>.<hmac>`, signed with HMAC-SHA256. Tokens that
+    /// were not issued with the key are rejected without a repository lookup.
+    Signed { key: Vec<u8> }
+}
+
+impl std::fmt::Debug for SessionTokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionTokenKind::Opaque => write!(f, "Opaque"),
+            SessionTokenKind::Signed { .. } => write!(f, "Signed {{ .. }}")
+        }
+    }
+}
+
+
+/// Session settings of a [`crate::Tenet`] instance, see [`crate::Tenet::with_session_config`].
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Time a session stays valid after it was created or, with sliding
+    /// renewal, last used.
+    pub lifetime: Duration,
+    /// Extends the expiry on every successful validation.
+    pub sliding: bool,
+    /// Upper bound for the total age of a session, regardless of renewals.
+    pub absolute_lifetime: Option<Duration>,
+    pub token_kind: SessionTokenKind,
+    /// Time a refresh token stays valid after it was issued.
+    pub refresh_token_lifetime: Duration
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            lifetime: Duration::minutes(30),
+            sliding: true,
+            absolute_lifetime: None,
+            token_kind: SessionTokenKind::Opaque,
+            refresh_token_lifetime: Duration::days(30)
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Expiry of a session that is created or renewed at `now`.
+    pub(crate) fn expiry(&self, created_at: NaiveDateTime, now: NaiveDateTime) -> NaiveDateTime {
+        let expires_at = now + self.lifetime;
+        match self.absolute_lifetime {
+            Some(absolute_lifetime) => expires_at.min(created_at + absolute_lifetime),
+            None => expires_at
+        }
+    }
+
+    /// Creates a new token for the given session id.
+    pub(crate) fn issue_token(&self, session_id: uuid::Uuid) -> String {
+        let secret = random_token();
+        match &self.token_kind {
+            SessionTokenKind::Opaque => secret,
+            SessionTokenKind::Signed { key } => {
+                let payload = format!("{}.{}", session_id.simple(), secret);
+                let signature = hex::encode(hmac(key, &payload).finalize().into_bytes());
+                format!("{}.{}", payload, signature)
+            }
+        }
+    }
+
+    /// Checks the form and, for signed tokens, the signature of a token.
+    pub(crate) fn check_token(&self, token: &str) -> Result<(), TenetError> {
+        match &self.token_kind {
+            SessionTokenKind::Opaque => Ok(()),
+            SessionTokenKind::Signed { key } => {
+                let (payload, signature) = token.rsplit_once('.').ok_or(TenetError::InvalidTokenError)?;
+                let signature = hex::decode(signature).map_err(|_| TenetError::InvalidTokenError)?;
+                hmac(key, payload).verify_slice(&signature).map_err(|_| TenetError::InvalidTokenError)
+            }
+        }
+    }
+}
+
+fn hmac(key: &[u8], payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+
+/// Returns 256 random bits, hex encoded.
+pub(crate) fn random_token() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    hex::encode(bytes)
+}
+
+/// Returns the hex encoded SHA-256 hash under which a token is stored.
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opaque_token_test() {
+        let config = SessionConfig::default();
+        let token = config.issue_token(uuid::Uuid::new_v4());
+
+        assert_eq!(64, token.len());
+        assert_ne!(token, config.issue_token(uuid::Uuid::new_v4()));
+        assert!(config.check_token(&token).is_ok());
+        assert_ne!(token, hash_token(&token));
+    }
+
+    #[test]
+    fn signed_token_test() {
+        let config = SessionConfig { token_kind: SessionTokenKind::Signed { key: b"secret".to_vec() }, ..Default::default() };
+        let other = SessionConfig { token_kind: SessionTokenKind::Signed { key: b"other".to_vec() }, ..Default::default() };
+        let session_id = uuid::Uuid::new_v4();
+        let token = config.issue_token(session_id);
+
+        assert!(token.starts_with(&session_id.simple().to_string()));
+        assert!(config.check_token(&token).is_ok());
+        assert!(matches!(other.check_token(&token), Err(TenetError::InvalidTokenError)));
+        assert!(matches!(config.check_token(&token.replace('.', "")), Err(TenetError::InvalidTokenError)));
+        assert!(matches!(config.check_token(&format!("{}0", token)), Err(TenetError::InvalidTokenError)));
+    }
+
+    #[test]
+    fn expiry_test() {
+        let now = Utc::now().naive_utc();
+        let config = SessionConfig::default();
+        assert_eq!(now + Duration::minutes(30), config.expiry(now, now));
+
+        let capped = SessionConfig { absolute_lifetime: Some(Duration::minutes(40)), ..Default::default() };
+        let created_at = now - Duration::minutes(20);
+        assert_eq!(created_at + Duration::minutes(40), capped.expiry(created_at, now));
+    }
+}
+
+
+/// SHA-256 hash of the token guarding the admin APIs, `None` if the token is
+/// empty, which locks them.
+#[cfg(any(feature = "server", feature = "grpc"))]
+#[derive(Clone)]
+pub(crate) struct AdminToken(Option<String>);
+
+#[cfg(any(feature = "server", feature = "grpc"))]
+impl AdminToken {
+    pub(crate) fn new(token: &str) -> Self {
+        AdminToken((!token.is_empty()).then(|| hash_token(token)))
+    }
+
+    /// Compares hashes, so the time taken does not depend on how much of the
+    /// presented token is right.
+    pub(crate) fn matches(&self, token: &str) -> bool {
+        self.0.as_ref().is_some_and(|hash| *hash == hash_token(token))
+    }
+}
+
+#[cfg(any(feature = "server", feature = "grpc"))]
+impl std::fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AdminToken(..)")
+    }
+}
@@ -0,0 +1,52 @@
+use diesel::{Connection, SqliteConnection};
+use diesel::connection::SimpleConnection;
+use diesel::r2d2::CustomizeConnection;
+use log::info;
+
+use diesel_migrations::EmbeddedMigrations;
+use crate::diesel_migrations::MigrationHarness;
+use crate::postgresql::database::{AnyConnection, ConnectionManager, Pool};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite");
+
+/// Applies the per-connection settings SQLite needs to behave like the
+/// PostgreSQL backend: foreign keys are enforced and concurrent writers wait
+/// for each other instead of failing immediately.
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<AnyConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, connection: &mut AnyConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// Builds a new connection pool for the given database file and runs any
+/// pending migrations against it.
+///
+/// Every connection to `:memory:` opens its own, empty database, so the pool
+/// keeps exactly one connection open for its whole lifetime in that case.
+pub fn build_pool(path: &str) -> Pool {
+    info!("Initializing Tenet SQLite Database");
+    info!("Tenet database file: {}", path);
+
+    let manager = ConnectionManager::new(path, |path| SqliteConnection::establish(path).map(AnyConnection::Sqlite));
+    let mut builder = Pool::builder()
+        .connection_customizer(Box::new(ConnectionOptions));
+    if path == ":memory:" {
+        builder = builder
+            .max_size(1)
+            .idle_timeout(None)
+            .max_lifetime(None);
+    }
+    let pool = builder.build(manager).expect("Failed to create db pool");
+
+    info!("Running pending database migrations...");
+    let mut connection = pool.get().expect("Failed to get db connection");
+    connection.run_pending_migrations(MIGRATIONS).expect("Unable to run migrations");
+    info!("Database migrations completed");
+
+    pool
+}
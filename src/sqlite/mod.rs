@@ -0,0 +1,4 @@
+pub mod database;
+pub mod repository;
+
+pub use repository::SqliteRepository;
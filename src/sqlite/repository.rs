@@ -0,0 +1,64 @@
+use diesel::sqlite::Sqlite;
+
+use super::database;
+use crate::postgresql::DieselRepository;
+
+
+/// The SQLite backend used by [`crate::Tenet::sqlite`].
+pub type SqliteRepository = DieselRepository<Sqlite>;
+
+impl SqliteRepository {
+    /// Opens (or creates) the database file and runs any pending migrations.
+    ///
+    /// Pass `:memory:` for a throwaway database that lives as long as the repository.
+    pub fn new(path: &str) -> Self {
+        DieselRepository::with_pool(database::build_pool(path))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{application_type::ApplicationType, role_type::RoleType};
+    use crate::{Application, Role, Storage, Tenet};
+    use crate::test_suite::{repository_test_suite, user};
+
+    repository_test_suite!(|test| test(Tenet::sqlite(":memory:")));
+
+    #[test]
+    fn create_and_load_test() {
+        let tenet = Tenet::sqlite(":memory:");
+        let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+
+        let storage = tenant.add_storage(&Storage::new_sqlite_database("shop.db", tenant.id)).unwrap();
+        let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+        let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+        let role = tenant.add_role(&Role::new(RoleType::Administrator, created_user.id, application.id, tenant.id)).unwrap();
+
+        assert_eq!(vec![tenant.id], tenet.get_tenant_ids());
+        assert_eq!(storage.path, tenant.get_storage_by_id(storage.id).unwrap().path);
+        assert_eq!(application.storage_id, tenant.get_application_by_id(application.id).unwrap().storage_id);
+        assert_eq!(created_user.email, tenant.get_user_by_id(created_user.id).unwrap().email);
+        assert_eq!(RoleType::Administrator, tenant.get_roles_for_user(created_user.id).unwrap()[0].role_type);
+        assert_eq!(role.id, tenant.get_role_by_id(role.id).unwrap().id);
+
+        assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_some());
+        assert_eq!(tenant.id, tenet.get_tenant_by_username("someone@something.de".to_string()).unwrap().id);
+
+        let updated_tenant = tenet.set_tenant_title(tenant.id, "Updated Title".to_string()).unwrap();
+        assert_eq!("Updated Title", updated_tenant.title);
+    }
+
+    #[test]
+    fn persistent_file_test() {
+        let path = std::env::temp_dir().join(format!("tenet-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        let tenant_id = Tenet::sqlite(path).create_tenant("Persistent".to_string()).unwrap().id;
+
+        let reopened = Tenet::sqlite(path);
+        assert_eq!("Persistent", reopened.get_tenant_by_id(tenant_id).unwrap().title);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
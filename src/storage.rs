@@ -24,7 +24,7 @@ pub struct Storage {
 impl From<&DbStorage> for Storage {
     fn from(value: &DbStorage) -> Self {
         Storage { 
-            id: value.id, 
+            id: value.id.0, 
             storage_type: StorageType::from_str(&value.storage_type).unwrap(), 
             path: value.path.clone(), 
             connection_string: value.connection_string.clone(), 
@@ -32,7 +32,7 @@ impl From<&DbStorage> for Storage {
             table_prefix: value.table_prefix.clone(), 
             created_at: value.created_at, 
             updated_at: value.updated_at, 
-            db_tenant_id: value.db_tenant_id 
+            db_tenant_id: value.db_tenant_id.map(uuid::Uuid::from) 
         }
     }
 }
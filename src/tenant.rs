@@ -1,16 +1,35 @@
+use std::sync::Arc;
+
 use chrono::{Utc, NaiveDateTime};
 
 use crate::{
     error::TenetError,
-    postgresql::database::Pool,
     user::User,
-    postgresql::{dbtenant::DbTenant, dbuser::{DbUser, DbUserMessage},
-    dbapplication::{DbApplication, DbApplicationMessage},
-    dbrole::{DbRole, DbRoleMessage},
-    dbstorage::{DbStorage, DbStorageMessage}},
+    postgresql::dbtenant::DbTenant,
+    context::Context,
+    encryption_modes::EncryptionModes,
+    repository::{Invitee, Repository},
+    session::{self, IssuedSession, Session},
+    role_type::RoleType,
     Application,
+    AuthorizationDecision,
+    DenialReason,
+    Group,
+    Invitation,
+    InvitationRole,
+    IssuedInvitation,
+    IssuedRefreshToken,
+    IssuedUserToken,
+    Mail,
+    MailKind,
+    PasswordHistoryEntry,
+    PasswordPolicy,
+    RefreshToken,
+    RoleDefinition,
     Role,
-    Storage
+    Storage,
+    UserToken,
+    UserTokenPurpose
 };
 
 
@@ -20,30 +39,188 @@ pub struct Tenant {
     pub title: String,
     pub created_at: NaiveDateTime,
     pub updated_at: Option<NaiveDateTime>,
-    #[serde(skip, default = "disconnected_pool")]
-    pub(crate) pool: Pool
-}
-
-fn disconnected_pool() -> Pool {
-    Pool::builder().build_unchecked(diesel::r2d2::ConnectionManager::new(""))
+    #[serde(skip)]
+    pub(crate) context: Option<Arc<Context>>
 }
 
-impl Tenant {
-    pub(crate) fn from_db(value: &DbTenant, pool: Pool) -> Self {
+impl From<&DbTenant> for Tenant {
+    fn from(value: &DbTenant) -> Self {
         Tenant {
-            id: value.id,
+            id: value.id.0,
             title: value.title.clone(),
             created_at: value.created_at,
             updated_at: value.updated_at,
-            pool
+            context: None
+        }
+    }
+}
+
+impl Tenant {
+    pub(crate) fn connect(mut self, context: Arc<Context>) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    fn context(&self) -> Result<&Context, TenetError> {
+        self.context.as_deref().ok_or(TenetError::NotConnectedError)
+    }
+
+    fn repository(&self) -> Result<&dyn Repository, TenetError> {
+        Ok(self.context()?.repository.as_ref())
+    }
+
+    fn role_definitions_for_user(&self, user_id: uuid::Uuid, application_id: uuid::Uuid) -> Result<Vec<RoleDefinition>, TenetError> {
+        let repository = self.repository()?;
+        let role_definition_ids: Vec<uuid::Uuid> = self.get_roles_for_user(user_id)?
+            .iter()
+            .filter(|r| r.applies_to(application_id))
+            .filter_map(|r| r.role_definition_id)
+            .collect();
+        if role_definition_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(repository.find_role_definitions_by_tenant(self.id)?
+            .into_iter()
+            .filter(|d| role_definition_ids.contains(&d.id))
+            .collect())
+    }
+
+    fn create_refresh_token(&self, context: &Context, user_id: uuid::Uuid, family_id: uuid::Uuid) -> Result<IssuedRefreshToken, TenetError> {
+        let now = Utc::now().naive_utc();
+        let token = session::random_token();
+        let refresh_token = RefreshToken {
+            id: uuid::Uuid::new_v4(),
+            family_id,
+            user_id,
+            token_hash: session::hash_token(&token),
+            created_at: now,
+            expires_at: now + context.session_config.refresh_token_lifetime,
+            used_at: None,
+            revoked_at: None,
+            db_tenant_id: Some(self.id)
+        };
+
+        let refresh_token = context.repository.create_refresh_token(&refresh_token)?;
+        Ok(IssuedRefreshToken { token, refresh_token })
+    }
+
+    fn find_refresh_token(&self, context: &Context, token: &str) -> Result<RefreshToken, TenetError> {
+        match context.repository.find_refresh_token_by_token_hash(session::hash_token(token)) {
+            Ok(refresh_token) if refresh_token.db_tenant_id == Some(self.id) => Ok(refresh_token),
+            Ok(_) | Err(TenetError::DatabaseError(diesel::result::Error::NotFound)) => Err(TenetError::InvalidTokenError),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn find_invitation(&self, repository: &dyn Repository, token: &str) -> Result<Invitation, TenetError> {
+        match repository.find_invitation_by_token_hash(session::hash_token(token)) {
+            Ok(invitation) if invitation.db_tenant_id == Some(self.id) => Ok(invitation),
+            Ok(_) | Err(TenetError::DatabaseError(diesel::result::Error::NotFound)) => Err(TenetError::InvalidTokenError),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn create_user_token(&self, context: &Context, user_id: uuid::Uuid, purpose: UserTokenPurpose, lifetime: chrono::Duration) -> Result<IssuedUserToken, TenetError> {
+        let now = Utc::now().naive_utc();
+        let token = session::random_token();
+        let user_token = UserToken {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            purpose,
+            token_hash: session::hash_token(&token),
+            created_at: now,
+            expires_at: now + lifetime,
+            used_at: None,
+            db_tenant_id: Some(self.id)
+        };
+
+        let user_token = context.repository.create_user_token(&user_token)?;
+        Ok(IssuedUserToken { token, user_token })
+    }
+
+    /// Returns the token if it can still be used. Unknown and used tokens and
+    /// tokens of other tenants or purposes are rejected alike, expired ones
+    /// with `TenetError::TokenExpiredError`.
+    fn find_user_token(&self, context: &Context, token: &str, purpose: UserTokenPurpose) -> Result<UserToken, TenetError> {
+        let user_token = match context.repository.find_user_token_by_token_hash(session::hash_token(token)) {
+            Ok(user_token) if user_token.db_tenant_id == Some(self.id) && user_token.purpose == purpose => user_token,
+            Ok(_) | Err(TenetError::DatabaseError(diesel::result::Error::NotFound)) => return Err(TenetError::InvalidTokenError),
+            Err(e) => return Err(e)
+        };
+        if user_token.is_used() {
+            return Err(TenetError::InvalidTokenError);
+        }
+        if user_token.is_expired() {
+            return Err(TenetError::TokenExpiredError);
         }
+        Ok(user_token)
+    }
+
+    /// Marks a token returned by [`Tenant::find_user_token`] as used, unless
+    /// it was used concurrently.
+    fn mark_user_token_used(&self, context: &Context, user_token: &UserToken) -> Result<(), TenetError> {
+        if !context.repository.use_user_token(user_token.id, Utc::now().naive_utc())? {
+            return Err(TenetError::InvalidTokenError);
+        }
+        Ok(())
+    }
+
+    /// Marks the token as used, see [`Tenant::find_user_token`].
+    fn use_user_token(&self, context: &Context, token: &str, purpose: UserTokenPurpose) -> Result<UserToken, TenetError> {
+        let user_token = self.find_user_token(context, token, purpose)?;
+        self.mark_user_token_used(context, &user_token)?;
+        Ok(user_token)
+    }
+
+    /// Hashes the new password with Argon2 and stores it, which also moves
+    /// users with a legacy hash off their old scheme. The previous password is added to the user's password history, which
+    /// keeps as many entries as the longest history of the user's tenants
+    /// needs. Check the password with `Context::check_new_password` first.
+    fn store_password(&self, context: &Context, user: User, new_password: &str) -> Result<User, TenetError> {
+        let mut history_limit = 0;
+        for tenant in context.repository.find_tenants_by_user(user.id)? {
+            // The current password counts towards the history length
+            history_limit = history_limit.max(context.password_policy(tenant.id)?.history_length.saturating_sub(1));
+        }
+
+        let entry = PasswordHistoryEntry::new(&user);
+        let password = EncryptionModes::Argon2.hash_password_with(new_password, &context.password_hash_config)?;
+        let user = User { password, encryption_mode: EncryptionModes::Argon2, updated_at: Some(Utc::now().naive_utc()), ..user };
+        context.repository.update_user_password(&user, &entry, history_limit)
+    }
+
+    /// Stores a new password, then revokes the user's sessions and refresh
+    /// tokens in all of their tenants.
+    fn replace_password(&self, context: &Context, user: User, new_password: &str) -> Result<User, TenetError> {
+        let user = self.store_password(context, user, new_password)?;
+
+        for tenant in context.repository.find_tenants_by_user(user.id)? {
+            let tenant = Tenant { context: self.context.clone(), ..tenant };
+            tenant.revoke_sessions_for_user(user.id)?;
+            tenant.revoke_refresh_tokens_for_user(user.id)?;
+        }
+        Ok(user)
+    }
+
+    /// Mails the token if the instance has a mailer.
+    fn send_mail(&self, context: &Context, kind: MailKind, to: &str, token: &str) -> Result<(), TenetError> {
+        let Some(mailer) = &context.mailer else {
+            return Ok(());
+        };
+        mailer.send(&self.render_mail(context, kind, to, token))
+    }
+
+    fn render_mail(&self, context: &Context, kind: MailKind, to: &str, token: &str) -> Mail {
+        let template = context.mail_templates.get(self.id, kind);
+        template.render(to, &[("token", token), ("email", to), ("tenant", &self.title)])
     }
 }
 
 impl Tenant {
     /// Creates a transient, unsaved `Tenant` value not yet associated with any database.
     ///
-    /// This value is not connected to a database, so calling any of `Tenant`'s
+    /// This value is not connected to a repository, so calling any of `Tenant`'s
     /// methods that read or write data will fail. Obtain a usable `Tenant` from
     /// `Tenet::create_tenant`, `Tenet::get_tenant_by_id`, or similar lookup methods.
     pub fn new(title: String) -> Self {
@@ -52,166 +229,700 @@ impl Tenant {
             title,
             created_at: Utc::now().naive_utc(),
             updated_at: None,
-            pool: disconnected_pool()
+            context: None
         }
     }
 
     /* Users */
     pub fn get_users(&self) -> Vec<User> {
-        if let Ok(db_users) = DbUser::find_by_tenant(&self.pool, self.id) {
-            return db_users.iter().map(User::from).collect();
+        if let Ok(users) = self.repository().and_then(|r| r.find_users_by_tenant(self.id)) {
+            return users;
         }
         Vec::new()
     }
 
     pub fn get_user_ids(&self) -> Vec<uuid::Uuid> {
-        if let Ok(users) = DbUser::find_by_tenant(&self.pool, self.id) {
-            return users.iter().map(|u| u.id).collect();
-        }
-        Vec::new()
+        self.get_users().iter().map(|u| u.id).collect()
     }
 
+    /// Hashes the password and stores the user. Fails with
+    /// `TenetError::PasswordPolicyError` if the password breaks the tenant's
+    /// password policy or is in the breach corpus.
     pub fn add_user(&self, user: &User) -> Result<User, TenetError> {
-        let user_message = DbUserMessage {
-            email: user.email.clone(),
-            email_verified: user.email_verified,
-            password: user.password.clone(),
-            encryption_mode: user.encryption_mode.to_string(),
-            full_name: user.full_name.clone(),
-            db_tenant_id: user.db_tenant_id
-        };
-        let created_user = DbUser::create(&self.pool, user_message)?;
+        let context = self.context()?;
+        context.check_password(self.id, &user.password, user)?;
+        let mut new_user = user.clone();
+        new_user.password = user.encryption_mode.hash_password_with(&user.password, &context.password_hash_config)?;
+
+        context.repository.create_user(&new_user)
+    }
 
-        Ok(User::from(&created_user))
+    /// Stores a user imported from a legacy system. `user.password` must
+    /// already be a hash in the format of `user.encryption_mode`; it is stored
+    /// as it is, without checking the password policy. Imported users sign in
+    /// with their old password and are re-hashed with Argon2 on their first
+    /// login.
+    pub fn import_user(&self, user: &User) -> Result<User, TenetError> {
+        self.repository()?.create_user(user)
     }
 
     pub fn get_user_by_id(&self, user_id: uuid::Uuid) -> Result<User, TenetError> {
-        let user = DbUser::find(&self.pool, self.id, user_id)?;
-        Ok(User::from(&user))
+        self.repository()?.find_user(self.id, user_id)
+    }
+
+    /// Stores the user's profile, e.g. email and full name. The password and
+    /// encryption mode stay as stored, set new passwords with
+    /// [`Tenant::change_password`] or [`Tenant::force_password_reset`].
+    pub fn update_user(&self, user: &User) -> Result<User, TenetError> {
+        let repository = self.repository()?;
+        let stored_user = repository.find_user(self.id, user.id)?;
+        repository.update_user(&User { password: stored_user.password, encryption_mode: stored_user.encryption_mode, ..user.clone() })
     }
 
+    /// Deletes the user. A user that is also a member of other tenants is
+    /// only removed from this one, see [`Tenant::remove_member`].
     pub fn delete_user(&self, user_id: uuid::Uuid) -> Result<(), TenetError> {
-        DbUser::delete(&self.pool, user_id)?;
-        Ok(())
+        let repository = self.repository()?;
+        if repository.find_tenants_by_user(user_id)?.iter().any(|t| t.id != self.id) {
+            return self.remove_member(user_id);
+        }
+        repository.delete_user(user_id)
+    }
+
+    /// Makes an existing user, e.g. one created in another tenant, a member of this tenant.
+    pub fn add_member(&self, user_id: uuid::Uuid) -> Result<User, TenetError> {
+        let repository = self.repository()?;
+        repository.create_membership(user_id, self.id)?;
+        repository.find_user(self.id, user_id)
+    }
+
+    /// Removes the user from this tenant together with their roles, group
+    /// memberships, sessions and refresh tokens in it. The user itself and
+    /// their memberships in other tenants are kept.
+    pub fn remove_member(&self, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        repository.find_user(self.id, user_id)?;
+        repository.delete_membership(user_id, self.id)
     }
 
     pub fn contains_username(&self, username: String) -> bool {
-        DbUser::find_by_tenant_and_email(&self.pool, self.id, username).is_ok()
+        self.repository().and_then(|r| r.find_user_by_tenant_and_email(self.id, username)).is_ok()
     }
 
     pub fn set_user_verified(&self, user_id: uuid::Uuid) -> bool {
-        if let Ok(user) = DbUser::find(&self.pool, self.id, user_id) {
-            let user_message = DbUserMessage::from(user);
-            return DbUser::update(&self.pool, user_id, user_message).is_ok();
+        if let Ok(repository) = self.repository()
+            && let Ok(user) = repository.find_user(self.id, user_id) {
+            return repository.update_user(&User { email_verified: true, ..user }).is_ok();
         }
         false
     }
 
+    /// Returns the user if the password is correct. Passwords stored with an
+    /// outdated [`crate::encryption_modes::PasswordHashConfig`] or a legacy encryption mode are
+    /// re-hashed on the way.
     pub fn authenticate_user(&self, username: String, password: String) -> Option<User> {
-        if let Ok(user) = DbUser::find_by_tenant_and_email(&self.pool, self.id, username) {
-            if let Ok(verified) = user.verify_password(&password) {
-                if verified {
-                    return Some(User::from(&user))
-                }
-            }
+        let context = self.context().ok()?;
+        let Ok(user) = context.repository.find_user_by_tenant_and_email(self.id, username) else {
+            context.verify_dummy_password(&password);
+            return None;
+        };
+        if let Ok(true) = user.verify_password(&password) {
+            return Some(context.rehash_password(user, &password))
         }
         None
     }
 
+    /// Sets a new password after checking the current one. Fails with
+    /// `TenetError::InvalidPasswordError` if `old_password` is wrong. Existing
+    /// sessions stay valid.
+    pub fn change_password(&self, user_id: uuid::Uuid, old_password: String, new_password: String) -> Result<User, TenetError> {
+        let context = self.context()?;
+        let user = context.repository.find_user(self.id, user_id)?;
+        if !user.verify_password(&old_password)? {
+            return Err(TenetError::InvalidPasswordError);
+        }
+        context.check_new_password(self.id, &new_password, &user)?;
+        self.store_password(context, user, &new_password)
+    }
+
+    /// Sets a new password without the current one, e.g. by an administrator,
+    /// and revokes the user's sessions and refresh tokens in all of their tenants.
+    pub fn force_password_reset(&self, user_id: uuid::Uuid, new_password: String) -> Result<User, TenetError> {
+        let context = self.context()?;
+        let user = context.repository.find_user(self.id, user_id)?;
+        context.check_new_password(self.id, &new_password, &user)?;
+        self.replace_password(context, user, &new_password)
+    }
+
+    /* Password policy */
+    /// Returns the policy enforced for this tenant: its own one if set,
+    /// otherwise the instance wide one, see [`crate::Tenet::with_password_policy`].
+    pub fn get_password_policy(&self) -> Result<PasswordPolicy, TenetError> {
+        self.context()?.password_policy(self.id)
+    }
+
+    /// Stores a password policy for this tenant, replacing the instance wide
+    /// one for it. It is removed together with the tenant.
+    pub fn set_password_policy(&self, password_policy: &PasswordPolicy) -> Result<PasswordPolicy, TenetError> {
+        self.repository()?.save_password_policy(self.id, password_policy)
+    }
+
+    /// Removes the tenant's own policy, so that the instance wide one applies again.
+    pub fn remove_password_policy(&self) -> Result<(), TenetError> {
+        self.repository()?.delete_password_policy(self.id)
+    }
+
     /* Applications */
     pub fn get_applications(&self) -> Vec<Application> {
-        if let Ok(applications) = DbApplication::find_by_tenant(&self.pool, self.id) {
-            return applications.iter().map(Application::from).collect();
+        if let Ok(applications) = self.repository().and_then(|r| r.find_applications_by_tenant(self.id)) {
+            return applications;
         }
         Vec::new()
     }
 
     pub fn get_application_by_id(&self, application_id: uuid::Uuid) -> Result<Application, TenetError> {
-        let application = DbApplication::find(&self.pool, self.id, application_id)?;
-        Ok(Application::from(&application))
+        self.repository()?.find_application(self.id, application_id)
     }
 
     pub fn add_application(&self, application: &Application) -> Result<Application, TenetError> {
-        let application_message = DbApplicationMessage {
-            application_type: application.application_type.to_string(),
-            storage_id: application.storage_id,
-            db_tenant_id: application.db_tenant_id
-        };
-        let created_application = DbApplication::create(&self.pool, application_message)?;
-
-        Ok(Application::from(&created_application))
+        self.repository()?.create_application(application)
     }
 
     pub fn delete_application(&self, application_id: uuid::Uuid) -> Result<(), TenetError> {
-        DbApplication::delete(&self.pool, application_id)?;
-        Ok(())
+        self.repository()?.delete_application(application_id)
     }
 
     /* Storage */
     pub fn get_storages(&self) -> Vec<Storage> {
-        if let Ok(storages) = DbStorage::find_by_tenant(&self.pool, self.id) {
-            return storages.iter().map(Storage::from).collect();
+        if let Ok(storages) = self.repository().and_then(|r| r.find_storages_by_tenant(self.id)) {
+            return storages;
         }
         Vec::new()
     }
 
     pub fn get_storage_by_id(&self, storage_id: uuid::Uuid) -> Result<Storage, TenetError> {
-        let storage = DbStorage::find(&self.pool, self.id, storage_id)?;
-        Ok(Storage::from(&storage))
+        self.repository()?.find_storage(self.id, storage_id)
     }
 
     pub fn add_storage(&self, storage: &Storage) -> Result<Storage, TenetError> {
-        let storage_message = DbStorageMessage {
-            storage_type: storage.storage_type.to_string(),
-            path: storage.path.clone(),
-            connection_string: storage.connection_string.clone(),
-            schema: storage.schema.clone(),
-            table_prefix: storage.table_prefix.clone(),
-            db_tenant_id: storage.db_tenant_id
-        };
-        let created_storage = DbStorage::create(&self.pool, storage_message)?;
-
-        Ok(Storage::from(&created_storage))
+        self.repository()?.create_storage(storage)
     }
 
     pub fn delete_storage(&self, storage_id: uuid::Uuid) -> Result<(), TenetError> {
-        DbStorage::delete(&self.pool, storage_id)?;
-        Ok(())
+        self.repository()?.delete_storage(storage_id)
     }
 
     /* Roles */
     pub fn get_roles(&self) -> Vec<Role> {
-        if let Ok(roles) = DbRole::find_by_tenant(&self.pool, self.id) {
-            return roles.iter().map(Role::from).collect();
+        if let Ok(roles) = self.repository().and_then(|r| r.find_roles_by_tenant(self.id)) {
+            return roles;
         }
         Vec::new()
     }
 
     pub fn get_role_by_id(&self, role_id: uuid::Uuid) -> Result<Role, TenetError> {
-        let role = DbRole::find(&self.pool, self.id, role_id)?;
-        Ok(Role::from(&role))
+        self.repository()?.find_role(self.id, role_id)
     }
 
+    /// Assigns a role. Roles without a definition get the tenant's built-in
+    /// definition named after their `role_type`.
+    ///
+    /// The user, group and application of the role must belong to this tenant.
     pub fn add_role(&self, role: &Role) -> Result<Role, TenetError> {
-        let role_message = DbRoleMessage {
-            role_type: role.role_type.to_string(),
-            user_id: role.user_id,
-            application_id: role.application_id,
-            db_tenant_id: role.db_tenant_id
+        let repository = self.repository()?;
+        if let Some(user_id) = role.user_id {
+            repository.find_user(self.id, user_id)?;
+        }
+        if let Some(group_id) = role.group_id {
+            repository.find_group(self.id, group_id)?;
+        }
+        if let Some(application_id) = role.application_id {
+            repository.find_application(self.id, application_id)?;
+        }
+        let role_definition = match role.role_definition_id {
+            Some(role_definition_id) => repository.find_role_definition(self.id, role_definition_id)?,
+            None => repository.find_role_definition_by_name(self.id, role.role_type.to_string())?
         };
-        let created_role = DbRole::create(&self.pool, role_message)?;
 
-        Ok(Role::from(&created_role))
+        repository.create_role(&Role {
+            role_type: role_definition.role_type,
+            role_definition_id: Some(role_definition.id),
+            ..*role
+        })
     }
 
     pub fn delete_role(&self, role_id: uuid::Uuid) -> Result<(), TenetError> {
-        DbRole::delete(&self.pool, role_id)?;
-        Ok(())
+        self.repository()?.delete_role(role_id)
     }
 
+    /// Returns the roles assigned to the user directly and the roles inherited
+    /// from the user's groups.
     pub fn get_roles_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Role>, TenetError> {
-        let user_roles = DbRole::find_by_user(&self.pool, self.id, user_id)?;
+        let repository = self.repository()?;
+        let mut roles = repository.find_roles_by_user(self.id, user_id)?;
+        roles.extend(repository.find_inherited_roles_by_user(self.id, user_id)?);
+        Ok(roles)
+    }
+
+    /// Returns only the roles assigned to the user directly, without the ones
+    /// inherited from groups.
+    pub fn get_direct_roles_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Role>, TenetError> {
+        self.repository()?.find_roles_by_user(self.id, user_id)
+    }
+
+    pub fn get_tenant_wide_roles_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Role>, TenetError> {
+        let roles = self.get_roles_for_user(user_id)?;
+        Ok(roles.into_iter().filter(Role::is_tenant_wide).collect())
+    }
+
+    /// Returns the user's roles for the application together with the user's
+    /// tenant-wide roles, which apply to every application.
+    pub fn get_roles_for_user_in_application(&self, user_id: uuid::Uuid, application_id: uuid::Uuid) -> Result<Vec<Role>, TenetError> {
+        let roles = self.get_roles_for_user(user_id)?;
+        Ok(roles.into_iter().filter(|r| r.applies_to(application_id)).collect())
+    }
+
+    pub fn get_roles_for_group(&self, group_id: uuid::Uuid) -> Result<Vec<Role>, TenetError> {
+        self.repository()?.find_roles_by_group(self.id, group_id)
+    }
+
+    /* Role definitions */
+    pub fn get_role_definitions(&self) -> Vec<RoleDefinition> {
+        if let Ok(role_definitions) = self.repository().and_then(|r| r.find_role_definitions_by_tenant(self.id)) {
+            return role_definitions;
+        }
+        Vec::new()
+    }
+
+    pub fn get_role_definition_by_id(&self, role_definition_id: uuid::Uuid) -> Result<RoleDefinition, TenetError> {
+        self.repository()?.find_role_definition(self.id, role_definition_id)
+    }
+
+    pub fn add_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError> {
+        self.repository()?.create_role_definition(role_definition)
+    }
+
+    pub fn update_role_definition(&self, role_definition: &RoleDefinition) -> Result<RoleDefinition, TenetError> {
+        let repository = self.repository()?;
+        if repository.find_role_definition(self.id, role_definition.id)?.is_builtin() {
+            return Err(TenetError::BuiltinRoleDefinitionError);
+        }
+        repository.update_role_definition(role_definition)
+    }
+
+    pub fn delete_role_definition(&self, role_definition_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        if repository.find_role_definition(self.id, role_definition_id)?.is_builtin() {
+            return Err(TenetError::BuiltinRoleDefinitionError);
+        }
+        repository.delete_role_definition(role_definition_id)
+    }
+
+    /* Groups */
+    pub fn get_groups(&self) -> Vec<Group> {
+        if let Ok(groups) = self.repository().and_then(|r| r.find_groups_by_tenant(self.id)) {
+            return groups;
+        }
+        Vec::new()
+    }
+
+    pub fn get_group_by_id(&self, group_id: uuid::Uuid) -> Result<Group, TenetError> {
+        self.repository()?.find_group(self.id, group_id)
+    }
+
+    pub fn add_group(&self, group: &Group) -> Result<Group, TenetError> {
+        self.repository()?.create_group(group)
+    }
+
+    pub fn update_group(&self, group: &Group) -> Result<Group, TenetError> {
+        let repository = self.repository()?;
+        repository.find_group(self.id, group.id)?;
+        repository.update_group(group)
+    }
+
+    /// Deletes the group together with its memberships and the roles assigned to it.
+    pub fn delete_group(&self, group_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        repository.find_group(self.id, group_id)?;
+        repository.delete_group(group_id)
+    }
+
+    pub fn get_group_members(&self, group_id: uuid::Uuid) -> Result<Vec<User>, TenetError> {
+        let repository = self.repository()?;
+        repository.find_group(self.id, group_id)?;
+        repository.find_group_members(self.id, group_id)
+    }
+
+    pub fn get_groups_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Group>, TenetError> {
+        self.repository()?.find_groups_by_user(self.id, user_id)
+    }
+
+    pub fn add_user_to_group(&self, group_id: uuid::Uuid, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        repository.find_group(self.id, group_id)?;
+        repository.find_user(self.id, user_id)?;
+        repository.add_group_member(group_id, user_id)
+    }
+
+    pub fn remove_user_from_group(&self, group_id: uuid::Uuid, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        repository.find_group(self.id, group_id)?;
+        repository.remove_group_member(group_id, user_id)
+    }
+
+    /* Authorization */
+    /// Returns the highest role the user holds for the application, if any.
+    ///
+    /// Application roles and tenant-wide roles, direct and inherited from
+    /// groups, are ranked together by role type. On a tie the application role
+    /// wins over the tenant-wide one and the direct role over the inherited one,
+    /// being the more specific.
+    pub fn effective_role(&self, user_id: uuid::Uuid, application_id: uuid::Uuid) -> Result<Option<Role>, TenetError> {
+        let roles = self.get_roles_for_user_in_application(user_id, application_id)?;
+        Ok(roles.into_iter().max_by_key(|r| (r.role_type, !r.is_tenant_wide(), !r.is_inherited())))
+    }
+
+    /// Decides whether the user holds `required` or a higher role for the application.
+    ///
+    /// Unknown users and applications are denied rather than reported as errors,
+    /// so only repository failures end up in `Err`.
+    pub fn is_authorized(&self, user_id: uuid::Uuid, application_id: uuid::Uuid, required: RoleType) -> Result<AuthorizationDecision, TenetError> {
+        let repository = self.repository()?;
+        if let Err(e) = repository.find_user(self.id, user_id) {
+            return if e.is_not_found() { Ok(AuthorizationDecision::Denied { reason: DenialReason::UnknownUser }) } else { Err(e) };
+        }
+        if let Err(e) = repository.find_application(self.id, application_id) {
+            return if e.is_not_found() { Ok(AuthorizationDecision::Denied { reason: DenialReason::UnknownApplication }) } else { Err(e) };
+        }
+
+        Ok(match self.effective_role(user_id, application_id)? {
+            Some(role) if role.role_type >= required => AuthorizationDecision::Granted { role },
+            Some(role) => AuthorizationDecision::Denied { reason: DenialReason::InsufficientRole { effective: role.role_type, required } },
+            None => AuthorizationDecision::Denied { reason: DenialReason::NoRole }
+        })
+    }
+
+    /// Returns the permissions the user's application and tenant-wide roles
+    /// grant in the application, sorted and without duplicates.
+    pub fn get_permissions_for_user(&self, user_id: uuid::Uuid, application_id: uuid::Uuid) -> Result<Vec<String>, TenetError> {
+        let mut permissions: Vec<String> = self.role_definitions_for_user(user_id, application_id)?
+            .into_iter()
+            .flat_map(|d| d.permissions)
+            .collect();
+        permissions.sort();
+        permissions.dedup();
+        Ok(permissions)
+    }
+
+    /// Whether one of the user's roles in the application grants `permission`,
+    /// see [`RoleDefinition::grants`].
+    pub fn has_permission(&self, user_id: uuid::Uuid, application_id: uuid::Uuid, permission: &str) -> Result<bool, TenetError> {
+        Ok(self.role_definitions_for_user(user_id, application_id)?
+            .iter()
+            .any(|d| d.grants(permission)))
+    }
+
+    /* Sessions */
+    pub fn create_session(&self, user_id: uuid::Uuid) -> Result<IssuedSession, TenetError> {
+        let context = self.context()?;
+        context.repository.find_user(self.id, user_id)?;
+
+        let now = Utc::now().naive_utc();
+        let id = uuid::Uuid::new_v4();
+        let token = context.session_config.issue_token(id);
+        let session = Session {
+            id,
+            user_id,
+            token_hash: session::hash_token(&token),
+            created_at: now,
+            expires_at: context.session_config.expiry(now, now),
+            last_used_at: now,
+            revoked_at: None,
+            db_tenant_id: Some(self.id)
+        };
+
+        let session = context.repository.create_session(&session)?;
+        Ok(IssuedSession { token, session })
+    }
+
+    pub fn start_session(&self, username: String, password: String) -> Option<IssuedSession> {
+        let user = self.authenticate_user(username, password)?;
+        self.create_session(user.id).ok()
+    }
+
+    pub fn validate_session(&self, token: &str) -> Result<Session, TenetError> {
+        let context = self.context()?;
+        context.session_config.check_token(token)?;
+
+        let mut session = match context.repository.find_session_by_token_hash(session::hash_token(token)) {
+            Ok(session) if session.db_tenant_id == Some(self.id) => session,
+            Ok(_) | Err(TenetError::DatabaseError(diesel::result::Error::NotFound)) => return Err(TenetError::InvalidTokenError),
+            Err(e) => return Err(e)
+        };
+        if session.is_revoked() {
+            return Err(TenetError::SessionRevokedError);
+        }
+        if session.is_expired() {
+            return Err(TenetError::SessionExpiredError);
+        }
+
+        let now = Utc::now().naive_utc();
+        session.last_used_at = now;
+        if context.session_config.sliding {
+            session.expires_at = context.session_config.expiry(session.created_at, now);
+        }
+        context.repository.update_session(&session)
+    }
+
+    pub fn get_sessions_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<Session>, TenetError> {
+        self.repository()?.find_sessions_by_user(self.id, user_id)
+    }
+
+    pub fn revoke_session(&self, session_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        let session = repository.find_session(self.id, session_id)?;
+        if !session.is_revoked() {
+            repository.update_session(&Session { revoked_at: Some(Utc::now().naive_utc()), ..session })?;
+        }
+        Ok(())
+    }
+
+    pub fn revoke_sessions_for_user(&self, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        let now = Utc::now().naive_utc();
+        for session in repository.find_sessions_by_user(self.id, user_id)? {
+            if !session.is_revoked() {
+                repository.update_session(&Session { revoked_at: Some(now), ..session })?;
+            }
+        }
+        Ok(())
+    }
+
+    /* Refresh tokens */
+    pub fn issue_refresh_token(&self, user_id: uuid::Uuid) -> Result<IssuedRefreshToken, TenetError> {
+        let context = self.context()?;
+        context.repository.find_user(self.id, user_id)?;
+        self.create_refresh_token(context, user_id, uuid::Uuid::new_v4())
+    }
+
+    /// Exchanges a refresh token for its successor in the same family.
+    ///
+    /// Each token can be used once. Presenting a used token again revokes the
+    /// whole family, so a stolen token stops working for thief and owner alike.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError::RefreshTokenReusedError` on reuse, and a
+    /// `TenetError::SessionRevokedError` or `TenetError::SessionExpiredError`
+    /// for revoked or expired tokens.
+    pub fn refresh(&self, token: &str) -> Result<IssuedRefreshToken, TenetError> {
+        let context = self.context()?;
+        let refresh_token = self.find_refresh_token(context, token)?;
+        if refresh_token.is_revoked() {
+            return Err(TenetError::SessionRevokedError);
+        }
+
+        let now = Utc::now().naive_utc();
+        if !refresh_token.is_used() && refresh_token.is_expired() {
+            return Err(TenetError::SessionExpiredError);
+        }
+        if refresh_token.is_used() || !context.repository.use_refresh_token(refresh_token.id, now)? {
+            context.repository.revoke_refresh_token_family(refresh_token.family_id, now)?;
+            return Err(TenetError::RefreshTokenReusedError);
+        }
+
+        self.create_refresh_token(context, refresh_token.user_id, refresh_token.family_id)
+    }
+
+    pub fn get_refresh_tokens_for_user(&self, user_id: uuid::Uuid) -> Result<Vec<RefreshToken>, TenetError> {
+        self.repository()?.find_refresh_tokens_by_user(self.id, user_id)
+    }
+
+    /// Revokes the family of the given refresh token, e.g. on logout.
+    pub fn revoke_refresh_token(&self, token: &str) -> Result<(), TenetError> {
+        let context = self.context()?;
+        let refresh_token = self.find_refresh_token(context, token)?;
+        context.repository.revoke_refresh_token_family(refresh_token.family_id, Utc::now().naive_utc())
+    }
+
+    pub fn revoke_refresh_tokens_for_user(&self, user_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        let now = Utc::now().naive_utc();
+        let mut family_ids: Vec<uuid::Uuid> = repository.find_refresh_tokens_by_user(self.id, user_id)?
+            .iter()
+            .filter(|t| !t.is_revoked())
+            .map(|t| t.family_id)
+            .collect();
+        family_ids.sort();
+        family_ids.dedup();
+        for family_id in family_ids {
+            repository.revoke_refresh_token_family(family_id, now)?;
+        }
+        Ok(())
+    }
+
+    /* Invitations */
+    /// Invites a person by email and mails them the token if the
+    /// [`crate::Tenet`] instance has a mailer. The invitation assigns `roles`
+    /// to the user accepting it with [`Tenant::accept_invitation`] and expires
+    /// after `TokenConfig::invitation_lifetime`.
+    pub fn invite_user(&self, email: String, roles: &[InvitationRole]) -> Result<IssuedInvitation, TenetError> {
+        let context = self.context()?;
+        let repository = context.repository.as_ref();
+        for role in roles {
+            repository.find_role_definition(self.id, role.role_definition_id)?;
+            if let Some(application_id) = role.application_id {
+                repository.find_application(self.id, application_id)?;
+            }
+        }
+
+        let now = Utc::now().naive_utc();
+        let token = session::random_token();
+        let invitation = Invitation {
+            id: uuid::Uuid::new_v4(),
+            email,
+            roles: roles.to_vec(),
+            token_hash: session::hash_token(&token),
+            created_at: now,
+            expires_at: now + context.token_config.invitation_lifetime,
+            accepted_at: None,
+            revoked_at: None,
+            db_tenant_id: Some(self.id)
+        };
+
+        let invitation = repository.create_invitation(&invitation)?;
+        self.send_mail(context, MailKind::Invitation, &invitation.email, &token)?;
+        Ok(IssuedInvitation { token, invitation })
+    }
+
+    /// Creates the invited user with the given password and full name and
+    /// assigns the invitation's roles. The email counts as verified, since the
+    /// token was sent to it.
+    ///
+    /// If a user with the email already exists, e.g. in another tenant,
+    /// `password` must be their current one and `full_name` is ignored. They
+    /// become a member of this tenant and get the invitation's roles here.
+    ///
+    /// Each invitation can be accepted once, and either all of this happens or
+    /// nothing. Fails with `TenetError::InvitationRevokedError` or
+    /// `TenetError::InvitationExpiredError` if the invitation is no longer
+    /// valid.
+    pub fn accept_invitation(&self, token: &str, password: String, full_name: String) -> Result<User, TenetError> {
+        let context = self.context()?;
+        let repository = context.repository.as_ref();
+        let invitation = self.find_invitation(repository, token)?;
+        if invitation.is_revoked() {
+            return Err(TenetError::InvitationRevokedError);
+        }
+        if invitation.is_accepted() {
+            return Err(TenetError::InvalidTokenError);
+        }
+        if invitation.is_expired() {
+            return Err(TenetError::InvitationExpiredError);
+        }
+
+        let accepted_at = Utc::now().naive_utc();
+        let user = match repository.find_user_by_email(invitation.email.clone()) {
+            Ok(user) => {
+                if !user.verify_password(&password)? {
+                    return Err(TenetError::InvalidPasswordError);
+                }
+                repository.accept_invitation(&invitation, Invitee::Existing(user.id), accepted_at)?
+            },
+            Err(e) if e.is_not_found() => {
+                let mut new_user = User::new(invitation.email.clone(), full_name, password, EncryptionModes::Argon2, invitation.email.clone(), true, self.id);
+                context.check_password(self.id, &new_user.password, &new_user)?;
+                new_user.password = new_user.encryption_mode.hash_password_with(&new_user.password, &context.password_hash_config)?;
+                repository.accept_invitation(&invitation, Invitee::New(&new_user), accepted_at)?
+            },
+            Err(e) => return Err(e)
+        };
+        // Accepted or revoked concurrently
+        user.ok_or(TenetError::InvalidTokenError)
+    }
+
+    /// Returns the invitations that are neither accepted, revoked nor expired.
+    pub fn get_pending_invitations(&self) -> Result<Vec<Invitation>, TenetError> {
+        let invitations = self.repository()?.find_invitations_by_tenant(self.id)?;
+        Ok(invitations.into_iter().filter(Invitation::is_pending).collect())
+    }
+
+    pub fn get_invitation_by_id(&self, invitation_id: uuid::Uuid) -> Result<Invitation, TenetError> {
+        self.repository()?.find_invitation(self.id, invitation_id)
+    }
+
+    pub fn revoke_invitation(&self, invitation_id: uuid::Uuid) -> Result<(), TenetError> {
+        let repository = self.repository()?;
+        repository.find_invitation(self.id, invitation_id)?;
+        repository.revoke_invitation(invitation_id, Utc::now().naive_utc())
+    }
+
+    /* Email verification */
+    /// Issues a single use token confirming the user's email address and
+    /// mails it to the user if the [`crate::Tenet`] instance has a mailer.
+    /// The token expires after `TokenConfig::email_verification_lifetime`.
+    pub fn start_email_verification(&self, user_id: uuid::Uuid) -> Result<IssuedUserToken, TenetError> {
+        let context = self.context()?;
+        let user = context.repository.find_user(self.id, user_id)?;
+        let issued = self.create_user_token(context, user.id, UserTokenPurpose::EmailVerification, context.token_config.email_verification_lifetime)?;
+        self.send_mail(context, MailKind::EmailVerification, &user.email, &issued.token)?;
+        Ok(issued)
+    }
+
+    /// Marks the email address of the token's user as verified.
+    pub fn confirm_email(&self, token: &str) -> Result<User, TenetError> {
+        let context = self.context()?;
+        let user_token = self.use_user_token(context, token, UserTokenPurpose::EmailVerification)?;
+        let user = context.repository.find_user(self.id, user_token.user_id)?;
+        context.repository.update_user(&User { email_verified: true, ..user })
+    }
+
+    /* Password reset */
+    /// Issues a single use token for resetting the password of the member with
+    /// the given email and mails it to them. The token expires after
+    /// `TokenConfig::password_reset_lifetime`.
+    ///
+    /// Unknown emails are not an error, so that the result does not reveal
+    /// whether an account exists. For the same reason the token is never
+    /// returned and the mail is sent in the background by a bounded queue;
+    /// failures to send it are only logged. Fails with
+    /// `TenetError::NoMailerError` if the [`crate::Tenet`] instance has no
+    /// mailer.
+    pub fn request_password_reset(&self, email: String) -> Result<(), TenetError> {
+        let context = self.context()?;
+        let mail_queue = context.mail_queue.as_ref().ok_or(TenetError::NoMailerError)?;
+        let user = match context.repository.find_user_by_tenant_and_email(self.id, email) {
+            Ok(user) => user,
+            Err(e) if e.is_not_found() => {
+                // Comparable work to issuing a token, so that the timing gives nothing away either
+                let _ = context.repository.find_user_token_by_token_hash(session::hash_token(&session::random_token()));
+                return Ok(());
+            },
+            Err(e) => return Err(e)
+        };
+        let issued = self.create_user_token(context, user.id, UserTokenPurpose::PasswordReset, context.token_config.password_reset_lifetime)?;
+        mail_queue.send(self.render_mail(context, MailKind::PasswordReset, &user.email, &issued.token));
+        Ok(())
+    }
+
+    /// Sets a new password for the token's user and revokes all of their
+    /// sessions and refresh tokens. If the password breaks the tenant's
+    /// password policy, the token stays usable.
+    pub fn reset_password(&self, token: &str, new_password: String) -> Result<User, TenetError> {
+        let context = self.context()?;
+        let user_token = self.find_user_token(context, token, UserTokenPurpose::PasswordReset)?;
+        let user = context.repository.find_user(self.id, user_token.user_id)?;
+        // A password breaking the policy must not use up the token
+        context.check_new_password(self.id, &new_password, &user)?;
+        self.mark_user_token_used(context, &user_token)?;
+        self.replace_password(context, user, &new_password)
+    }
 
-        Ok(user_roles.iter().map(Role::from).collect())
+    /* Access tokens */
+    #[cfg(feature = "jwt")]
+    pub fn issue_access_token(&self, user_id: uuid::Uuid, issuer: &crate::jwt::JwtIssuer) -> Result<String, TenetError> {
+        self.repository()?.find_user(self.id, user_id)?;
+        let roles = self.get_roles_for_user(user_id)?;
+        issuer.issue(user_id, self.id, &roles)
     }
 }
@@ -0,0 +1,37 @@
+use crate::{IssuedSession, Tenant, TenetError, User};
+
+
+/// Outcome of [`crate::Tenet::login`]: an authenticated user together with
+/// the tenants they are a member of.
+///
+/// Users belonging to more than one tenant pick the one to continue with,
+/// which [`TenantSelection::select`] then starts a session in.
+#[derive(Debug, Clone)]
+pub struct TenantSelection {
+    pub user: User,
+    /// Ordered by title
+    pub tenants: Vec<Tenant>
+}
+
+impl TenantSelection {
+    /// Returns the only tenant, if the user is a member of exactly one and
+    /// there is nothing to choose.
+    pub fn single(&self) -> Option<&Tenant> {
+        match self.tenants.as_slice() {
+            [tenant] => Some(tenant),
+            _ => None
+        }
+    }
+
+    /// Starts a session for the user in the chosen tenant.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TenetError::NotFoundError` if the user is not a member of the tenant.
+    pub fn select(&self, tenant_id: uuid::Uuid) -> Result<IssuedSession, TenetError> {
+        let tenant = self.tenants.iter()
+            .find(|t| t.id == tenant_id)
+            .ok_or(TenetError::NotFoundError)?;
+        tenant.create_session(self.user.id)
+    }
+}
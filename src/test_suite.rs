@@ -0,0 +1,966 @@
+//! Tests shared by every repository backend.
+//!
+//! Each test takes a fresh [`Tenet`] on an empty repository. Backends run the
+//! whole suite with [`repository_test_suite`], passing a function that builds
+//! the `Tenet` and hands it to a test.
+
+use diesel::result::{DatabaseErrorKind, Error};
+use uuid::Uuid;
+
+use crate::{application_type::ApplicationType, encryption_modes::{EncryptionModes, PasswordHashConfig}, role_type::RoleType};
+use crate::{Application, AuthorizationDecision, BreachedPasswords, DenialReason, Group, InMemoryMailer, InvitationRole, MailKind, MailTemplate, MailTemplates, PasswordHistoryEntry, PasswordPolicy, PasswordViolation, Role, RoleDefinition, SessionConfig, Storage, Tenant, Tenet, TenetError, TokenConfig, User, ALL_PERMISSIONS};
+use crate::repository::Invitee;
+
+
+/// Generates a `#[test]` for every test of the suite. `$run` is called with
+/// the test and must call it with a `Tenet` on an empty repository.
+macro_rules! repository_test_suite {
+    ($run:expr) => {
+        $crate::test_suite::repository_test_suite!(@tests $run;
+            tenant_lifecycle_test,
+            user_test,
+            unique_email_test,
+            tenant_scoping_test,
+            foreign_key_test,
+            authorization_test,
+            tenant_wide_role_test,
+            membership_test,
+            delete_tenant_with_member_test,
+            group_test,
+            role_definition_test,
+            role_definition_name_test,
+            create_tenant_is_atomic_test,
+            session_test,
+            refresh_token_test,
+            refresh_token_expiry_test,
+            invitation_test,
+            accept_invitation_is_atomic_test,
+            invitation_expiry_test,
+            mail_template_test,
+            legacy_password_test,
+            update_user_test,
+            encryption_modes_test,
+            rehash_on_login_test,
+            password_policy_test,
+            tenant_password_policy_test,
+            breached_passwords_test,
+            password_history_test,
+            set_user_verified_test,
+            email_verification_test,
+            email_verification_expiry_test,
+            password_reset_test,
+            change_password_test
+        );
+    };
+    (@tests $run:expr; $($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                let run: fn(fn($crate::Tenet)) = $run;
+                run($crate::test_suite::$name);
+            }
+        )*
+    };
+}
+pub(crate) use repository_test_suite;
+
+
+/// A verified user of the tenant with the password `password`.
+pub(crate) fn user(email: &str, tenant_id: Uuid) -> User {
+    User::new(
+        email.to_string(),
+        "Danny Crane".to_string(),
+        "password".to_string(),
+        EncryptionModes::Argon2,
+        email.to_string(),
+        true,
+        tenant_id)
+}
+
+pub(crate) fn is_unique_violation<T>(result: Result<T, TenetError>) -> bool {
+    matches!(result, Err(TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _))))
+}
+
+pub(crate) fn is_foreign_key_violation<T>(result: Result<T, TenetError>) -> bool {
+    matches!(result, Err(TenetError::DatabaseError(Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _))))
+}
+
+/// Waits for the `count`th mail to the address and returns the token in it.
+/// Bcrypt hash of `legacy password`, as imported from another system.
+const BCRYPT_HASH: &str = "$2b$04$s8YPg7EtkhuHCxQkHW4ghuDbafSvsmPbBW.QxcdfVNtRajnROsQTa";
+
+pub(crate) fn mailed_token(mailer: &InMemoryMailer, to: &str, count: usize) -> String {
+    let mails = mailer.wait_for_mails_to(to, count, std::time::Duration::from_secs(5));
+    mails[count - 1].body.lines().nth(2).unwrap().to_string()
+}
+
+
+pub(crate) fn tenant_lifecycle_test(tenet: Tenet) {
+    assert!(tenet.get_tenant_ids().is_empty());
+
+    let tenant = tenet.create_tenant("Initial Title".to_string()).unwrap();
+    assert_eq!(vec![tenant.id], tenet.get_tenant_ids());
+
+    let updated_tenant = tenet.set_tenant_title(tenant.id, "Updated Title".to_string()).unwrap();
+    assert_eq!("Updated Title", updated_tenant.title);
+    assert_eq!("Updated Title", tenet.get_tenant_by_id(tenant.id).unwrap().title);
+
+    tenet.delete_tenant(tenant.id).unwrap();
+    assert!(tenet.get_tenant_by_id(tenant.id).is_none());
+    assert!(matches!(tenet.set_tenant_title(tenant.id, "Gone".to_string()), Err(TenetError::DatabaseError(Error::NotFound))));
+}
+
+pub(crate) fn user_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    assert_ne!("password", created_user.password);
+    assert!(created_user.verify_password("password").unwrap());
+
+    assert_eq!(created_user.id, tenant.get_user_by_id(created_user.id).unwrap().id);
+    assert!(tenant.contains_username("someone@something.de".to_string()));
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_some());
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "wrong".to_string()).is_none());
+    assert_eq!(tenant.id, tenet.get_tenant_by_username("someone@something.de".to_string()).unwrap().id);
+
+    tenant.delete_user(created_user.id).unwrap();
+    assert!(tenant.get_users().is_empty());
+}
+
+pub(crate) fn unique_email_test(tenet: Tenet) {
+    let tenant_a = tenet.create_tenant("Tenant A".to_string()).unwrap();
+    let tenant_b = tenet.create_tenant("Tenant B".to_string()).unwrap();
+
+    tenant_a.add_user(&user("someone@something.de", tenant_a.id)).unwrap();
+    let duplicate = tenant_b.add_user(&user("someone@something.de", tenant_b.id));
+
+    assert!(is_unique_violation(duplicate));
+}
+
+pub(crate) fn tenant_scoping_test(tenet: Tenet) {
+    let tenant_a = tenet.create_tenant("Tenant A".to_string()).unwrap();
+    let tenant_b = tenet.create_tenant("Tenant B".to_string()).unwrap();
+
+    let user_a = tenant_a.add_user(&user("a@something.de", tenant_a.id)).unwrap();
+    let storage_a = tenant_a.add_storage(&Storage::new_json_file("some_path", tenant_a.id)).unwrap();
+    let application_a = tenant_a.add_application(&Application::new(ApplicationType::Shop, storage_a.id, tenant_a.id)).unwrap();
+    let role_a = tenant_a.add_role(&Role::new(RoleType::User, user_a.id, application_a.id, tenant_a.id)).unwrap();
+
+    assert!(tenant_b.get_user_by_id(user_a.id).is_err());
+    assert!(tenant_b.get_storage_by_id(storage_a.id).is_err());
+    assert!(tenant_b.get_application_by_id(application_a.id).is_err());
+    assert!(tenant_b.get_role_by_id(role_a.id).is_err());
+    assert!(!tenant_b.contains_username("a@something.de".to_string()));
+    assert!(tenant_b.get_roles_for_user(user_a.id).unwrap().is_empty());
+
+    assert_eq!(1, tenant_a.get_roles_for_user(user_a.id).unwrap().len());
+    assert!(tenant_b.get_users().is_empty());
+
+    // Roles can only be granted to members of the tenant, for its own applications
+    let user_b = tenant_b.add_user(&user("b@something.de", tenant_b.id)).unwrap();
+    let storage_b = tenant_b.add_storage(&Storage::new_json_file("some_path", tenant_b.id)).unwrap();
+    let application_b = tenant_b.add_application(&Application::new(ApplicationType::Shop, storage_b.id, tenant_b.id)).unwrap();
+    assert!(tenant_b.add_role(&Role::new(RoleType::User, user_a.id, application_b.id, tenant_b.id)).unwrap_err().is_not_found());
+    assert!(tenant_b.add_role(&Role::new(RoleType::User, user_b.id, application_a.id, tenant_b.id)).unwrap_err().is_not_found());
+    assert!(tenant_b.add_role(&Role::new_tenant_wide(RoleType::User, user_a.id, tenant_b.id)).unwrap_err().is_not_found());
+    assert!(tenant_b.get_roles_for_user(user_b.id).unwrap().is_empty());
+}
+
+pub(crate) fn foreign_key_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+
+    let orphan = Application::new(ApplicationType::Shop, Uuid::new_v4(), tenant.id);
+    assert!(is_foreign_key_violation(tenant.add_application(&orphan)));
+
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    let role = tenant.add_role(&Role::new(RoleType::Administrator, created_user.id, application.id, tenant.id)).unwrap();
+
+    assert!(is_foreign_key_violation(tenant.delete_storage(storage.id)));
+    assert!(is_foreign_key_violation(tenant.delete_user(created_user.id)));
+    assert!(is_foreign_key_violation(tenet.delete_tenant(tenant.id)));
+
+    tenant.delete_role(role.id).unwrap();
+    tenant.delete_application(application.id).unwrap();
+    tenant.delete_storage(storage.id).unwrap();
+    tenant.delete_user(created_user.id).unwrap();
+    tenet.delete_tenant(tenant.id).unwrap();
+}
+
+pub(crate) fn authorization_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let other_application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    assert_eq!(None, tenant.effective_role(created_user.id, application.id).unwrap());
+    assert_eq!(AuthorizationDecision::Denied { reason: DenialReason::NoRole },
+        tenant.is_authorized(created_user.id, application.id, RoleType::User).unwrap());
+    assert_eq!(AuthorizationDecision::Denied { reason: DenialReason::UnknownUser },
+        tenant.is_authorized(Uuid::new_v4(), application.id, RoleType::User).unwrap());
+    assert_eq!(AuthorizationDecision::Denied { reason: DenialReason::UnknownApplication },
+        tenant.is_authorized(created_user.id, Uuid::new_v4(), RoleType::User).unwrap());
+
+    let user_role = tenant.add_role(&Role::new(RoleType::User, created_user.id, application.id, tenant.id)).unwrap();
+    tenant.add_role(&Role::new(RoleType::Administrator, created_user.id, other_application.id, tenant.id)).unwrap();
+    assert_eq!(Some(user_role), tenant.effective_role(created_user.id, application.id).unwrap());
+    assert_eq!(AuthorizationDecision::Granted { role: user_role },
+        tenant.is_authorized(created_user.id, application.id, RoleType::User).unwrap());
+    assert_eq!(AuthorizationDecision::Denied { reason: DenialReason::InsufficientRole { effective: RoleType::User, required: RoleType::Administrator } },
+        tenant.is_authorized(created_user.id, application.id, RoleType::Administrator).unwrap());
+
+    let admin_role = tenant.add_role(&Role::new(RoleType::Administrator, created_user.id, application.id, tenant.id)).unwrap();
+    let decision = tenant.is_authorized(created_user.id, application.id, RoleType::User).unwrap();
+    assert!(decision.is_granted());
+    assert_eq!(AuthorizationDecision::Granted { role: admin_role }, decision);
+}
+
+pub(crate) fn tenant_wide_role_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let other_application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    let tenant_user = tenant.add_role(&Role::new_tenant_wide(RoleType::User, created_user.id, tenant.id)).unwrap();
+    assert!(tenant_user.is_tenant_wide());
+    assert_eq!(None, tenant.get_role_by_id(tenant_user.id).unwrap().application_id);
+    assert_eq!(vec![tenant_user], tenant.get_tenant_wide_roles_for_user(created_user.id).unwrap());
+    assert_eq!(Some(tenant_user), tenant.effective_role(created_user.id, application.id).unwrap());
+    assert_eq!(Some(tenant_user), tenant.effective_role(created_user.id, other_application.id).unwrap());
+
+    // On equal role types the application role is the more specific one
+    let application_user = tenant.add_role(&Role::new(RoleType::User, created_user.id, application.id, tenant.id)).unwrap();
+    assert_eq!(Some(application_user), tenant.effective_role(created_user.id, application.id).unwrap());
+    assert_eq!(2, tenant.get_roles_for_user_in_application(created_user.id, application.id).unwrap().len());
+    assert_eq!(vec![tenant_user], tenant.get_roles_for_user_in_application(created_user.id, other_application.id).unwrap());
+
+    // A higher tenant-wide role outranks the application role
+    let owner = tenant.add_role(&Role::new_tenant_wide(RoleType::Administrator, created_user.id, tenant.id)).unwrap();
+    assert_eq!(Some(owner), tenant.effective_role(created_user.id, application.id).unwrap());
+    assert_eq!(AuthorizationDecision::Granted { role: owner },
+        tenant.is_authorized(created_user.id, other_application.id, RoleType::Administrator).unwrap());
+    assert!(tenant.has_permission(created_user.id, other_application.id, "billing:write").unwrap());
+
+    tenant.delete_application(other_application.id).unwrap();
+    assert_eq!(2, tenant.get_tenant_wide_roles_for_user(created_user.id).unwrap().len());
+}
+
+pub(crate) fn membership_test(tenet: Tenet) {
+    let home = tenet.create_tenant("Home".to_string()).unwrap();
+    let customer = tenet.create_tenant("Customer".to_string()).unwrap();
+    let storage = customer.add_storage(&Storage::new_json_file("some_path", customer.id)).unwrap();
+    let application = customer.add_application(&Application::new(ApplicationType::Shop, storage.id, customer.id)).unwrap();
+    let consultant = home.add_user(&user("consultant@something.de", home.id)).unwrap();
+
+    assert!(customer.get_user_by_id(consultant.id).is_err());
+    assert_eq!(vec![home.id], tenet.get_tenants_for_user(consultant.id).unwrap().iter().map(|t| t.id).collect::<Vec<_>>());
+
+    customer.add_member(consultant.id).unwrap();
+    assert!(is_unique_violation(customer.add_member(consultant.id)));
+    assert_eq!(vec![consultant.id], customer.get_user_ids());
+    assert!(customer.get_user_by_id(consultant.id).is_ok());
+    assert!(customer.contains_username("consultant@something.de".to_string()));
+    assert!(customer.authenticate_user("consultant@something.de".to_string(), "password".to_string()).is_some());
+    let tenants = tenet.get_tenants_for_user(consultant.id).unwrap();
+    assert_eq!(vec!["Customer", "Home"], tenants.iter().map(|t| t.title.as_str()).collect::<Vec<_>>());
+
+    // Roles are per tenant
+    customer.add_role(&Role::new(RoleType::Administrator, consultant.id, application.id, customer.id)).unwrap();
+    assert!(home.get_roles_for_user(consultant.id).unwrap().is_empty());
+
+    // Login first, then pick the tenant
+    assert!(tenet.login("consultant@something.de".to_string(), "wrong".to_string()).is_none());
+    let selection = tenet.login("consultant@something.de".to_string(), "password".to_string()).unwrap();
+    assert_eq!(consultant.id, selection.user.id);
+    assert_eq!(2, selection.tenants.len());
+    assert!(selection.single().is_none());
+    let issued = selection.select(customer.id).unwrap();
+    assert_eq!(Some(customer.id), customer.validate_session(&issued.token).unwrap().db_tenant_id);
+    assert!(matches!(selection.select(Uuid::new_v4()), Err(TenetError::NotFoundError)));
+
+    // Deleting a user that belongs to other tenants only removes the membership
+    customer.delete_user(consultant.id).unwrap();
+    assert!(customer.get_user_by_id(consultant.id).is_err());
+    assert!(customer.get_roles().is_empty());
+    assert!(matches!(customer.validate_session(&issued.token), Err(TenetError::SessionRevokedError)));
+    assert!(home.get_user_by_id(consultant.id).is_ok());
+    let selection = tenet.login("consultant@something.de".to_string(), "password".to_string()).unwrap();
+    assert_eq!(home.id, selection.single().unwrap().id);
+
+    // Removing the membership keeps the user in their other tenants
+    customer.add_member(consultant.id).unwrap();
+    let group = customer.add_group(&Group::new("Consultants".to_string(), customer.id)).unwrap();
+    customer.add_user_to_group(group.id, consultant.id).unwrap();
+    customer.add_role(&Role::new(RoleType::User, consultant.id, application.id, customer.id)).unwrap();
+    let session = customer.create_session(consultant.id).unwrap();
+    let refresh_token = customer.issue_refresh_token(consultant.id).unwrap();
+    let home_session = home.create_session(consultant.id).unwrap();
+    customer.remove_member(consultant.id).unwrap();
+    assert!(customer.get_users().is_empty());
+    assert!(customer.get_roles().is_empty());
+    assert!(customer.get_groups_for_user(consultant.id).unwrap().is_empty());
+    assert!(matches!(customer.validate_session(&session.token), Err(TenetError::SessionRevokedError)));
+    assert!(matches!(customer.refresh(&refresh_token.token), Err(TenetError::SessionRevokedError)));
+    assert!(home.validate_session(&home_session.token).is_ok());
+    assert!(home.get_user_by_id(consultant.id).is_ok());
+
+    home.delete_user(consultant.id).unwrap();
+    assert!(tenet.login("consultant@something.de".to_string(), "password".to_string()).is_none());
+}
+
+pub(crate) fn delete_tenant_with_member_test(tenet: Tenet) {
+    let home = tenet.create_tenant("Home".to_string()).unwrap();
+    let customer = tenet.create_tenant("Customer".to_string()).unwrap();
+    let consultant = home.add_user(&user("consultant@something.de", home.id)).unwrap();
+    customer.add_member(consultant.id).unwrap();
+    customer.create_session(consultant.id).unwrap();
+    customer.issue_refresh_token(consultant.id).unwrap();
+    let user_token = customer.start_email_verification(consultant.id).unwrap().user_token;
+    let home_session = home.create_session(consultant.id).unwrap();
+
+    tenet.delete_tenant(customer.id).unwrap();
+    assert!(customer.get_sessions_for_user(consultant.id).unwrap().is_empty());
+    assert!(customer.get_refresh_tokens_for_user(consultant.id).unwrap().is_empty());
+    assert!(tenet.context.repository.find_user_token_by_token_hash(user_token.token_hash).unwrap_err().is_not_found());
+    assert!(home.validate_session(&home_session.token).is_ok());
+}
+
+pub(crate) fn group_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let alice = tenant.add_user(&user("alice@something.de", tenant.id)).unwrap();
+    let bob = tenant.add_user(&user("bob@something.de", tenant.id)).unwrap();
+
+    let staff = tenant.add_group(&Group::new("Staff".to_string(), tenant.id)).unwrap();
+    assert!(is_unique_violation(tenant.add_group(&Group::new("Staff".to_string(), tenant.id))));
+    tenant.add_user_to_group(staff.id, alice.id).unwrap();
+    tenant.add_user_to_group(staff.id, bob.id).unwrap();
+    assert!(is_unique_violation(tenant.add_user_to_group(staff.id, bob.id)));
+    let member_ids: Vec<uuid::Uuid> = tenant.get_group_members(staff.id).unwrap().iter().map(|u| u.id).collect();
+    assert_eq!(vec![alice.id, bob.id], member_ids);
+    assert_eq!(vec![staff.clone()], tenant.get_groups_for_user(alice.id).unwrap());
+
+    let user_definition = tenant.get_role_definitions().into_iter().find(|d| d.name == "User").unwrap();
+    let staff_role = tenant.add_role(&Role::for_group(&user_definition, staff.id, Some(application.id), tenant.id)).unwrap();
+    assert!(staff_role.is_inherited());
+    assert_eq!(Some(staff.id), tenant.get_role_by_id(staff_role.id).unwrap().group_id);
+    assert_eq!(vec![staff_role], tenant.get_roles_for_group(staff.id).unwrap());
+    assert_eq!(vec![staff_role], tenant.get_roles_for_user(bob.id).unwrap());
+    assert!(tenant.get_direct_roles_for_user(bob.id).unwrap().is_empty());
+    assert!(tenant.is_authorized(bob.id, application.id, RoleType::User).unwrap().is_granted());
+
+    // A direct role of the same type takes precedence, a higher one outranks the group
+    let alice_role = tenant.add_role(&Role::new(RoleType::User, alice.id, application.id, tenant.id)).unwrap();
+    assert_eq!(Some(alice_role), tenant.effective_role(alice.id, application.id).unwrap());
+    let admins = tenant.add_group(&Group::new("Admins".to_string(), tenant.id)).unwrap();
+    let admin_definition = tenant.get_role_definitions().into_iter().find(|d| d.name == "Administrator").unwrap();
+    let admin_role = tenant.add_role(&Role::for_group(&admin_definition, admins.id, None, tenant.id)).unwrap();
+    tenant.add_user_to_group(admins.id, alice.id).unwrap();
+    assert_eq!(Some(admin_role), tenant.effective_role(alice.id, application.id).unwrap());
+    assert!(tenant.has_permission(alice.id, application.id, "orders:write").unwrap());
+    assert_eq!(vec![admins.clone(), staff.clone()], tenant.get_groups_for_user(alice.id).unwrap());
+
+    tenant.remove_user_from_group(admins.id, alice.id).unwrap();
+    assert_eq!(Some(alice_role), tenant.effective_role(alice.id, application.id).unwrap());
+
+    let renamed = tenant.update_group(&Group { name: "Team".to_string(), ..staff.clone() }).unwrap();
+    assert_eq!("Team", renamed.name);
+
+    // Deleting a group removes its memberships and roles
+    tenant.delete_group(staff.id).unwrap();
+    assert!(tenant.get_roles_for_user(bob.id).unwrap().is_empty());
+    assert!(tenant.get_groups_for_user(bob.id).unwrap().is_empty());
+    assert!(tenant.get_group_by_id(staff.id).is_err());
+
+    // Groups are scoped to their tenant
+    let other_tenant = tenet.create_tenant("Other".to_string()).unwrap();
+    assert!(other_tenant.get_group_by_id(admins.id).is_err());
+    assert!(other_tenant.add_user_to_group(admins.id, bob.id).is_err());
+    assert!(other_tenant.add_role(&Role::for_group(&admin_definition, admins.id, None, other_tenant.id)).is_err());
+}
+
+pub(crate) fn role_definition_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    let names: Vec<String> = tenant.get_role_definitions().iter().map(|d| d.name.clone()).collect();
+    assert_eq!(vec!["Administrator".to_string(), "User".to_string()], names);
+    assert_eq!(vec![ALL_PERMISSIONS.to_string()], tenant.get_role_definitions()[0].permissions);
+
+    // Permissions are stored once
+    let clerk = tenant.add_role_definition(&RoleDefinition::new(
+        "Clerk".to_string(), RoleType::User, vec!["orders:*".to_string(), "catalog:read".to_string(), "orders:*".to_string()], tenant.id)).unwrap();
+    assert_eq!(2, clerk.permissions.len());
+    assert!(is_unique_violation(tenant.add_role_definition(&RoleDefinition::new("Clerk".to_string(), RoleType::User, Vec::new(), tenant.id))));
+
+    assert!(!tenant.has_permission(created_user.id, application.id, "orders:read").unwrap());
+    let role = tenant.add_role(&Role::with_definition(&clerk, created_user.id, application.id, tenant.id)).unwrap();
+    assert_eq!(Some(clerk.id), role.role_definition_id);
+    assert!(tenant.has_permission(created_user.id, application.id, "orders:read").unwrap());
+    assert!(tenant.has_permission(created_user.id, application.id, "catalog:read").unwrap());
+    assert!(!tenant.has_permission(created_user.id, application.id, "catalog:write").unwrap());
+    assert_eq!(vec!["catalog:read".to_string(), "orders:*".to_string()], tenant.get_permissions_for_user(created_user.id, application.id).unwrap());
+
+    let manager = tenant.update_role_definition(&RoleDefinition {
+        role_type: RoleType::Administrator,
+        permissions: vec!["catalog:*".to_string()],
+        ..clerk.clone()
+    }).unwrap();
+    assert!(manager.updated_at.is_some());
+    assert!(tenant.has_permission(created_user.id, application.id, "catalog:write").unwrap());
+    assert!(!tenant.has_permission(created_user.id, application.id, "orders:read").unwrap());
+    assert_eq!(RoleType::Administrator, tenant.get_role_by_id(role.id).unwrap().role_type);
+
+    assert!(is_foreign_key_violation(tenant.delete_role_definition(clerk.id)));
+    tenant.delete_role(role.id).unwrap();
+    tenant.delete_role_definition(clerk.id).unwrap();
+    assert_eq!(2, tenant.get_role_definitions().len());
+
+    let admin = tenant.add_role(&Role::new(RoleType::Administrator, created_user.id, application.id, tenant.id)).unwrap();
+    assert_eq!(Some("Administrator".to_string()), admin.role_definition_id.map(|id| tenant.get_role_definition_by_id(id).unwrap().name));
+    assert!(tenant.has_permission(created_user.id, application.id, "anything:at_all").unwrap());
+
+    let administrator = tenant.get_role_definition_by_id(admin.role_definition_id.unwrap()).unwrap();
+    assert!(administrator.is_builtin());
+    assert!(matches!(tenant.update_role_definition(&RoleDefinition { permissions: Vec::new(), ..administrator.clone() }), Err(TenetError::BuiltinRoleDefinitionError)));
+    assert!(matches!(tenant.delete_role_definition(administrator.id), Err(TenetError::BuiltinRoleDefinitionError)));
+    assert!(tenant.has_permission(created_user.id, application.id, "anything:at_all").unwrap());
+}
+
+pub(crate) fn role_definition_name_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    let clerk = tenant.add_role_definition(&RoleDefinition::new("Clerk".to_string(), RoleType::User, Vec::new(), tenant.id)).unwrap();
+    tenant.add_role_definition(&RoleDefinition::new("Auditor".to_string(), RoleType::User, Vec::new(), tenant.id)).unwrap();
+    other.add_role_definition(&RoleDefinition::new("Clerk".to_string(), RoleType::User, Vec::new(), other.id)).unwrap();
+
+    // Names are unique within the tenant the definition is stored in, whatever tenant the caller passes
+    tenant.update_role_definition(&RoleDefinition { db_tenant_id: Some(other.id), ..clerk.clone() }).unwrap();
+    assert!(is_unique_violation(tenant.update_role_definition(&RoleDefinition { name: "Auditor".to_string(), db_tenant_id: Some(other.id), ..clerk.clone() })));
+}
+
+pub(crate) fn create_tenant_is_atomic_test(tenet: Tenet) {
+    let tenant = Tenant::new("TenantTitle".to_string());
+    let mut role_definitions = RoleDefinition::defaults(tenant.id);
+    role_definitions.push(RoleDefinition::new("User".to_string(), RoleType::User, Vec::new(), tenant.id));
+
+    assert!(tenet.context.repository.create_tenant(&tenant, &role_definitions).is_err());
+    assert!(tenet.get_tenant_ids().is_empty());
+}
+
+pub(crate) fn session_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other_tenant = tenet.create_tenant("Other Tenant".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    assert!(tenant.start_session("someone@something.de".to_string(), "wrong".to_string()).is_none());
+    let issued = tenant.start_session("someone@something.de".to_string(), "password".to_string()).unwrap();
+    assert_ne!(issued.token, issued.session.token_hash);
+
+    let session = tenant.validate_session(&issued.token).unwrap();
+    assert_eq!(created_user.id, session.user_id);
+    assert!(session.expires_at >= issued.session.expires_at);
+    assert!(matches!(other_tenant.validate_session(&issued.token), Err(TenetError::InvalidTokenError)));
+    assert!(matches!(tenant.validate_session("unknown"), Err(TenetError::InvalidTokenError)));
+
+    let second = tenant.create_session(created_user.id).unwrap();
+    assert_eq!(2, tenant.get_sessions_for_user(created_user.id).unwrap().len());
+
+    tenant.revoke_session(issued.session.id).unwrap();
+    assert!(matches!(tenant.validate_session(&issued.token), Err(TenetError::SessionRevokedError)));
+    assert!(tenant.validate_session(&second.token).is_ok());
+
+    tenant.revoke_sessions_for_user(created_user.id).unwrap();
+    assert!(matches!(tenant.validate_session(&second.token), Err(TenetError::SessionRevokedError)));
+
+    tenant.delete_user(created_user.id).unwrap();
+    assert!(tenant.get_sessions_for_user(created_user.id).unwrap().is_empty());
+}
+
+pub(crate) fn refresh_token_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other_tenant = tenet.create_tenant("Other Tenant".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    let first = tenant.issue_refresh_token(created_user.id).unwrap();
+    assert_ne!(first.token, first.refresh_token.token_hash);
+    assert!(matches!(other_tenant.refresh(&first.token), Err(TenetError::InvalidTokenError)));
+    assert!(matches!(tenant.refresh("unknown"), Err(TenetError::InvalidTokenError)));
+
+    let second = tenant.refresh(&first.token).unwrap();
+    assert_eq!(first.refresh_token.family_id, second.refresh_token.family_id);
+    assert_eq!(created_user.id, second.refresh_token.user_id);
+
+    let other_family = tenant.issue_refresh_token(created_user.id).unwrap();
+    assert_ne!(first.refresh_token.family_id, other_family.refresh_token.family_id);
+
+    assert!(matches!(tenant.refresh(&first.token), Err(TenetError::RefreshTokenReusedError)));
+    assert!(matches!(tenant.refresh(&second.token), Err(TenetError::SessionRevokedError)));
+    assert!(tenant.refresh(&other_family.token).is_ok());
+    assert_eq!(4, tenant.get_refresh_tokens_for_user(created_user.id).unwrap().len());
+
+    let third = tenant.issue_refresh_token(created_user.id).unwrap();
+    tenant.revoke_refresh_token(&third.token).unwrap();
+    assert!(matches!(tenant.refresh(&third.token), Err(TenetError::SessionRevokedError)));
+
+    let fourth = tenant.issue_refresh_token(created_user.id).unwrap();
+    tenant.revoke_refresh_tokens_for_user(created_user.id).unwrap();
+    assert!(matches!(tenant.refresh(&fourth.token), Err(TenetError::SessionRevokedError)));
+
+    tenant.delete_user(created_user.id).unwrap();
+    assert!(tenant.get_refresh_tokens_for_user(created_user.id).unwrap().is_empty());
+}
+
+pub(crate) fn refresh_token_expiry_test(tenet: Tenet) {
+    let tenet = tenet.with_session_config(SessionConfig { refresh_token_lifetime: chrono::Duration::zero(), ..Default::default() });
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    let issued = tenant.issue_refresh_token(created_user.id).unwrap();
+    assert!(matches!(tenant.refresh(&issued.token), Err(TenetError::SessionExpiredError)));
+    assert!(!tenant.get_refresh_tokens_for_user(created_user.id).unwrap()[0].is_used());
+}
+
+pub(crate) fn invitation_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let administrator = tenant.get_role_definitions().into_iter().find(|d| d.name == "Administrator").unwrap();
+    let clerk = tenant.add_role_definition(&RoleDefinition::new("Clerk".to_string(), RoleType::User, vec!["orders:*".to_string()], tenant.id)).unwrap();
+
+    // Roles must belong to the tenant
+    let foreign = other.get_role_definitions()[0].clone();
+    assert!(tenant.invite_user("colleague@something.de".to_string(), &[InvitationRole::tenant_wide(&foreign)]).unwrap_err().is_not_found());
+
+    let issued = tenant.invite_user("colleague@something.de".to_string(), &[
+        InvitationRole::tenant_wide(&clerk),
+        InvitationRole::new(&administrator, application.id)
+    ]).unwrap();
+    assert_eq!(64, issued.token.len());
+    assert_ne!(issued.token, issued.invitation.token_hash);
+    let pending = tenant.get_pending_invitations().unwrap();
+    assert_eq!(vec![issued.invitation.id], pending.iter().map(|i| i.id).collect::<Vec<_>>());
+    assert_eq!(issued.invitation.roles, pending[0].roles);
+    assert!(other.get_pending_invitations().unwrap().is_empty());
+    assert!(matches!(other.accept_invitation(&issued.token, "secret".to_string(), "Colleague".to_string()), Err(TenetError::InvalidTokenError)));
+
+    let colleague = tenant.accept_invitation(&issued.token, "secret".to_string(), "Colleague".to_string()).unwrap();
+    assert_eq!("colleague@something.de", colleague.email);
+    assert!(colleague.email_verified);
+    assert!(tenant.authenticate_user("colleague@something.de".to_string(), "secret".to_string()).is_some());
+    assert_eq!(2, tenant.get_roles_for_user(colleague.id).unwrap().len());
+    assert!(tenant.has_permission(colleague.id, application.id, "catalog:write").unwrap());
+    assert!(tenant.get_invitation_by_id(issued.invitation.id).unwrap().is_accepted());
+    assert!(tenant.get_pending_invitations().unwrap().is_empty());
+
+    // Single use
+    assert!(matches!(tenant.accept_invitation(&issued.token, "other".to_string(), "Other".to_string()), Err(TenetError::InvalidTokenError)));
+
+    let revoked = tenant.invite_user("revoked@something.de".to_string(), &[]).unwrap();
+    tenant.revoke_invitation(revoked.invitation.id).unwrap();
+    assert!(tenant.get_pending_invitations().unwrap().is_empty());
+    assert!(matches!(tenant.accept_invitation(&revoked.token, "secret".to_string(), "Revoked".to_string()), Err(TenetError::InvitationRevokedError)));
+    assert!(!tenant.contains_username("revoked@something.de".to_string()));
+    assert!(other.revoke_invitation(revoked.invitation.id).unwrap_err().is_not_found());
+
+    // Existing users accept with their current password and keep their identity
+    let consultant = other.add_user(&User::new(
+        "consultant@something.de".to_string(),
+        "Danny Crane".to_string(),
+        "password".to_string(),
+        EncryptionModes::Argon2,
+        "consultant@something.de".to_string(),
+        true,
+        other.id)).unwrap();
+    let existing = tenant.invite_user("consultant@something.de".to_string(), &[InvitationRole::tenant_wide(&clerk)]).unwrap();
+    assert!(matches!(tenant.accept_invitation(&existing.token, "wrong".to_string(), "Someone".to_string()), Err(TenetError::InvalidPasswordError)));
+    assert_eq!(1, tenant.get_pending_invitations().unwrap().len());
+    let accepted = tenant.accept_invitation(&existing.token, "password".to_string(), "Someone".to_string()).unwrap();
+    assert_eq!(consultant.id, accepted.id);
+    assert_eq!("Danny Crane", accepted.full_name);
+    assert_eq!(Some(other.id), accepted.db_tenant_id);
+    assert_eq!(2, tenet.get_tenants_for_user(consultant.id).unwrap().len());
+    assert_eq!(1, tenant.get_roles_for_user(consultant.id).unwrap().len());
+    assert!(other.get_roles_for_user(consultant.id).unwrap().is_empty());
+
+    // Members of the tenant can be invited to further roles
+    let again = tenant.invite_user("colleague@something.de".to_string(), &[]).unwrap();
+    assert_eq!(colleague.id, tenant.accept_invitation(&again.token, "secret".to_string(), "Colleague".to_string()).unwrap().id);
+    assert_eq!(2, tenant.get_roles_for_user(colleague.id).unwrap().len());
+}
+
+pub(crate) fn accept_invitation_is_atomic_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let issued = tenant.invite_user("colleague@something.de".to_string(), &tenant.get_role_definitions().iter().map(InvitationRole::tenant_wide).collect::<Vec<_>>()).unwrap();
+
+    assert!(tenet.context.repository.accept_invitation(&issued.invitation, Invitee::Existing(Uuid::new_v4()), chrono::Utc::now().naive_utc()).is_err());
+    assert_eq!(1, tenant.get_pending_invitations().unwrap().len());
+    assert!(tenant.get_roles().is_empty());
+}
+
+pub(crate) fn invitation_expiry_test(tenet: Tenet) {
+    let tenet = tenet.with_token_config(TokenConfig { invitation_lifetime: chrono::Duration::zero(), ..Default::default() });
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let issued = tenant.invite_user("colleague@something.de".to_string(), &[]).unwrap();
+    assert!(tenant.get_pending_invitations().unwrap().is_empty());
+    assert!(matches!(tenant.accept_invitation(&issued.token, "secret".to_string(), "Colleague".to_string()), Err(TenetError::InvitationExpiredError)));
+    assert!(tenant.get_users().is_empty());
+}
+
+pub(crate) fn mail_template_test(tenet: Tenet) {
+    let mailer = InMemoryMailer::new();
+    let tenet = tenet.with_mailer(mailer.clone());
+    let tenant = tenet.create_tenant("Shop".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    let tenet = tenet.with_mail_templates(MailTemplates::default().with_tenant_template(
+        tenant.id,
+        MailKind::Invitation,
+        MailTemplate::new("Willkommen bei {tenant}".to_string(), "Hallo {email}, dein Code: {token}".to_string())
+    ));
+    // Tenants obtained before keep the previous templates
+    let tenant = tenet.get_tenant_by_id(tenant.id).unwrap();
+
+    let issued = tenant.invite_user("colleague@something.de".to_string(), &[]).unwrap();
+    let mails = mailer.mails_to("colleague@something.de");
+    assert_eq!(1, mails.len());
+    assert_eq!("Willkommen bei Shop", mails[0].subject);
+    assert_eq!(format!("Hallo colleague@something.de, dein Code: {}", issued.token), mails[0].body);
+
+    mailer.clear();
+    let issued = other.invite_user("colleague@something.de".to_string(), &[]).unwrap();
+    let mails = mailer.mails();
+    assert_eq!(1, mails.len());
+    assert_eq!("You have been invited to Other", mails[0].subject);
+    assert!(mails[0].body.contains(&issued.token));
+}
+
+pub(crate) fn legacy_password_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let legacy_hashes = [
+        (EncryptionModes::Bcrypt, BCRYPT_HASH),
+        (EncryptionModes::Pbkdf2Sha256, "$pbkdf2-sha256$i=1000,l=32$bGVnYWN5LXNhbHQtMDAwMQ$W8FvASVvuo+YHkP6saeN+BhTovUg5nkphC+h6WcTIG0"),
+        (EncryptionModes::Scrypt, "$scrypt$ln=10,r=8,p=1$bGVnYWN5LXNhbHQtMDAwMQ$oWicJZpg9lCwee0o99AtaY3YrpoIO5x//cqA8CoFiXI")
+    ];
+
+    for (i, (encryption_mode, hash)) in legacy_hashes.into_iter().enumerate() {
+        let email = format!("imported{i}@something.de");
+        // Imported hashes are stored as they are
+        let imported = tenant.import_user(&User { password: hash.to_string(), encryption_mode: encryption_mode.clone(), ..user(&email, tenant.id) }).unwrap();
+        assert_eq!(hash, imported.password);
+        assert!(tenant.authenticate_user(email.clone(), "legacy password".to_string()).is_some());
+        assert!(tenant.authenticate_user(email, "wrong password".to_string()).is_none());
+
+        assert!(!encryption_mode.verify_password(hash, "wrong password").unwrap());
+        assert!(encryption_mode.verify_password("not a hash", "legacy password").is_err());
+    }
+}
+
+pub(crate) fn update_user_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    // Profile changes keep the stored password
+    let updated = tenant.update_user(&User {
+        full_name: "Denny Crane".to_string(),
+        password: "plaintext".to_string(),
+        encryption_mode: EncryptionModes::Bcrypt,
+        ..created_user.clone()
+    }).unwrap();
+    assert_eq!("Denny Crane", updated.full_name);
+    assert_eq!(created_user.password, updated.password);
+    assert_eq!(EncryptionModes::Argon2, updated.encryption_mode);
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_some());
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "plaintext".to_string()).is_none());
+
+    // Only members can be updated
+    assert!(other.update_user(&created_user).unwrap_err().is_not_found());
+}
+
+pub(crate) fn encryption_modes_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&User { encryption_mode: EncryptionModes::Bcrypt, ..user("someone@something.de", tenant.id) }).unwrap();
+    assert!(created_user.password.starts_with("$2b$"));
+    assert!(created_user.verify_password("password").unwrap());
+
+    for encryption_mode in [EncryptionModes::Argon2, EncryptionModes::Bcrypt, EncryptionModes::Pbkdf2Sha256, EncryptionModes::Scrypt] {
+        assert_eq!(Ok(encryption_mode.clone()), encryption_mode.to_string().parse());
+    }
+}
+
+pub(crate) fn rehash_on_login_test(tenet: Tenet) {
+    let cheap = PasswordHashConfig { memory_cost: 1024, time_cost: 1, parallelism: 1 };
+    let tenet = tenet.with_password_hash_config(cheap.clone());
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    assert!(created_user.password.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+    assert!(!cheap.needs_rehash(&created_user.encryption_mode, &created_user.password));
+
+    // Argon2i with the parameters of earlier versions
+    let old_hash = argon2::hash_encoded(b"password", &[0; 32], &argon2::Config::original()).unwrap();
+    tenet.context.repository.update_user(&User { password: old_hash.clone(), ..created_user.clone() }).unwrap();
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "wrong password".to_string()).is_none());
+    assert_eq!(old_hash, tenant.get_user_by_id(created_user.id).unwrap().password);
+
+    let authenticated = tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).unwrap();
+    assert!(authenticated.password.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+    assert_eq!(authenticated.password, tenant.get_user_by_id(created_user.id).unwrap().password);
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_some());
+
+    // Legacy encryption modes are migrated to Argon2, also by Tenet::login
+    let bcrypt_hash = "$2b$04$s8YPg7EtkhuHCxQkHW4ghuDbafSvsmPbBW.QxcdfVNtRajnROsQTa";
+    tenet.context.repository.update_user(&User { password: bcrypt_hash.to_string(), encryption_mode: EncryptionModes::Bcrypt, ..created_user.clone() }).unwrap();
+    let selection = tenet.login("someone@something.de".to_string(), "legacy password".to_string()).unwrap();
+    assert_eq!(EncryptionModes::Argon2, selection.user.encryption_mode);
+    let stored = tenant.get_user_by_id(created_user.id).unwrap();
+    assert_eq!(EncryptionModes::Argon2, stored.encryption_mode);
+    assert!(stored.verify_password("legacy password").unwrap());
+
+    // Changed parameters apply to tenants obtained afterwards
+    let tenet = tenet.with_password_hash_config(PasswordHashConfig { time_cost: 2, ..cheap });
+    let tenant = tenet.get_tenant_by_id(tenant.id).unwrap();
+    let authenticated = tenant.authenticate_user("someone@something.de".to_string(), "legacy password".to_string()).unwrap();
+    assert!(authenticated.password.starts_with("$argon2id$v=19$m=1024,t=2,p=1$"));
+}
+
+pub(crate) fn password_policy_test(tenet: Tenet) {
+    let mailer = InMemoryMailer::new();
+    let tenet = tenet
+        .with_mailer(mailer.clone())
+        .with_password_policy(PasswordPolicy { min_length: 8, ..Default::default() });
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let strict = tenet.create_tenant("Strict".to_string()).unwrap();
+    strict.set_password_policy(&PasswordPolicy { require_digit: true, ..PasswordPolicy::recommended() }).unwrap();
+
+    let result = tenant.add_user(&User { password: String::new(), ..user("someone@something.de", tenant.id) });
+    assert!(matches!(result, Err(TenetError::PasswordPolicyError(v)) if v == vec![PasswordViolation::TooShort { min_length: 8 }]));
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    let result = strict.add_user(&User { password: "Danny Crane is a secret".to_string(), ..user("strict@something.de", strict.id) });
+    assert!(matches!(result, Err(TenetError::PasswordPolicyError(v)) if v == vec![PasswordViolation::MissingDigit, PasswordViolation::ContainsUserData]));
+    assert!(!strict.contains_username("strict@something.de".to_string()));
+    strict.add_user(&User { password: "correct horse battery 7".to_string(), ..user("strict@something.de", strict.id) }).unwrap();
+
+    // Every way of changing a password is checked
+    assert!(matches!(tenant.change_password(created_user.id, "password".to_string(), "short".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    assert!(matches!(tenant.force_password_reset(created_user.id, "short".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    tenant.request_password_reset("someone@something.de".to_string()).unwrap();
+    let token = mailed_token(&mailer, "someone@something.de", 1);
+    assert!(matches!(tenant.reset_password(&token, "short".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_some());
+    tenant.reset_password(&token, "long enough".to_string()).unwrap();
+
+    let invitation = strict.invite_user("colleague@something.de".to_string(), &[]).unwrap();
+    assert!(matches!(strict.accept_invitation(&invitation.token, "password".to_string(), "Colleague".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    strict.accept_invitation(&invitation.token, "correct horse battery 8".to_string(), "Colleague".to_string()).unwrap();
+}
+
+pub(crate) fn tenant_password_policy_test(tenet: Tenet) {
+    let tenet = tenet.with_password_policy(PasswordPolicy { min_length: 8, ..Default::default() });
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    assert_eq!(8, tenant.get_password_policy().unwrap().min_length);
+
+    let policy = PasswordPolicy { history_length: 5, ..PasswordPolicy::recommended() };
+    assert_eq!(policy, tenant.set_password_policy(&policy).unwrap());
+    // The policy is stored, other handles to the tenant see it as well
+    assert_eq!(policy, tenet.get_tenant_by_id(tenant.id).unwrap().get_password_policy().unwrap());
+    assert!(matches!(tenant.add_user(&user("someone@something.de", tenant.id)), Err(TenetError::PasswordPolicyError(_))));
+    let replaced = PasswordPolicy { min_length: 20, ..policy };
+    tenant.set_password_policy(&replaced).unwrap();
+    assert_eq!(replaced, tenant.get_password_policy().unwrap());
+
+    tenant.remove_password_policy().unwrap();
+    assert_eq!(8, tenant.get_password_policy().unwrap().min_length);
+
+    tenant.set_password_policy(&replaced).unwrap();
+    tenet.delete_tenant(tenant.id).unwrap();
+    assert!(tenet.context.repository.find_password_policy(tenant.id).unwrap_err().is_not_found());
+    assert!(is_foreign_key_violation(tenet.context.repository.save_password_policy(tenant.id, &replaced)));
+}
+
+pub(crate) fn breached_passwords_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+
+    // Tenants obtained before the corpus is set are checked as well
+    tenet.set_breached_passwords(Some(BreachedPasswords::from_passwords(["password", "hunter2"])));
+    let result = tenant.add_user(&user("other@something.de", tenant.id));
+    assert!(matches!(result, Err(TenetError::PasswordPolicyError(v)) if v == vec![PasswordViolation::Breached]));
+    assert!(matches!(tenant.change_password(created_user.id, "password".to_string(), "hunter2".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    // Existing passwords keep working
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_some());
+
+    tenet.clone().set_breached_passwords(Some(BreachedPasswords::from_passwords(["hunter2"])));
+    tenant.add_user(&user("other@something.de", tenant.id)).unwrap();
+    tenet.set_breached_passwords(None);
+    tenant.change_password(created_user.id, "password".to_string(), "hunter2".to_string()).unwrap();
+}
+
+pub(crate) fn password_history_test(tenet: Tenet) {
+    let mailer = InMemoryMailer::new();
+    let tenet = tenet.with_mailer(mailer.clone());
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    tenant.set_password_policy(&PasswordPolicy { history_length: 3, ..Default::default() }).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    other.add_member(created_user.id).unwrap();
+
+    // The current password counts as one of the last three
+    let result = tenant.change_password(created_user.id, "password".to_string(), "password".to_string());
+    assert!(matches!(result, Err(TenetError::PasswordPolicyError(v)) if v == vec![PasswordViolation::RecentlyUsed { history_length: 3 }]));
+    tenant.change_password(created_user.id, "password".to_string(), "second".to_string()).unwrap();
+    tenant.force_password_reset(created_user.id, "third".to_string()).unwrap();
+
+    assert!(matches!(tenant.change_password(created_user.id, "third".to_string(), "second".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    assert!(matches!(tenant.force_password_reset(created_user.id, "password".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    tenant.request_password_reset("someone@something.de".to_string()).unwrap();
+    let token = mailed_token(&mailer, "someone@something.de", 1);
+    assert!(matches!(tenant.reset_password(&token, "third".to_string()), Err(TenetError::PasswordPolicyError(_))));
+    tenant.reset_password(&token, "fourth".to_string()).unwrap();
+
+    // "password" is now four passwords back
+    tenant.change_password(created_user.id, "fourth".to_string(), "password".to_string()).unwrap();
+    // The policy of the tenant the change happens in applies
+    other.change_password(created_user.id, "password".to_string(), "password".to_string()).unwrap();
+    // Only the two entries the longest history of the user's tenants needs are kept
+    let history = tenet.context.repository.find_password_history(created_user.id, 100).unwrap();
+    assert_eq!(2, history.len());
+
+    // A failing change stores neither the password nor the entry
+    let stored_user = tenant.get_user_by_id(created_user.id).unwrap();
+    let entry = PasswordHistoryEntry { id: history[0].id, ..PasswordHistoryEntry::new(&stored_user) };
+    let result = tenet.context.repository.update_user_password(&User { password: "changed".to_string(), ..stored_user.clone() }, &entry, 2);
+    assert!(is_unique_violation(result));
+    assert_eq!(stored_user.password, tenant.get_user_by_id(created_user.id).unwrap().password);
+    let ids = |entries: Vec<PasswordHistoryEntry>| entries.iter().map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(ids(history), ids(tenet.context.repository.find_password_history(created_user.id, 100).unwrap()));
+
+    tenant.delete_user(created_user.id).unwrap();
+}
+
+pub(crate) fn set_user_verified_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&User { email_verified: false, ..user("someone@something.de", tenant.id) }).unwrap();
+    assert!(tenant.set_user_verified(created_user.id));
+    assert!(tenant.get_user_by_id(created_user.id).unwrap().email_verified);
+    assert!(!tenant.set_user_verified(Uuid::new_v4()));
+    tenant.update_user(&User { email_verified: false, ..created_user.clone() }).unwrap();
+    assert!(!tenant.get_user_by_id(created_user.id).unwrap().email_verified);
+}
+
+pub(crate) fn email_verification_test(tenet: Tenet) {
+    let mailer = InMemoryMailer::new();
+    let tenet = tenet.with_mailer(mailer.clone());
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    let created_user = tenant.add_user(&User { email_verified: false, ..user("someone@something.de", tenant.id) }).unwrap();
+
+    let issued = tenant.start_email_verification(created_user.id).unwrap();
+    let mails = mailer.mails();
+    assert_eq!(1, mails.len());
+    assert_eq!("someone@something.de", mails[0].to);
+    assert!(mails[0].body.contains(&issued.token));
+    assert!(!tenant.get_user_by_id(created_user.id).unwrap().email_verified);
+
+    assert!(matches!(other.confirm_email(&issued.token), Err(TenetError::InvalidTokenError)));
+    assert!(tenant.confirm_email(&issued.token).unwrap().email_verified);
+    assert!(tenant.get_user_by_id(created_user.id).unwrap().email_verified);
+    assert!(matches!(tenant.confirm_email(&issued.token), Err(TenetError::InvalidTokenError)));
+    assert!(matches!(tenant.confirm_email("unknown"), Err(TenetError::InvalidTokenError)));
+    assert!(tenant.start_email_verification(Uuid::new_v4()).unwrap_err().is_not_found());
+}
+
+pub(crate) fn email_verification_expiry_test(tenet: Tenet) {
+    let tenet = tenet.with_token_config(TokenConfig { email_verification_lifetime: chrono::Duration::zero(), ..Default::default() });
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&User { email_verified: false, ..user("someone@something.de", tenant.id) }).unwrap();
+
+    // Without a mailer the token is only returned
+    let issued = tenant.start_email_verification(created_user.id).unwrap();
+    assert!(matches!(tenant.confirm_email(&issued.token), Err(TenetError::TokenExpiredError)));
+    assert!(!tenant.get_user_by_id(created_user.id).unwrap().email_verified);
+}
+
+pub(crate) fn password_reset_test(tenet: Tenet) {
+    let mailer = InMemoryMailer::new();
+    let tenet = tenet.with_mailer(mailer.clone());
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let other = tenet.create_tenant("Other".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    other.add_member(created_user.id).unwrap();
+    let session = tenant.create_session(created_user.id).unwrap();
+    let other_session = other.create_session(created_user.id).unwrap();
+    let refresh_token = tenant.issue_refresh_token(created_user.id).unwrap();
+
+    // Unknown emails look like known ones to the caller
+    tenant.request_password_reset("unknown@something.de".to_string()).unwrap();
+    assert!(mailer.mails().is_empty());
+
+    // The token is only delivered by mail
+    let without_mailer = Tenet::in_memory().create_tenant("TenantTitle".to_string()).unwrap();
+    assert!(matches!(without_mailer.request_password_reset("someone@something.de".to_string()), Err(TenetError::NoMailerError)));
+
+    tenant.request_password_reset("someone@something.de".to_string()).unwrap();
+    let token = mailed_token(&mailer, "someone@something.de", 1);
+    assert_eq!(1, mailer.mails().len());
+    assert_eq!(64, token.len());
+
+    let verification = tenant.start_email_verification(created_user.id).unwrap();
+    assert!(matches!(tenant.reset_password(&verification.token, "new password".to_string()), Err(TenetError::InvalidTokenError)));
+    assert!(matches!(other.reset_password(&token, "new password".to_string()), Err(TenetError::InvalidTokenError)));
+
+    let updated = tenant.reset_password(&token, "new password".to_string()).unwrap();
+    assert_ne!("new password", updated.password);
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "password".to_string()).is_none());
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "new password".to_string()).is_some());
+    assert!(matches!(tenant.validate_session(&session.token), Err(TenetError::SessionRevokedError)));
+    assert!(matches!(other.validate_session(&other_session.token), Err(TenetError::SessionRevokedError)));
+    assert!(matches!(tenant.refresh(&refresh_token.token), Err(TenetError::SessionRevokedError)));
+
+    // Single use
+    assert!(matches!(tenant.reset_password(&token, "other password".to_string()), Err(TenetError::InvalidTokenError)));
+
+    // A legacy hash is replaced by an Argon2 one
+    let imported = User { password: BCRYPT_HASH.to_string(), encryption_mode: EncryptionModes::Bcrypt, ..user("imported@something.de", tenant.id) };
+    let imported = tenant.import_user(&imported).unwrap();
+    tenant.request_password_reset("imported@something.de".to_string()).unwrap();
+    let token = mailed_token(&mailer, "imported@something.de", 1);
+    let reset = tenant.reset_password(&token, "new password".to_string()).unwrap();
+    assert_eq!(EncryptionModes::Argon2, reset.encryption_mode);
+    assert_eq!(EncryptionModes::Argon2, tenant.get_user_by_id(imported.id).unwrap().encryption_mode);
+    assert!(tenant.authenticate_user("imported@something.de".to_string(), "new password".to_string()).is_some());
+}
+
+pub(crate) fn change_password_test(tenet: Tenet) {
+    let tenant = tenet.create_tenant("TenantTitle".to_string()).unwrap();
+    let created_user = tenant.add_user(&user("someone@something.de", tenant.id)).unwrap();
+    let session = tenant.create_session(created_user.id).unwrap();
+    assert_eq!(None, created_user.updated_at);
+
+    assert!(matches!(tenant.change_password(created_user.id, "wrong".to_string(), "new password".to_string()), Err(TenetError::InvalidPasswordError)));
+    let changed = tenant.change_password(created_user.id, "password".to_string(), "new password".to_string()).unwrap();
+    assert_ne!("new password", changed.password);
+    assert!(changed.updated_at.is_some());
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "new password".to_string()).is_some());
+    assert!(tenant.validate_session(&session.token).is_ok());
+
+    let reset = tenant.force_password_reset(created_user.id, "admin password".to_string()).unwrap();
+    assert!(reset.verify_password("admin password").unwrap());
+    assert!(tenant.authenticate_user("someone@something.de".to_string(), "new password".to_string()).is_none());
+    assert!(matches!(tenant.validate_session(&session.token), Err(TenetError::SessionRevokedError)));
+    assert!(tenant.force_password_reset(Uuid::new_v4(), "password".to_string()).unwrap_err().is_not_found());
+
+    // Changing a legacy password moves the user to Argon2
+    let imported = User { password: BCRYPT_HASH.to_string(), encryption_mode: EncryptionModes::Bcrypt, ..user("imported@something.de", tenant.id) };
+    let imported = tenant.import_user(&imported).unwrap();
+    let changed = tenant.change_password(imported.id, "legacy password".to_string(), "new password".to_string()).unwrap();
+    assert_eq!(EncryptionModes::Argon2, changed.encryption_mode);
+    assert_eq!(EncryptionModes::Argon2, tenant.get_user_by_id(imported.id).unwrap().encryption_mode);
+    assert!(tenant.authenticate_user("imported@something.de".to_string(), "new password".to_string()).is_some());
+}
@@ -0,0 +1,24 @@
+use chrono::Duration;
+
+
+/// Lifetimes of the single use tokens sent to users by mail, see
+/// [`crate::Tenet::with_token_config`].
+#[derive(Debug, Clone)]
+pub struct TokenConfig {
+    /// Time an invitation can be accepted after it was created.
+    pub invitation_lifetime: Duration,
+    /// Time an email verification token stays valid after it was issued.
+    pub email_verification_lifetime: Duration,
+    /// Time a password reset token stays valid after it was issued.
+    pub password_reset_lifetime: Duration
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        TokenConfig {
+            invitation_lifetime: Duration::days(7),
+            email_verification_lifetime: Duration::days(1),
+            password_reset_lifetime: Duration::hours(1)
+        }
+    }
+}
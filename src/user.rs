@@ -23,7 +23,7 @@ pub struct User {
 impl From<&DbUser> for User {
     fn from(value: &DbUser) -> Self {
         User { 
-            id: value.id, 
+            id: value.id.0, 
             username: value.email.clone(), // We do not have a concept of username at the moment, we just use email
             password: value.password.clone(), 
             encryption_mode: EncryptionModes::from_str(&value.encryption_mode).unwrap(), 
@@ -32,7 +32,7 @@ impl From<&DbUser> for User {
             full_name: value.full_name.clone(),
             created_at: value.created_at,
             updated_at: value.updated_at,
-            db_tenant_id: value.db_tenant_id,
+            db_tenant_id: value.db_tenant_id.map(uuid::Uuid::from),
         }
     }
 }
@@ -66,7 +66,7 @@ impl User {
     ///
     /// * `true` wenn das Passwort übereinstimmt, andernfalls `false`
     pub fn verify_password(&self, password: &str) -> Result<bool, TenetError> {
-        Ok(argon2::verify_encoded(&self.password, password.as_bytes())?)
+        self.encryption_mode.verify_password(&self.password, password)
     }
 }
 
@@ -0,0 +1,70 @@
+use std::{str::FromStr, fmt::Display};
+
+use chrono::{NaiveDateTime, Utc};
+
+
+/// What a [`UserToken`] may be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub enum UserTokenPurpose {
+    /// Confirms the user's email address, see [`crate::Tenant::confirm_email`]
+    EmailVerification,
+    /// Sets a new password, see [`crate::Tenant::reset_password`]
+    PasswordReset
+}
+
+impl FromStr for UserTokenPurpose {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<UserTokenPurpose, Self::Err> {
+        match input {
+            "EmailVerification" => Ok(UserTokenPurpose::EmailVerification),
+            "PasswordReset" => Ok(UserTokenPurpose::PasswordReset),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for UserTokenPurpose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+
+/// A single use token sent to a user, e.g. to verify their email address or
+/// to reset their password.
+///
+/// Only a SHA-256 hash of the token is stored.
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct UserToken {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub purpose: UserTokenPurpose,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub db_tenant_id: Option<uuid::Uuid>
+}
+
+impl UserToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().naive_utc()
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used_at.is_some()
+    }
+}
+
+
+/// A freshly created user token together with the token itself.
+///
+/// The token is not stored anywhere. It has already been mailed to the user
+/// if the [`crate::Tenet`] instance has a mailer.
+#[derive(Debug, Clone)]
+pub struct IssuedUserToken {
+    pub token: String,
+    pub user_token: UserToken
+}
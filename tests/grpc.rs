@@ -0,0 +1,221 @@
+#![cfg(feature = "grpc")]
+
+use stec_tenet::application_type::ApplicationType;
+use stec_tenet::encryption_modes::EncryptionModes;
+use stec_tenet::grpc::Service;
+use stec_tenet::grpc::user_tenant_service::user_tenant_service_client::UserTenantServiceClient;
+use stec_tenet::grpc::user_tenant_service::{
+    AuthorizationRequest, Credentials, Empty, Roles, SessionRequest, Tenant, TenantRequest, User, UserRequest, UserUpdate
+};
+use stec_tenet::role_type::RoleType;
+use stec_tenet::{Application, Role, SessionConfig, Storage, Tenet};
+use tokio::net::TcpListener;
+use tonic::Code;
+use tonic::metadata::MetadataValue;
+use tonic::service::{interceptor::InterceptedService, Interceptor};
+use tonic::transport::{Channel, Server};
+use tonic::transport::server::TcpIncoming;
+
+
+const ADMIN_TOKEN: &str = "admin-token";
+
+
+/// Starts the service on a free port and returns its address.
+async fn start_server(service: Service) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(Server::builder()
+        .add_service(service.into_server())
+        .serve_with_incoming(TcpIncoming::from(listener)));
+
+    format!("http://{}", address)
+}
+
+/// Connects a client that sends `token` as bearer token with every call.
+async fn connect(address: String, token: &str) -> UserTenantServiceClient<InterceptedService<Channel, impl Interceptor>> {
+    let channel = Channel::from_shared(address).unwrap().connect().await.unwrap();
+    let authorization: MetadataValue<_> = format!("Bearer {}", token).parse().unwrap();
+    UserTenantServiceClient::with_interceptor(channel, move |mut request: tonic::Request<()>| {
+        request.metadata_mut().insert("authorization", authorization.clone());
+        Ok(request)
+    })
+}
+
+
+#[tokio::test]
+async fn tenant_and_user_crud_test() {
+    let mut client = connect(start_server(Service::new(Tenet::in_memory(), ADMIN_TOKEN)).await, ADMIN_TOKEN).await;
+
+    let tenant = client.create_tenant(Tenant { id: String::new(), title: "Tenant".to_string() }).await.unwrap().into_inner();
+    assert_eq!("Tenant", tenant.title);
+    assert_eq!(1, client.get_all_tenants(Empty {}).await.unwrap().into_inner().tenants.len());
+
+    let updated = client.update_tenant(Tenant { id: tenant.id.clone(), title: "Renamed".to_string() }).await.unwrap().into_inner();
+    assert_eq!("Renamed", updated.title);
+
+    let user = client.create_user(User {
+        id: String::new(),
+        email: "someone@something.de".to_string(),
+        email_verified: false,
+        password: "password".to_string(),
+        full_name: "Danny Crane".to_string(),
+        tenant_id: tenant.id.clone()
+    }).await.unwrap().into_inner();
+    assert!(user.password.is_empty());
+
+    let user_request = UserRequest { id: user.id.clone(), tenant_id: tenant.id.clone() };
+    let fetched = client.get_user(user_request.clone()).await.unwrap().into_inner();
+    assert_eq!("Danny Crane", fetched.full_name);
+
+    let updated_user = client.update_user(UserUpdate {
+        id: user.id.clone(),
+        tenant_id: tenant.id.clone(),
+        full_name: Some("Denny Crane".to_string()),
+        email_verified: Some(true),
+        ..Default::default()
+    }).await.unwrap().into_inner();
+    assert_eq!("Denny Crane", updated_user.full_name);
+    assert!(updated_user.email_verified);
+    // Fields that are not set keep their value
+    assert_eq!("someone@something.de", updated_user.email);
+
+    let by_user = client.get_tenant_by_user(User { email: "someone@something.de".to_string(), ..Default::default() }).await.unwrap().into_inner();
+    assert_eq!(tenant.id, by_user.id);
+    assert_eq!(1, client.get_users_by_tenant(tenant.clone()).await.unwrap().into_inner().users.len());
+    assert_eq!(1, client.get_all_users(Empty {}).await.unwrap().into_inner().users.len());
+
+    assert!(client.delete_user(user_request.clone()).await.unwrap().into_inner().success);
+    assert_eq!(Code::NotFound, client.get_user(user_request).await.unwrap_err().code());
+
+    assert!(client.delete_tenant(TenantRequest { id: tenant.id.clone() }).await.unwrap().into_inner().success);
+    assert_eq!(Code::NotFound, client.get_tenant(TenantRequest { id: tenant.id }).await.unwrap_err().code());
+    assert_eq!(Code::InvalidArgument, client.get_tenant(TenantRequest { id: "no-uuid".to_string() }).await.unwrap_err().code());
+}
+
+
+#[tokio::test]
+async fn authentication_and_authorization_test() {
+    let tenet = Tenet::in_memory();
+    let tenant = tenet.create_tenant("Tenant".to_string()).unwrap();
+    let storage = tenant.add_storage(&Storage::new_json_file("some_path", tenant.id)).unwrap();
+    let application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+    let other_application = tenant.add_application(&Application::new(ApplicationType::Shop, storage.id, tenant.id)).unwrap();
+
+    let mut client = connect(start_server(Service::new(tenet, ADMIN_TOKEN)).await, ADMIN_TOKEN).await;
+    let user = client.create_user(User {
+        email: "someone@something.de".to_string(),
+        password: "password".to_string(),
+        full_name: "Danny Crane".to_string(),
+        tenant_id: tenant.id.to_string(),
+        ..Default::default()
+    }).await.unwrap().into_inner();
+    let user_id = uuid::Uuid::parse_str(&user.id).unwrap();
+
+    let invalid_request = SessionRequest { token: "invalid".to_string(), tenant_id: tenant.id.to_string() };
+    assert!(!client.is_authenticated(invalid_request).await.unwrap().into_inner().status);
+
+    let failed = client.authenticate(Credentials { email: "someone@something.de".to_string(), password: "wrong".to_string() }).await.unwrap().into_inner();
+    assert!(!failed.success);
+    assert!(failed.token.is_empty());
+
+    let succeeded = client.authenticate(Credentials { email: "someone@something.de".to_string(), password: "password".to_string() }).await.unwrap().into_inner();
+    assert!(succeeded.success);
+    assert_eq!(user.id, succeeded.id);
+    let session_request = SessionRequest { token: succeeded.token.clone(), tenant_id: tenant.id.to_string() };
+    let authentication_status = client.is_authenticated(session_request.clone()).await.unwrap().into_inner();
+    assert!(authentication_status.status);
+    assert_eq!(user.id, authentication_status.user_id);
+
+    // Sessions are stored in the repository, so they can be checked and revoked through the Tenant as well
+    let session = tenant.validate_session(&succeeded.token).unwrap();
+    tenant.revoke_session(session.id).unwrap();
+    assert!(!client.is_authenticated(session_request).await.unwrap().into_inner().status);
+
+    let revoked_request = AuthorizationRequest {
+        tenant_id: tenant.id.to_string(),
+        application_id: application.id.to_string(),
+        token: succeeded.token
+    };
+    assert_eq!(Code::Unauthenticated, client.is_authorized(revoked_request).await.unwrap_err().code());
+
+    let reply = client.authenticate(Credentials { email: "someone@something.de".to_string(), password: "password".to_string() }).await.unwrap().into_inner();
+    let authorization_request = AuthorizationRequest {
+        tenant_id: tenant.id.to_string(),
+        application_id: application.id.to_string(),
+        token: reply.token
+    };
+    assert_eq!(Roles::None as i32, client.is_authorized(authorization_request.clone()).await.unwrap().into_inner().role);
+
+    tenant.add_role(&Role::new(RoleType::User, user_id, application.id, tenant.id)).unwrap();
+    tenant.add_role(&Role::new(RoleType::Administrator, user_id, other_application.id, tenant.id)).unwrap();
+    assert_eq!(Roles::User as i32, client.is_authorized(authorization_request.clone()).await.unwrap().into_inner().role);
+
+    tenant.add_role(&Role::new(RoleType::Administrator, user_id, application.id, tenant.id)).unwrap();
+    assert_eq!(Roles::Administrator as i32, client.is_authorized(authorization_request).await.unwrap().into_inner().role);
+}
+
+
+#[tokio::test]
+async fn session_expiry_test() {
+    let tenet = Tenet::in_memory().with_session_config(SessionConfig { lifetime: chrono::Duration::zero(), ..Default::default() });
+    let tenant = tenet.create_tenant("Tenant".to_string()).unwrap();
+
+    let mut client = connect(start_server(Service::new(tenet, ADMIN_TOKEN)).await, ADMIN_TOKEN).await;
+    let user = client.create_user(User {
+        email: "someone@something.de".to_string(),
+        password: "password".to_string(),
+        tenant_id: tenant.id.to_string(),
+        ..Default::default()
+    }).await.unwrap().into_inner();
+
+    let reply = client.authenticate(Credentials { email: "someone@something.de".to_string(), password: "password".to_string() }).await.unwrap().into_inner();
+    assert!(reply.success);
+    assert_eq!(user.id, reply.id);
+    assert!(!client.is_authenticated(SessionRequest { token: reply.token, tenant_id: tenant.id.to_string() }).await.unwrap().into_inner().status);
+}
+
+
+#[tokio::test]
+async fn admin_token_test() {
+    let tenet = Tenet::in_memory();
+    let tenant = tenet.create_tenant("Tenant".to_string()).unwrap();
+    tenant.add_user(&stec_tenet::User::new(
+        "someone@something.de".to_string(),
+        "Danny Crane".to_string(),
+        "password".to_string(),
+        EncryptionModes::Argon2,
+        "someone@something.de".to_string(),
+        true,
+        tenant.id)).unwrap();
+    let address = start_server(Service::new(tenet, ADMIN_TOKEN)).await;
+
+    let mut anonymous = UserTenantServiceClient::connect(address.clone()).await.unwrap();
+    assert_eq!(Code::Unauthenticated, anonymous.get_all_tenants(Empty {}).await.unwrap_err().code());
+    assert_eq!(Code::Unauthenticated, anonymous.create_tenant(Tenant { id: String::new(), title: "Other".to_string() }).await.unwrap_err().code());
+
+    let mut client = connect(address.clone(), "wrong").await;
+    assert_eq!(Code::Unauthenticated, client.delete_tenant(TenantRequest { id: tenant.id.to_string() }).await.unwrap_err().code());
+    assert_eq!(Code::Unauthenticated, client.create_user(User {
+        email: "someone.else@something.de".to_string(),
+        password: "password".to_string(),
+        tenant_id: tenant.id.to_string(),
+        ..Default::default()
+    }).await.unwrap_err().code());
+
+    // Authentication does not need the admin token
+    let reply = anonymous.authenticate(Credentials { email: "someone@something.de".to_string(), password: "password".to_string() }).await.unwrap().into_inner();
+    assert!(reply.success);
+
+    let mut admin = connect(address, ADMIN_TOKEN).await;
+    assert_eq!(1, admin.get_all_tenants(Empty {}).await.unwrap().into_inner().tenants.len());
+}
+
+
+#[tokio::test]
+async fn empty_admin_token_locks_admin_calls_test() {
+    let address = start_server(Service::new(Tenet::in_memory(), "")).await;
+
+    let mut client = connect(address, "").await;
+    assert_eq!(Code::Unauthenticated, client.get_all_tenants(Empty {}).await.unwrap_err().code());
+}
@@ -0,0 +1,219 @@
+#![cfg(feature = "server")]
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use stec_tenet::Tenet;
+use stec_tenet::server::router;
+use tower::ServiceExt;
+
+
+const ADMIN_TOKEN: &str = "admin-token";
+
+
+async fn send(app: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    send_with_token(app, method, uri, body, Some(ADMIN_TOKEN)).await
+}
+
+async fn send_with_token(app: &Router, method: Method, uri: &str, body: Option<Value>, token: Option<&str>) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {}", token));
+    }
+    let request = request
+        .body(body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let value = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string()));
+    (status, value)
+}
+
+
+#[tokio::test]
+async fn tenant_crud_test() {
+    let app = router(Tenet::in_memory(), ADMIN_TOKEN);
+
+    let (status, tenant) = send(&app, Method::POST, "/tenants", Some(json!({ "title": "Tenant" }))).await;
+    assert_eq!(StatusCode::CREATED, status);
+    let tenant_id = tenant["id"].as_str().unwrap().to_string();
+
+    let (status, tenants) = send(&app, Method::GET, "/tenants", None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(1, tenants.as_array().unwrap().len());
+
+    let (status, updated) = send(&app, Method::PUT, &format!("/{}", tenant_id), Some(json!({ "title": "Renamed" }))).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!("Renamed", updated["title"]);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/{}", tenant_id), None).await;
+    assert_eq!(StatusCode::NO_CONTENT, status);
+
+    let (status, error) = send(&app, Method::GET, &format!("/{}", tenant_id), None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+    assert!(error["error"].is_string());
+
+    let (status, _) = send(&app, Method::GET, "/not-a-uuid", None).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+}
+
+
+#[tokio::test]
+async fn user_application_and_authentication_test() {
+    let app = router(Tenet::in_memory(), ADMIN_TOKEN);
+    let (_, tenant) = send(&app, Method::POST, "/tenants", Some(json!({ "title": "Tenant" }))).await;
+    let tenant_id = tenant["id"].as_str().unwrap().to_string();
+
+    let (status, user) = send(&app, Method::POST, &format!("/{}/users", tenant_id), Some(json!({
+        "email": "someone@something.de",
+        "full_name": "Danny Crane",
+        "password": "password"
+    }))).await;
+    assert_eq!(StatusCode::CREATED, status);
+    assert_eq!("", user["password"]);
+    let user_id = user["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(&app, Method::POST, &format!("/{}/users", tenant_id), Some(json!({
+        "email": "someone@something.de",
+        "full_name": "Denny Crane",
+        "password": "password"
+    }))).await;
+    assert_eq!(StatusCode::CONFLICT, status);
+
+    let (status, updated) = send(&app, Method::PUT, &format!("/{}/users/{}", tenant_id, user_id), Some(json!({ "email_verified": true }))).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(true, updated["email_verified"]);
+    assert_eq!("Danny Crane", updated["full_name"]);
+
+    let (status, storage) = send(&app, Method::POST, &format!("/{}/storages", tenant_id), Some(json!({
+        "storage_type": "JsonFile",
+        "path": "some_path"
+    }))).await;
+    assert_eq!(StatusCode::CREATED, status);
+
+    let (status, application) = send(&app, Method::POST, &format!("/{}/applications", tenant_id), Some(json!({
+        "application_type": "Shop",
+        "storage_id": storage["id"]
+    }))).await;
+    assert_eq!(StatusCode::CREATED, status);
+    let application_id = application["id"].as_str().unwrap().to_string();
+
+    let (status, fetched) = send(&app, Method::GET, &format!("/{}/{}", tenant_id, application_id), None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(application, fetched);
+
+    let (status, _) = send(&app, Method::POST, &format!("/{}/roles", tenant_id), Some(json!({
+        "role_type": "Administrator",
+        "user_id": user_id,
+        "application_id": application_id
+    }))).await;
+    assert_eq!(StatusCode::CREATED, status);
+
+    let (status, roles) = send(&app, Method::GET, &format!("/{}/users/{}/roles", tenant_id, user_id), None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(1, roles.as_array().unwrap().len());
+
+    let (status, _) = send(&app, Method::POST, &format!("/{}/authenticate", tenant_id), Some(json!({
+        "username": "someone@something.de",
+        "password": "wrong"
+    }))).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+    let (status, authentication) = send(&app, Method::POST, &format!("/{}/{}/authenticate", tenant_id, application_id), Some(json!({
+        "username": "someone@something.de",
+        "password": "password"
+    }))).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(user_id, authentication["user"]["id"]);
+    assert_eq!("Administrator", authentication["roles"][0]["role_type"]);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/{}/users/{}", tenant_id, user_id), None).await;
+    assert_eq!(StatusCode::CONFLICT, status);
+}
+
+
+#[tokio::test]
+async fn membership_and_login_test() {
+    let app = router(Tenet::in_memory(), ADMIN_TOKEN);
+    let (_, home) = send(&app, Method::POST, "/tenants", Some(json!({ "title": "Home" }))).await;
+    let (_, customer) = send(&app, Method::POST, "/tenants", Some(json!({ "title": "Customer" }))).await;
+    let home_id = home["id"].as_str().unwrap().to_string();
+    let customer_id = customer["id"].as_str().unwrap().to_string();
+
+    let (_, user) = send(&app, Method::POST, &format!("/{}/users", home_id), Some(json!({
+        "email": "consultant@something.de",
+        "full_name": "Danny Crane",
+        "password": "password"
+    }))).await;
+    let user_id = user["id"].as_str().unwrap().to_string();
+
+    let (status, member) = send(&app, Method::PUT, &format!("/{}/members/{}", customer_id, user_id), None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!("", member["password"]);
+
+    let (status, login) = send(&app, Method::POST, "/login", Some(json!({
+        "username": "consultant@something.de",
+        "password": "password"
+    }))).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(user_id, login["user"]["id"]);
+    assert_eq!(vec!["Customer", "Home"], login["tenants"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect::<Vec<_>>());
+
+    let (status, _) = send(&app, Method::POST, &format!("/{}/authenticate", customer_id), Some(json!({
+        "username": "consultant@something.de",
+        "password": "password"
+    }))).await;
+    assert_eq!(StatusCode::OK, status);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/{}/members/{}", customer_id, user_id), None).await;
+    assert_eq!(StatusCode::NO_CONTENT, status);
+    let (status, _) = send(&app, Method::GET, &format!("/{}/users/{}", customer_id, user_id), None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+
+    let (status, _) = send(&app, Method::POST, "/login", Some(json!({
+        "username": "consultant@something.de",
+        "password": "wrong"
+    }))).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+}
+
+
+#[tokio::test]
+async fn admin_token_test() {
+    let app = router(Tenet::in_memory(), ADMIN_TOKEN);
+
+    let (status, error) = send_with_token(&app, Method::GET, "/tenants", None, None).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+    assert!(error["error"].is_string());
+    let (status, _) = send_with_token(&app, Method::POST, "/tenants", Some(json!({ "title": "Tenant" })), Some("wrong")).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+    let (_, tenant) = send(&app, Method::POST, "/tenants", Some(json!({ "title": "Tenant" }))).await;
+    let tenant_id = tenant["id"].as_str().unwrap().to_string();
+    send(&app, Method::POST, &format!("/{}/users", tenant_id), Some(json!({
+        "email": "someone@something.de",
+        "full_name": "Danny Crane",
+        "password": "password"
+    }))).await;
+    let (status, _) = send_with_token(&app, Method::GET, &format!("/{}/users", tenant_id), None, None).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+    // Signing in needs no admin token
+    let credentials = json!({ "username": "someone@something.de", "password": "password" });
+    let (status, _) = send_with_token(&app, Method::POST, "/login", Some(credentials.clone()), None).await;
+    assert_eq!(StatusCode::OK, status);
+    let (status, _) = send_with_token(&app, Method::POST, &format!("/{}/authenticate", tenant_id), Some(credentials), None).await;
+    assert_eq!(StatusCode::OK, status);
+
+    // Without a configured token, the admin routes stay closed
+    let app = router(Tenet::in_memory(), "");
+    let (status, _) = send_with_token(&app, Method::GET, "/tenants", None, Some("")).await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+}